@@ -0,0 +1,103 @@
+//! Longitudinal acceleration estimate
+//!
+//! Differentiates timestamped vehicle velocity samples and smooths the result
+//! with a first order low-pass filter, for traction control and driver
+//! feedback.
+
+use crate::wavesculptor::Status;
+
+/// Acceleration estimator configuration
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Filter time constant in milliseconds
+    pub time_constant_ms: u32,
+    /// Samples closer together than this are skipped, in milliseconds
+    pub min_interval_ms: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            time_constant_ms: 500,
+            min_interval_ms: 50,
+        }
+    }
+}
+
+/// Acceleration estimator
+#[derive(Debug, Clone, Copy)]
+pub struct AccelerationEstimator {
+    config: Config,
+    last_velocity: Option<f32>,
+    last_update_ms: u32,
+    acceleration: Option<f32>,
+}
+
+impl AccelerationEstimator {
+    /// Create a new estimator with no samples.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            last_velocity: None,
+            last_update_ms: 0,
+            acceleration: None,
+        }
+    }
+
+    /// Smoothed acceleration in m/s², once two samples have been seen.
+    pub fn acceleration(&self) -> Option<f32> {
+        self.acceleration
+    }
+
+    /// Forget all samples.
+    pub fn reset(&mut self) {
+        self.last_velocity = None;
+        self.acceleration = None;
+    }
+
+    /// Update from the latest WaveSculptor status.
+    pub fn update(&mut self, now_ms: u32, status: &Status) -> Option<f32> {
+        if let Some(velocity) = status.vehicle_velocity {
+            self.update_velocity(now_ms, velocity);
+        }
+
+        self.acceleration
+    }
+
+    /// Update from a vehicle velocity sample in m/s.
+    ///
+    /// Non-finite samples are ignored.
+    pub fn update_velocity(&mut self, now_ms: u32, velocity: f32) -> Option<f32> {
+        if !velocity.is_finite() {
+            return self.acceleration;
+        }
+
+        let Some(last_velocity) = self.last_velocity else {
+            self.last_velocity = Some(velocity);
+            self.last_update_ms = now_ms;
+            return self.acceleration;
+        };
+
+        let elapsed_ms = now_ms.wrapping_sub(self.last_update_ms);
+        if elapsed_ms < self.config.min_interval_ms.max(1) {
+            return self.acceleration;
+        }
+
+        let raw = (velocity - last_velocity) * 1000.0 / elapsed_ms as f32;
+
+        self.acceleration = Some(match self.acceleration {
+            Some(acceleration) => {
+                let alpha =
+                    elapsed_ms as f32 / (self.config.time_constant_ms as f32 + elapsed_ms as f32);
+
+                acceleration + alpha * (raw - acceleration)
+            }
+            None => raw,
+        });
+
+        self.last_velocity = Some(velocity);
+        self.last_update_ms = now_ms;
+
+        self.acceleration
+    }
+}
@@ -0,0 +1,200 @@
+//! Driver alerts
+//!
+//! Prioritises the alerts raised by the alarm subsystem for a buzzer or
+//! chime: faults before limits before information. Alerts must stay raised
+//! for the debounce time before sounding, so brief glitches stay quiet, and
+//! sound again at their priority's repeat interval until cleared.
+//!
+//! Alerts are identified by a caller chosen key, such as a
+//! [`Signal`](crate::thresholds::Signal) or fault flag.
+
+/// Alert priority
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    /// Information
+    Info,
+    /// Operating limit reached
+    Limit,
+    /// Fault
+    Fault,
+}
+
+/// Alert queue configuration
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Time an alert must stay raised before sounding in milliseconds
+    pub debounce_ms: u32,
+    /// Repeat interval of faults in milliseconds, zero to sound once
+    pub fault_repeat_ms: u32,
+    /// Repeat interval of limits in milliseconds, zero to sound once
+    pub limit_repeat_ms: u32,
+    /// Repeat interval of information in milliseconds, zero to sound once
+    pub info_repeat_ms: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            debounce_ms: 200,
+            fault_repeat_ms: 2000,
+            limit_repeat_ms: 10_000,
+            info_repeat_ms: 0,
+        }
+    }
+}
+
+impl Config {
+    fn repeat_ms(&self, priority: Priority) -> u32 {
+        match priority {
+            Priority::Fault => self.fault_repeat_ms,
+            Priority::Limit => self.limit_repeat_ms,
+            Priority::Info => self.info_repeat_ms,
+        }
+    }
+}
+
+/// Alert to sound
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Alert<K> {
+    /// Alert key
+    pub key: K,
+    /// Priority
+    pub priority: Priority,
+}
+
+/// Raised alert
+#[derive(Debug, Clone, Copy)]
+struct Entry<K> {
+    alert: Alert<K>,
+    raised_ms: u32,
+    sounded_ms: Option<u32>,
+}
+
+/// Prioritised alert queue holding up to `N` raised alerts
+#[derive(Debug, Clone, Copy)]
+pub struct AlertQueue<K, const N: usize> {
+    config: Config,
+    entries: [Option<Entry<K>>; N],
+}
+
+impl<K: Copy + PartialEq, const N: usize> AlertQueue<K, N> {
+    /// Create a new, empty, queue.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            entries: [None; N],
+        }
+    }
+
+    /// Raise an alert, or change the priority of one already raised.
+    ///
+    /// When the queue is full the lowest priority alert is dropped to make
+    /// room, and `false` is returned if the new alert is the lowest.
+    pub fn raise(&mut self, now_ms: u32, key: K, priority: Priority) -> bool {
+        let alert = Alert { key, priority };
+
+        if let Some(entry) = self
+            .entries
+            .iter_mut()
+            .flatten()
+            .find(|entry| entry.alert.key == key)
+        {
+            entry.alert.priority = priority;
+            return true;
+        }
+
+        let slot = match self.entries.iter().position(Option::is_none) {
+            Some(slot) => slot,
+            None => {
+                let lowest = self
+                    .entries
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(slot, entry)| Some((slot, entry.as_ref()?.alert.priority)))
+                    .min_by_key(|(_, priority)| *priority);
+
+                match lowest {
+                    Some((slot, lowest)) if lowest < priority => {
+                        log_warn!("alert queue full, dropping {:?} alert", lowest);
+                        slot
+                    }
+                    _ => {
+                        log_warn!("alert queue full, ignoring {:?} alert", priority);
+                        return false;
+                    }
+                }
+            }
+        };
+
+        self.entries[slot] = Some(Entry {
+            alert,
+            raised_ms: now_ms,
+            sounded_ms: None,
+        });
+
+        true
+    }
+
+    /// Clear an alert.
+    pub fn clear(&mut self, key: K) {
+        for entry in self.entries.iter_mut() {
+            if entry.is_some_and(|entry| entry.alert.key == key) {
+                *entry = None;
+            }
+        }
+    }
+
+    /// Raise or clear an alert depending on `active`.
+    pub fn set(&mut self, now_ms: u32, key: K, priority: Priority, active: bool) {
+        if active {
+            self.raise(now_ms, key, priority);
+        } else {
+            self.clear(key);
+        }
+    }
+
+    /// Alerts raised for at least the debounce time.
+    pub fn active(&self, now_ms: u32) -> impl Iterator<Item = Alert<K>> + '_ {
+        self.entries
+            .iter()
+            .flatten()
+            .filter(move |entry| self.debounced(entry, now_ms))
+            .map(|entry| entry.alert)
+    }
+
+    /// Highest priority alert raised for at least the debounce time.
+    pub fn highest(&self, now_ms: u32) -> Option<Alert<K>> {
+        self.active(now_ms).max_by_key(|alert| alert.priority)
+    }
+
+    /// Alert to sound now, if any.
+    ///
+    /// Picks the highest priority alert that is debounced and has not
+    /// sounded within its repeat interval, oldest first, and marks it as
+    /// sounded.
+    pub fn poll(&mut self, now_ms: u32) -> Option<Alert<K>> {
+        let config = self.config;
+
+        let entry = self
+            .entries
+            .iter_mut()
+            .flatten()
+            .filter(|entry| now_ms.wrapping_sub(entry.raised_ms) >= config.debounce_ms)
+            .filter(|entry| match entry.sounded_ms {
+                None => true,
+                Some(sounded_ms) => {
+                    let repeat_ms = config.repeat_ms(entry.alert.priority);
+                    repeat_ms != 0 && now_ms.wrapping_sub(sounded_ms) >= repeat_ms
+                }
+            })
+            .max_by_key(|entry| (entry.alert.priority, now_ms.wrapping_sub(entry.raised_ms)))?;
+
+        entry.sounded_ms = Some(now_ms);
+
+        Some(entry.alert)
+    }
+
+    fn debounced(&self, entry: &Entry<K>, now_ms: u32) -> bool {
+        now_ms.wrapping_sub(entry.raised_ms) >= self.config.debounce_ms
+    }
+}
@@ -0,0 +1,121 @@
+//! Battery-limit-aware drive arbitration
+//!
+//! Caps the bus current and regenerative braking current commanded to the
+//! motor controller using the BMU's charge control broadcast, so the motor
+//! never draws or regenerates more than the pack can safely handle.
+//!
+//! Each limit is scaled linearly from full as the headroom reported by the
+//! BMU falls from the configured band to zero.
+
+use crate::bms::Status;
+use crate::driver_controls::DriveCommand;
+
+/// Arbiter configuration
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Maximum bus current in percent
+    pub max_bus_current_percent: f32,
+    /// Maximum regen current in percent
+    pub max_regen_percent: f32,
+    /// Discharging cell voltage headroom below which drive is limited, in
+    /// millivolts
+    pub discharge_band_mv: i16,
+    /// Charging cell voltage headroom below which regen is limited, in
+    /// millivolts
+    pub charge_band_mv: i16,
+    /// Cell temperature headroom below which drive and regen are limited, in
+    /// 1/10th degrees celcius
+    pub temperature_band: i16,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            max_bus_current_percent: 100.0,
+            max_regen_percent: 100.0,
+            discharge_band_mv: 200,
+            charge_band_mv: 100,
+            temperature_band: 50,
+        }
+    }
+}
+
+/// Current limits derived from the pack state
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Limits {
+    /// Allowed bus current in percent
+    pub bus_current_percent: f32,
+    /// Allowed regen current in percent
+    pub regen_percent: f32,
+}
+
+/// Battery-limit-aware drive arbiter
+#[derive(Debug, Clone, Copy)]
+pub struct Arbiter {
+    config: Config,
+}
+
+impl Arbiter {
+    /// Create a new arbiter.
+    pub fn new(config: Config) -> Self {
+        Self { config }
+    }
+
+    /// Current limits for the given BMU status.
+    ///
+    /// Missing charge control data results in zero limits.
+    pub fn limits<const CMUS: usize>(&self, status: &Status<CMUS>) -> Limits {
+        let temperature = headroom(status.cell_temperature_margin, self.config.temperature_band);
+        let discharge = headroom(
+            status.discharging_cell_voltage_error,
+            self.config.discharge_band_mv,
+        );
+        let charge = headroom(
+            status.charging_cell_voltage_error,
+            self.config.charge_band_mv,
+        );
+
+        Limits {
+            bus_current_percent: self.config.max_bus_current_percent * discharge * temperature,
+            regen_percent: self.config.max_regen_percent * charge * temperature,
+        }
+    }
+
+    /// Cap a requested bus current in percent.
+    pub fn bus_current_percent<const CMUS: usize>(
+        &self,
+        requested: f32,
+        status: &Status<CMUS>,
+    ) -> f32 {
+        requested.min(self.limits(status).bus_current_percent)
+    }
+
+    /// Cap the current of a regenerative braking command.
+    ///
+    /// Driving commands are limited through the bus current instead and pass
+    /// through untouched.
+    pub fn apply<const CMUS: usize>(
+        &self,
+        command: DriveCommand,
+        status: &Status<CMUS>,
+    ) -> DriveCommand {
+        if command.velocity_rpm != 0.0 {
+            return command;
+        }
+
+        DriveCommand::regen(
+            command
+                .current_percent
+                .min(self.limits(status).regen_percent),
+        )
+    }
+}
+
+fn headroom(value: Option<i16>, band: i16) -> f32 {
+    match value {
+        Some(value) if band > 0 => (value as f32 / band as f32).clamp(0.0, 1.0),
+        Some(value) if value > 0 => 1.0,
+        Some(_) => 0.0,
+        None => 0.0,
+    }
+}
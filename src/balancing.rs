@@ -0,0 +1,138 @@
+//! Cell balancing activity
+//!
+//! Tracks which cells are being bypassed for balancing and the fraction of
+//! time each spends bypassed, so pack maintainers can spot chronically high
+//! cells.
+//!
+//! CMUs don't broadcast their bypass state, so it is inferred the same way
+//! the BMU decides it: a cell is bypassed once its voltage rises above the
+//! BMU's rising balance threshold, until it falls below the falling
+//! threshold.
+
+use crate::bms::{Cell, Status, CELLS_PER_CMU, CMU_CAPACITY_DEFAULT};
+
+/// Cell balancing tracker
+#[derive(Debug, Clone, Copy)]
+pub struct BalanceTracker<const CMUS: usize = CMU_CAPACITY_DEFAULT> {
+    bypassed: [u8; CMUS],
+    bypassed_ms: [[u32; CELLS_PER_CMU]; CMUS],
+    tracked_ms: u32,
+    last_ms: Option<u32>,
+}
+
+impl<const CMUS: usize> Default for BalanceTracker<CMUS> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const CMUS: usize> BalanceTracker<CMUS> {
+    /// Create a new tracker with no cells bypassed.
+    pub fn new() -> Self {
+        Self {
+            bypassed: [0; CMUS],
+            bypassed_ms: [[0; CELLS_PER_CMU]; CMUS],
+            tracked_ms: 0,
+            last_ms: None,
+        }
+    }
+
+    /// Forget the accumulated balancing time, keeping the bypass state.
+    pub fn reset(&mut self) {
+        self.bypassed_ms = [[0; CELLS_PER_CMU]; CMUS];
+        self.tracked_ms = 0;
+    }
+
+    /// Is the cell being bypassed?
+    pub fn is_bypassed(&self, cell: Cell) -> bool {
+        self.bypassed
+            .get(cell.cmu as usize)
+            .is_some_and(|bypassed| {
+                (cell.number as usize) < CELLS_PER_CMU && bypassed & 1 << cell.number != 0
+            })
+    }
+
+    /// Number of cells being bypassed.
+    pub fn bypassed_count(&self) -> u32 {
+        self.bypassed
+            .iter()
+            .map(|bypassed| bypassed.count_ones())
+            .sum()
+    }
+
+    /// Cells being bypassed.
+    pub fn bypassed(&self) -> impl Iterator<Item = Cell> + '_ {
+        (0..CMUS as u8)
+            .flat_map(|cmu| (0..CELLS_PER_CMU as u8).map(move |number| Cell { cmu, number }))
+            .filter(|cell| self.is_bypassed(*cell))
+    }
+
+    /// Fraction of the tracked time the cell has been bypassed.
+    pub fn duty(&self, cell: Cell) -> f32 {
+        let Some(bypassed_ms) = self
+            .bypassed_ms
+            .get(cell.cmu as usize)
+            .and_then(|cells| cells.get(cell.number as usize))
+        else {
+            return 0.0;
+        };
+
+        if self.tracked_ms == 0 {
+            return 0.0;
+        }
+
+        *bypassed_ms as f32 / self.tracked_ms as f32
+    }
+
+    /// Update from the latest BMU status and return the number of cells
+    /// being bypassed.
+    ///
+    /// Time since the previous update is credited to the cells bypassed
+    /// then. The bypass state is held while the balance thresholds are
+    /// unknown.
+    pub fn update(&mut self, now_ms: u32, status: &Status<CMUS>) -> u32 {
+        if let Some(last_ms) = self.last_ms {
+            let elapsed = now_ms.wrapping_sub(last_ms);
+            self.tracked_ms = self.tracked_ms.saturating_add(elapsed);
+
+            for (bypassed, cells) in self.bypassed.iter().zip(self.bypassed_ms.iter_mut()) {
+                for (number, ms) in cells.iter_mut().enumerate() {
+                    if bypassed & 1 << number != 0 {
+                        *ms = ms.saturating_add(elapsed);
+                    }
+                }
+            }
+        }
+
+        self.last_ms = Some(now_ms);
+
+        let (Some(rising), Some(falling)) = (
+            status.balance_threshold_rising,
+            status.balance_threshold_falling,
+        ) else {
+            return self.bypassed_count();
+        };
+
+        for (cmu, cmu_status) in status.cmu_status.iter().enumerate() {
+            let Some(cmu_status) = cmu_status else {
+                continue;
+            };
+
+            for (number, voltage) in cmu_status.cell_voltage.iter().enumerate() {
+                if cmu_status.cell_voltage_received & 1 << number == 0 {
+                    continue;
+                }
+
+                let voltage = i32::from(*voltage);
+
+                if voltage > i32::from(rising) {
+                    self.bypassed[cmu] |= 1 << number;
+                } else if voltage < i32::from(falling) {
+                    self.bypassed[cmu] &= !(1 << number);
+                }
+            }
+        }
+
+        self.bypassed_count()
+    }
+}
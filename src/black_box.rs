@@ -0,0 +1,305 @@
+//! Black-box logger
+//!
+//! Records periodic snapshots of the signals, the active alarms and any
+//! frame that triggered a fault into a ring of fixed-size records in
+//! non-volatile storage such as FRAM, flash or EEPROM. After an incident the
+//! records are read back oldest first to reconstruct the lead-up.
+//!
+//! Each record is [`RECORD_LEN`] bytes, little-endian:
+//!
+//! ```text
+//! offset  size  field
+//!      0     4  sequence number
+//!      4     4  timestamp in milliseconds
+//!      8     4  alarms, application defined bits
+//!     12     4  signal mask, bit n set for the nth signal in `Signal::ALL`
+//!     16  4*18  f32 value of every signal, in `Signal::ALL` order
+//!     88     2  fault frame identifier, 0xFFFF if none
+//!     90     1  fault frame length
+//!     91     1  reserved
+//!     92     8  fault frame data
+//!    100     2  CRC-16/CCITT-FALSE over all preceding bytes
+//! ```
+//!
+//! Records failing their CRC, such as erased or torn writes, are skipped.
+
+use crate::telemetry::{crc16, SignalSet, SIGNAL_COUNT};
+use crate::thresholds::{Measurements, Signal};
+
+/// Offset of the fault frame in a record
+const FRAME_OFFSET: usize = 16 + 4 * SIGNAL_COUNT;
+
+/// Record length in bytes
+pub const RECORD_LEN: usize = FRAME_OFFSET + 12 + 2;
+
+/// Fault frame identifier marking no frame
+const NO_FRAME: u16 = 0xFFFF;
+
+/// Non-volatile storage
+pub trait Storage {
+    /// Storage size in bytes.
+    fn capacity(&self) -> u32;
+
+    /// Read `buf.len()` bytes starting at `offset`.
+    fn read(&mut self, offset: u32, buf: &mut [u8]) -> Result<(), &'static str>;
+
+    /// Write `data` starting at `offset`.
+    ///
+    /// Flash backed storage is expected to handle erasing itself.
+    fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), &'static str>;
+}
+
+/// Raw CAN frame recorded alongside a fault
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RawFrame {
+    /// Standard identifier
+    pub id: u16,
+    /// Data length in bytes
+    pub len: u8,
+    /// Frame data, zero padded
+    pub data: [u8; 8],
+}
+
+impl RawFrame {
+    /// Raw copy of a standard data frame.
+    pub fn from_frame(frame: &bxcan::Frame) -> Option<Self> {
+        let bxcan::Id::Standard(id) = frame.id() else {
+            return None;
+        };
+
+        let bytes = frame.data()?;
+        let mut data = [0; 8];
+        data[..bytes.len()].copy_from_slice(bytes);
+
+        Some(Self {
+            id: id.as_raw(),
+            len: bytes.len() as u8,
+            data,
+        })
+    }
+
+    /// Frame data.
+    pub fn data(&self) -> &[u8] {
+        &self.data[..(self.len as usize).min(8)]
+    }
+}
+
+/// Black-box record
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Record {
+    /// Sequence number, increasing with every record written
+    pub sequence: u32,
+    /// Timestamp in milliseconds
+    pub time_ms: u32,
+    /// Active alarms, application defined bits
+    pub alarms: u32,
+    /// Frame that triggered a fault, if any
+    pub frame: Option<RawFrame>,
+    values: [Option<f32>; SIGNAL_COUNT],
+}
+
+impl Record {
+    /// Value of a signal, if recorded.
+    pub fn value(&self, signal: Signal) -> Option<f32> {
+        self.values[signal as usize]
+    }
+
+    fn to_bytes(self) -> [u8; RECORD_LEN] {
+        let mut bytes = [0; RECORD_LEN];
+
+        let mask = Signal::ALL
+            .into_iter()
+            .filter(|signal| self.value(*signal).is_some())
+            .fold(SignalSet::empty(), SignalSet::with);
+
+        bytes[0..4].copy_from_slice(&self.sequence.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.time_ms.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.alarms.to_le_bytes());
+        bytes[12..16].copy_from_slice(&mask.bits().to_le_bytes());
+
+        for (chunk, value) in bytes[16..FRAME_OFFSET].chunks_exact_mut(4).zip(self.values) {
+            chunk.copy_from_slice(&value.unwrap_or(0.0).to_le_bytes());
+        }
+
+        let frame = &mut bytes[FRAME_OFFSET..RECORD_LEN - 2];
+        match self.frame {
+            Some(raw) => {
+                frame[0..2].copy_from_slice(&raw.id.to_le_bytes());
+                frame[2] = raw.len;
+                frame[4..12].copy_from_slice(&raw.data);
+            }
+            None => frame[0..2].copy_from_slice(&NO_FRAME.to_le_bytes()),
+        }
+
+        let crc = crc16(&bytes[..RECORD_LEN - 2]);
+        bytes[RECORD_LEN - 2..].copy_from_slice(&crc.to_le_bytes());
+
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8; RECORD_LEN]) -> Option<Self> {
+        let crc = u16::from_le_bytes([bytes[RECORD_LEN - 2], bytes[RECORD_LEN - 1]]);
+        if crc16(&bytes[..RECORD_LEN - 2]) != crc {
+            return None;
+        }
+
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+
+        let mask = SignalSet::from_bits(u32_at(12));
+        let mut values = [None; SIGNAL_COUNT];
+
+        for signal in mask.iter() {
+            let offset = 16 + 4 * signal as usize;
+            values[signal as usize] = Some(f32::from_bits(u32_at(offset)));
+        }
+
+        let frame = &bytes[FRAME_OFFSET..RECORD_LEN - 2];
+        let id = u16::from_le_bytes([frame[0], frame[1]]);
+        let frame = (id != NO_FRAME).then(|| RawFrame {
+            id,
+            len: frame[2].min(8),
+            data: frame[4..12].try_into().unwrap(),
+        });
+
+        Some(Self {
+            sequence: u32_at(0),
+            time_ms: u32_at(4),
+            alarms: u32_at(8),
+            frame,
+            values,
+        })
+    }
+}
+
+impl Measurements for Record {
+    fn value(&self, signal: Signal) -> Option<f32> {
+        Record::value(self, signal)
+    }
+}
+
+/// Black-box logger writing a ring of records to `S`
+#[derive(Debug)]
+pub struct BlackBox<S> {
+    storage: S,
+    slots: u32,
+    next_slot: u32,
+    sequence: u32,
+}
+
+impl<S: Storage> BlackBox<S> {
+    /// Open the black box, resuming after the newest record already in
+    /// storage.
+    pub fn new(mut storage: S) -> Result<Self, &'static str> {
+        let slots = storage.capacity() / RECORD_LEN as u32;
+        if slots == 0 {
+            return Err("storage too small for a black-box record");
+        }
+
+        let mut newest: Option<(u32, u32)> = None;
+
+        for slot in 0..slots {
+            if let Some(record) = Self::read_slot(&mut storage, slot)? {
+                if newest.is_none_or(|(sequence, _)| record.sequence >= sequence) {
+                    newest = Some((record.sequence, slot));
+                }
+            }
+        }
+
+        let (sequence, next_slot) = match newest {
+            Some((sequence, slot)) => (sequence.wrapping_add(1), (slot + 1) % slots),
+            None => (0, 0),
+        };
+
+        Ok(Self {
+            storage,
+            slots,
+            next_slot,
+            sequence,
+        })
+    }
+
+    /// Number of records the storage holds.
+    pub fn slots(&self) -> u32 {
+        self.slots
+    }
+
+    /// Release the storage.
+    pub fn into_inner(self) -> S {
+        self.storage
+    }
+
+    /// Record every signal known by `sources` with the active alarms and the
+    /// frame that triggered a fault, if any, overwriting the oldest record.
+    ///
+    /// Where several sources know a signal the first one wins.
+    pub fn record(
+        &mut self,
+        now_ms: u32,
+        alarms: u32,
+        sources: &[&dyn Measurements],
+        frame: Option<RawFrame>,
+    ) -> Result<(), &'static str> {
+        let mut values = [None; SIGNAL_COUNT];
+
+        for signal in Signal::ALL {
+            values[signal as usize] = sources.iter().find_map(|source| source.value(signal));
+        }
+
+        let record = Record {
+            sequence: self.sequence,
+            time_ms: now_ms,
+            alarms,
+            frame,
+            values,
+        };
+
+        let offset = self.next_slot * RECORD_LEN as u32;
+        self.storage.write(offset, &record.to_bytes())?;
+
+        self.sequence = self.sequence.wrapping_add(1);
+        self.next_slot = (self.next_slot + 1) % self.slots;
+
+        Ok(())
+    }
+
+    /// Read back the newest records into `out`, oldest first, returning the
+    /// number read.
+    pub fn timeline(&mut self, out: &mut [Record]) -> Result<usize, &'static str> {
+        let mut valid: usize = 0;
+
+        for slot in 0..self.slots {
+            if Self::read_slot(&mut self.storage, slot)?.is_some() {
+                valid += 1;
+            }
+        }
+
+        let mut skip = valid.saturating_sub(out.len());
+        let mut count = 0;
+
+        for i in 0..self.slots {
+            let slot = (self.next_slot + i) % self.slots;
+
+            let Some(record) = Self::read_slot(&mut self.storage, slot)? else {
+                continue;
+            };
+
+            if skip > 0 {
+                skip -= 1;
+                continue;
+            }
+
+            out[count] = record;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    fn read_slot(storage: &mut S, slot: u32) -> Result<Option<Record>, &'static str> {
+        let mut bytes = [0; RECORD_LEN];
+        storage.read(slot * RECORD_LEN as u32, &mut bytes)?;
+
+        Ok(Record::from_bytes(&bytes))
+    }
+}
@@ -3,8 +3,10 @@
 //! [Product page](https://www.prohelion.com/product-category/bms/)
 //! [User's manual](https://www.prohelion.com/wp-content/uploads/2022/07/PHLN67.011v2-BMS-Users-Manual.pdf)
 
+use core::fmt;
+
 use bitflags::bitflags;
-use bxcan::{Frame, Id};
+use bxcan::{Frame, Id, StandardId};
 
 // id offsets for broadcast messages
 const ID_BROAD_HEARTBEAT: u16 = 0x00;
@@ -20,12 +22,38 @@ const ID_BROAD_STATUS: u16 = 0xFB;
 const ID_BROAD_FAN_STATUS: u16 = 0xFC;
 const ID_BROAD_STATUS_EXT: u16 = 0xFD;
 
-#[derive(Debug, Clone, Copy)]
-struct CmuStatus {
-    serial_number: u32,
-    pcb_temperature: u16,
-    cell_temperature: u16,
-    cell_voltage: [i16; 8],
+/// Number of identifiers used by a device, starting at its base identifier
+pub const ID_WINDOW: u16 = 0x100;
+
+/// Number of CMUs tracked in the status unless configured otherwise
+pub const CMU_CAPACITY_DEFAULT: usize = 8;
+
+/// Number of cells monitored by each CMU
+pub const CELLS_PER_CMU: usize = 8;
+
+/// Number of identifiers used by each CMU, starting at `ID_BROAD_CMU_STATUS`
+const CMU_FRAMES: u16 = 3;
+
+/// Most CMUs a BMU can report, limited by the identifiers before
+/// `ID_BROAD_SOC`
+pub const MAX_CMUS: usize = ((ID_BROAD_SOC - ID_BROAD_CMU_STATUS) / CMU_FRAMES) as usize;
+
+/// Default silence after which a CMU is considered to have stopped reporting
+const CMU_TIMEOUT_MS_DEFAULT: u32 = 1000;
+
+/// Cell monitoring unit status
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct CmuStatus {
+    /// CMU serial number, allocated at manufacture
+    pub serial_number: u32,
+    /// PCB temperature in 1/10th degrees celcius
+    pub pcb_temperature: u16,
+    /// Cell temperature in 1/10th degrees celcius
+    pub cell_temperature: u16,
+    /// Cell voltages in millivolts
+    pub cell_voltage: [i16; 8],
+    /// Bit n set once the voltage of cell n has been received
+    pub cell_voltage_received: u8,
 }
 
 bitflags! {
@@ -45,7 +73,7 @@ bitflags! {
 
 bitflags! {
     /// Precharge contactor driver status
-    struct ContactorDriverStatus: u8 {
+    pub struct ContactorDriverStatus: u8 {
         const CONTACTOR_1_DRIVER_ERROR = 0x01;
         const CONTACTOR_2_DRIVER_ERROR = 0x02;
         const CONTACTOR_1_OUTPUT_ON = 0x04;
@@ -58,9 +86,23 @@ bitflags! {
 
 }
 
+bitflags! {
+    /// Pack status flags
+    pub struct StatusFlags: u8 {
+        const CELL_OVER_VOLTAGE = 0x01;
+        const CELL_UNDER_VOLTAGE = 0x02;
+        const CELL_OVER_TEMPERATURE = 0x04;
+        const MEASUREMENT_UNTRUSTED = 0x08;
+        const CMU_COMMS_TIMEOUT = 0x10;
+        const VEHICLE_COMMS_TIMEOUT = 0x20;
+        const BMU_SETUP_MODE = 0x40;
+        const CMU_CAN_POWER = 0x80;
+    }
+}
+
 /// Precharge state
-#[derive(Clone, Copy)]
-enum PrechargeState {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrechargeState {
     Error = 0,
     Idle = 1,
     EnablePack = 5,
@@ -70,7 +112,7 @@ enum PrechargeState {
 }
 
 impl PrechargeState {
-    fn from_u8(value: u8) -> Option<PrechargeState> {
+    pub fn from_u8(value: u8) -> Option<PrechargeState> {
         match value {
             0 => Some(PrechargeState::Error),
             1 => Some(PrechargeState::Idle),
@@ -81,128 +123,907 @@ impl PrechargeState {
             _ => None,
         }
     }
+
+    /// Position in the precharge sequence, `Error` coming last.
+    fn step(self) -> u8 {
+        match self {
+            PrechargeState::Idle => 0,
+            PrechargeState::EnablePack => 1,
+            PrechargeState::Measure => 2,
+            PrechargeState::Precharge => 3,
+            PrechargeState::Run => 4,
+            PrechargeState::Error => 5,
+        }
+    }
+
+    /// Is moving from this state to `next` part of a normal sequence?
+    ///
+    /// The sequence may stop or fail from any state, and otherwise only
+    /// moves forwards. States may be skipped, since the BMU broadcasts its
+    /// state less often than short states like `EnablePack` last. Moving
+    /// backwards, such as from `Run` to `Measure`, suggests a BMU or bus
+    /// fault.
+    pub fn is_legal_transition(self, next: PrechargeState) -> bool {
+        match (self, next) {
+            (_, PrechargeState::Idle | PrechargeState::Error) => true,
+            (PrechargeState::Error, _) => false,
+            (from, to) => to.step() >= from.step(),
+        }
+    }
+}
+
+impl fmt::Display for PrechargeState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PrechargeState::Error => "error",
+            PrechargeState::Idle => "idle",
+            PrechargeState::EnablePack => "enable pack",
+            PrechargeState::Measure => "measure",
+            PrechargeState::Precharge => "precharge",
+            PrechargeState::Run => "run",
+        })
+    }
+}
+
+/// Cell location within the pack
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    /// CMU number
+    pub cmu: u8,
+    /// Cell number within the CMU
+    pub number: u8,
+}
+
+/// Cell with a voltage measurement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellWithVoltage {
+    /// Cell location
+    pub cell: Cell,
+    /// Cell voltage in millivolts
+    pub voltage: u16,
+}
+
+/// Cell with a temperature measurement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellWithTemperature {
+    /// Cell location
+    pub cell: Cell,
+    /// Cell temperature in 1/10th degrees celcius
+    pub temperature: u16,
+}
+
+/// Lowest and highest cells of the pack
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellExtremes {
+    /// Cell with the lowest voltage
+    pub minimum_voltage: CellWithVoltage,
+    /// Cell with the highest voltage
+    pub maximum_voltage: CellWithVoltage,
+    /// CMU with the lowest cell temperature
+    pub minimum_temperature: CellWithTemperature,
+    /// CMU with the highest cell temperature
+    pub maximum_temperature: CellWithTemperature,
+}
+
+/// Cell measurements reported by its CMU
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellReading {
+    /// Cell location
+    pub cell: Cell,
+    /// Cell voltage in millivolts
+    pub voltage: i16,
+    /// CMU cell temperature in 1/10th degrees celcius, shared by every cell
+    /// of the CMU
+    pub temperature: u16,
+}
+
+/// Status
+///
+/// Tracks up to `CMUS` cell monitoring units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Status<const CMUS: usize = CMU_CAPACITY_DEFAULT> {
+    /// Device identifier
+    pub device_identifier: Option<u32>,
+    /// Device serial number, allocated at manufacture
+    pub device_serial_number: Option<u32>,
+    /// Status of each cell monitoring unit
+    pub cmu_status: [Option<CmuStatus>; CMUS],
+    /// Amp-hours consumed from the pack
+    pub soc_amp_hours: Option<f32>,
+    /// Pack state of charge as a fraction, 1.0 being full
+    pub soc_percent: Option<f32>,
+    /// Amp-hours of imbalance between cells
+    pub balance_soc_amp_hours: Option<f32>,
+    /// Imbalance between cells as a fraction of capacity
+    pub balance_soc_percent: Option<f32>,
+    /// Charging cell voltage error in millivolts, the headroom between the
+    /// highest cell and the maximum cell voltage
+    pub charging_cell_voltage_error: Option<i16>,
+    /// Cell temperature margin in 1/10th degrees celcius, the headroom
+    /// between the hottest cell and the maximum cell temperature
+    pub cell_temperature_margin: Option<i16>,
+    /// Discharging cell voltage error in millivolts, the headroom between the
+    /// lowest cell and the minimum cell voltage
+    pub discharging_cell_voltage_error: Option<i16>,
+    /// Total pack capacity in amp-hours
+    pub total_pack_capacity: Option<u16>,
+    /// Precharge contactor driver status
+    pub contactor_driver_status: Option<ContactorDriverStatus>,
+    /// Precharge state
+    pub precharge_state: Option<PrechargeState>,
+    /// Contactor supply voltage in millivolts
+    pub contactor_supply_voltage: Option<u16>,
+    /// Precharge timer has elapsed
+    pub precharge_timer_elapsed: Option<bool>,
+    /// Precharge timer counter in 10 ms increments
+    pub precharge_timer_counter: Option<u8>,
+    /// Cell with the lowest voltage
+    pub minimum_voltage_cell: Option<CellWithVoltage>,
+    /// Cell with the highest voltage
+    pub maximum_voltage_cell: Option<CellWithVoltage>,
+    /// Cell with the lowest temperature
+    pub minimum_temperature_cell: Option<CellWithTemperature>,
+    /// Cell with the highest temperature
+    pub maximum_temperature_cell: Option<CellWithTemperature>,
+    /// Pack voltage in millivolts
+    pub pack_voltage_mv: Option<u32>,
+    /// Pack current in milliamps
+    pub pack_current_ma: Option<i32>,
+    /// Cell voltage above which balancing starts in millivolts
+    pub balance_threshold_rising: Option<u16>,
+    /// Cell voltage below which balancing stops in millivolts
+    pub balance_threshold_falling: Option<u16>,
+    /// Pack status flags
+    pub status_flags: Option<StatusFlags>,
+    /// Number of CMUs the BMU is configured for
+    pub configured_cmu_count: Option<u8>,
+    /// BMU firmware build number
+    pub firmware_build: Option<u16>,
+}
+
+impl<const CMUS: usize> Default for Status<CMUS> {
+    fn default() -> Self {
+        Self {
+            device_identifier: None,
+            device_serial_number: None,
+            cmu_status: [None; CMUS],
+            soc_amp_hours: None,
+            soc_percent: None,
+            balance_soc_amp_hours: None,
+            balance_soc_percent: None,
+            charging_cell_voltage_error: None,
+            cell_temperature_margin: None,
+            discharging_cell_voltage_error: None,
+            total_pack_capacity: None,
+            contactor_driver_status: None,
+            precharge_state: None,
+            contactor_supply_voltage: None,
+            precharge_timer_elapsed: None,
+            precharge_timer_counter: None,
+            minimum_voltage_cell: None,
+            maximum_voltage_cell: None,
+            minimum_temperature_cell: None,
+            maximum_temperature_cell: None,
+            pack_voltage_mv: None,
+            pack_current_ma: None,
+            balance_threshold_rising: None,
+            balance_threshold_falling: None,
+            status_flags: None,
+            configured_cmu_count: None,
+            firmware_build: None,
+        }
+    }
+}
+
+impl<const CMUS: usize> Status<CMUS> {
+    /// Forget every received value.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Merge another status into this one, taking every value present in
+    /// `other` as newer.
+    pub fn merge(&mut self, other: &Status<CMUS>) {
+        self.device_identifier = other.device_identifier.or(self.device_identifier);
+        self.device_serial_number = other.device_serial_number.or(self.device_serial_number);
+        for (cmu, other) in self.cmu_status.iter_mut().zip(other.cmu_status) {
+            *cmu = other.or(*cmu);
+        }
+        self.soc_amp_hours = other.soc_amp_hours.or(self.soc_amp_hours);
+        self.soc_percent = other.soc_percent.or(self.soc_percent);
+        self.balance_soc_amp_hours = other.balance_soc_amp_hours.or(self.balance_soc_amp_hours);
+        self.balance_soc_percent = other.balance_soc_percent.or(self.balance_soc_percent);
+        self.charging_cell_voltage_error = other
+            .charging_cell_voltage_error
+            .or(self.charging_cell_voltage_error);
+        self.cell_temperature_margin = other
+            .cell_temperature_margin
+            .or(self.cell_temperature_margin);
+        self.discharging_cell_voltage_error = other
+            .discharging_cell_voltage_error
+            .or(self.discharging_cell_voltage_error);
+        self.total_pack_capacity = other.total_pack_capacity.or(self.total_pack_capacity);
+        self.contactor_driver_status = other
+            .contactor_driver_status
+            .or(self.contactor_driver_status);
+        self.precharge_state = other.precharge_state.or(self.precharge_state);
+        self.contactor_supply_voltage = other
+            .contactor_supply_voltage
+            .or(self.contactor_supply_voltage);
+        self.precharge_timer_elapsed = other
+            .precharge_timer_elapsed
+            .or(self.precharge_timer_elapsed);
+        self.precharge_timer_counter = other
+            .precharge_timer_counter
+            .or(self.precharge_timer_counter);
+        self.minimum_voltage_cell = other.minimum_voltage_cell.or(self.minimum_voltage_cell);
+        self.maximum_voltage_cell = other.maximum_voltage_cell.or(self.maximum_voltage_cell);
+        self.minimum_temperature_cell = other
+            .minimum_temperature_cell
+            .or(self.minimum_temperature_cell);
+        self.maximum_temperature_cell = other
+            .maximum_temperature_cell
+            .or(self.maximum_temperature_cell);
+        self.pack_voltage_mv = other.pack_voltage_mv.or(self.pack_voltage_mv);
+        self.pack_current_ma = other.pack_current_ma.or(self.pack_current_ma);
+        self.balance_threshold_rising = other
+            .balance_threshold_rising
+            .or(self.balance_threshold_rising);
+        self.balance_threshold_falling = other
+            .balance_threshold_falling
+            .or(self.balance_threshold_falling);
+        self.status_flags = other.status_flags.or(self.status_flags);
+        self.configured_cmu_count = other.configured_cmu_count.or(self.configured_cmu_count);
+        self.firmware_build = other.firmware_build.or(self.firmware_build);
+    }
+
+    /// Update the status with a decoded broadcast message.
+    pub fn apply(&mut self, message: BmuMessage) {
+        match message {
+            BmuMessage::Heartbeat {
+                identifier,
+                serial_number,
+            } => {
+                self.device_identifier = Some(identifier);
+                self.device_serial_number = Some(serial_number);
+            }
+            BmuMessage::StateOfCharge { amp_hours, percent } => {
+                self.soc_amp_hours = Some(amp_hours);
+                self.soc_percent = Some(percent);
+            }
+            BmuMessage::BalanceStateOfCharge { amp_hours, percent } => {
+                self.balance_soc_amp_hours = Some(amp_hours);
+                self.balance_soc_percent = Some(percent);
+            }
+            BmuMessage::ChargerControl {
+                charging_cell_voltage_error,
+                cell_temperature_margin,
+                discharging_cell_voltage_error,
+                total_pack_capacity,
+            } => {
+                self.charging_cell_voltage_error = Some(charging_cell_voltage_error);
+                self.cell_temperature_margin = Some(cell_temperature_margin);
+                self.discharging_cell_voltage_error = Some(discharging_cell_voltage_error);
+                self.total_pack_capacity = Some(total_pack_capacity);
+            }
+            BmuMessage::Precharge {
+                contactor_driver_status,
+                precharge_state,
+                contactor_supply_voltage,
+                timer_elapsed,
+                timer_counter,
+            } => {
+                self.contactor_driver_status = contactor_driver_status;
+                self.precharge_state = precharge_state;
+                self.contactor_supply_voltage = Some(contactor_supply_voltage);
+                self.precharge_timer_elapsed = Some(timer_elapsed);
+                self.precharge_timer_counter = Some(timer_counter);
+            }
+            BmuMessage::CmuStatus {
+                cmu,
+                serial_number,
+                pcb_temperature,
+                cell_temperature,
+            } => {
+                if let Some(status) = self.cmu_mut(cmu) {
+                    status.serial_number = serial_number;
+                    status.pcb_temperature = pcb_temperature;
+                    status.cell_temperature = cell_temperature;
+                }
+            }
+            BmuMessage::CellVoltages {
+                cmu,
+                first_cell,
+                voltages,
+            } => {
+                if let Some(status) = self.cmu_mut(cmu) {
+                    let first = first_cell as usize;
+                    status.cell_voltage[first..first + 4].copy_from_slice(&voltages);
+                    status.cell_voltage_received |= 0x0F << first;
+                }
+            }
+            BmuMessage::CellVoltageExtremes { minimum, maximum } => {
+                self.minimum_voltage_cell = Some(minimum);
+                self.maximum_voltage_cell = Some(maximum);
+            }
+            BmuMessage::CellTemperatureExtremes { minimum, maximum } => {
+                self.minimum_temperature_cell = Some(minimum);
+                self.maximum_temperature_cell = Some(maximum);
+            }
+            BmuMessage::PackVoltageCurrent {
+                voltage_mv,
+                current_ma,
+            } => {
+                self.pack_voltage_mv = Some(voltage_mv);
+                self.pack_current_ma = Some(current_ma);
+            }
+            BmuMessage::PackStatus {
+                balance_threshold_rising,
+                balance_threshold_falling,
+                flags,
+                cmu_count,
+                firmware_build,
+            } => {
+                self.balance_threshold_rising = Some(balance_threshold_rising);
+                self.balance_threshold_falling = Some(balance_threshold_falling);
+                self.status_flags = Some(flags);
+                self.configured_cmu_count = Some(cmu_count);
+                self.firmware_build = Some(firmware_build);
+            }
+        }
+    }
+
+    /// Contactor supply voltage in volts.
+    pub fn contactor_supply_volts(&self) -> Option<f32> {
+        self.contactor_supply_voltage
+            .map(|voltage| voltage as f32 / 1000.0)
+    }
+
+    /// Every cell whose voltage has been received, in CMU then cell order.
+    pub fn cells(&self) -> impl Iterator<Item = CellReading> + '_ {
+        self.cmu_status
+            .iter()
+            .enumerate()
+            .filter_map(|(cmu, status)| Some((cmu as u8, status.as_ref()?)))
+            .flat_map(|(cmu, status)| {
+                (0..CELLS_PER_CMU as u8)
+                    .filter(|number| status.cell_voltage_received & 1 << number != 0)
+                    .map(move |number| CellReading {
+                        cell: Cell { cmu, number },
+                        voltage: status.cell_voltage[number as usize],
+                        temperature: status.cell_temperature,
+                    })
+            })
+    }
+
+    /// Lowest and highest cells computed from the CMU readings, rather than
+    /// the BMU's summary. `None` until a cell voltage has been received.
+    ///
+    /// Negative voltage readings, from a faulty sense lead, are left out of
+    /// the voltage extremes, so `None` is also returned when every reading is
+    /// negative. Temperatures are per CMU, so their cell number
+    /// is always 0.
+    pub fn cell_extremes(&self) -> Option<CellExtremes> {
+        let mut cells = self.cells();
+        let first = cells.next()?;
+
+        let mut minimum_voltage: Option<CellReading> = None;
+        let mut maximum_voltage: Option<CellReading> = None;
+        let mut minimum_temperature = first;
+        let mut maximum_temperature = first;
+
+        for reading in core::iter::once(first).chain(cells) {
+            if reading.voltage >= 0 {
+                if minimum_voltage.is_none_or(|minimum| reading.voltage < minimum.voltage) {
+                    minimum_voltage = Some(reading);
+                }
+                if maximum_voltage.is_none_or(|maximum| reading.voltage > maximum.voltage) {
+                    maximum_voltage = Some(reading);
+                }
+            }
+            if reading.temperature < minimum_temperature.temperature {
+                minimum_temperature = reading;
+            }
+            if reading.temperature > maximum_temperature.temperature {
+                maximum_temperature = reading;
+            }
+        }
+
+        let voltage = |reading: CellReading| CellWithVoltage {
+            cell: reading.cell,
+            voltage: reading.voltage as u16,
+        };
+        let temperature = |reading: CellReading| CellWithTemperature {
+            cell: Cell {
+                cmu: reading.cell.cmu,
+                number: 0,
+            },
+            temperature: reading.temperature,
+        };
+
+        Some(CellExtremes {
+            minimum_voltage: voltage(minimum_voltage?),
+            maximum_voltage: voltage(maximum_voltage?),
+            minimum_temperature: temperature(minimum_temperature),
+            maximum_temperature: temperature(maximum_temperature),
+        })
+    }
+
+    /// Status of a CMU, created on first use. `None` for CMUs beyond the
+    /// status' capacity.
+    fn cmu_mut(&mut self, cmu: u8) -> Option<&mut CmuStatus> {
+        let status = self.cmu_status.get_mut(cmu as usize)?;
+
+        Some(status.get_or_insert_with(CmuStatus::default))
+    }
+}
+
+/// Broadcast messages sent by a BMU
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BmuMessage {
+    /// Device identifier and serial number
+    Heartbeat { identifier: u32, serial_number: u32 },
+    /// Amp-hours consumed and state of charge as a fraction
+    StateOfCharge { amp_hours: f32, percent: f32 },
+    /// Amp-hours and fraction of imbalance between cells
+    BalanceStateOfCharge { amp_hours: f32, percent: f32 },
+    /// Charger control information
+    ChargerControl {
+        charging_cell_voltage_error: i16,
+        cell_temperature_margin: i16,
+        discharging_cell_voltage_error: i16,
+        total_pack_capacity: u16,
+    },
+    /// Precharge status
+    Precharge {
+        contactor_driver_status: Option<ContactorDriverStatus>,
+        precharge_state: Option<PrechargeState>,
+        contactor_supply_voltage: u16,
+        timer_elapsed: bool,
+        timer_counter: u8,
+    },
+    /// CMU serial number and temperatures
+    CmuStatus {
+        cmu: u8,
+        serial_number: u32,
+        pcb_temperature: u16,
+        cell_temperature: u16,
+    },
+    /// Four consecutive cell voltages of a CMU in millivolts
+    CellVoltages {
+        cmu: u8,
+        first_cell: u8,
+        voltages: [i16; 4],
+    },
+    /// Cells with the lowest and highest voltage
+    CellVoltageExtremes {
+        minimum: CellWithVoltage,
+        maximum: CellWithVoltage,
+    },
+    /// CMUs with the lowest and highest cell temperature
+    CellTemperatureExtremes {
+        minimum: CellWithTemperature,
+        maximum: CellWithTemperature,
+    },
+    /// Pack voltage and current, positive while discharging
+    PackVoltageCurrent { voltage_mv: u32, current_ma: i32 },
+    /// Balance thresholds, status flags, CMU count and firmware build
+    PackStatus {
+        balance_threshold_rising: u16,
+        balance_threshold_falling: u16,
+        flags: StatusFlags,
+        cmu_count: u8,
+        firmware_build: u16,
+    },
+}
+
+fn u16_at(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(data[offset..offset + 2].try_into().unwrap())
 }
 
-#[derive(Debug, Clone, Copy)]
-struct Cell {
-    cmu: u8,
-    number: u8,
+fn i16_at(data: &[u8], offset: usize) -> i16 {
+    i16::from_le_bytes(data[offset..offset + 2].try_into().unwrap())
 }
 
-#[derive(Debug, Clone, Copy)]
-struct CellWithVoltage {
-    cell: Cell,
-    voltage: u16,
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
 }
 
-#[derive(Debug, Clone, Copy)]
-struct CellWithTemperature {
-    cell: Cell,
-    temperature: u16,
+fn f32_at(data: &[u8], offset: usize) -> f32 {
+    f32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
 }
 
-#[derive(Default, Clone, Copy)]
-struct Status {
-    device_identifier: Option<u32>,
-    device_serial_number: Option<u32>,
-    cmu_status: [Option<CmuStatus>; 8],
-    soc_amp_hours: Option<f32>,
-    soc_percent: Option<f32>,
-    balance_soc_amp_hours: Option<f32>,
-    balance_soc_percent: Option<f32>,
-    charging_cell_voltage_error: Option<u16>,
-    cell_temperature_margin: Option<u16>,
-    discharging_cell_voltage_error: Option<u16>,
-    total_pack_capacity: Option<u16>,
-    contactor_driver_status: Option<ContactorDriverStatus>,
-    precharge_state: Option<PrechargeState>,
-    contactor_supply_voltage: Option<u16>,
-    precharge_timer_elapsed: Option<bool>,
-    precharge_timer_counter: Option<u8>,
-    minimum_voltage_cell: Option<CellWithVoltage>,
-    maximum_voltage_cell: Option<CellWithVoltage>,
-    minimum_temperature_cell: Option<CellWithTemperature>,
-    maximum_temperature_cell: Option<CellWithTemperature>,
-    pack_voltage_mv: Option<u32>,
-    pack_current_ma: Option<u32>,
+/// Offset and payload of a data frame within the window starting at
+/// `base_id`, `None` for frames belonging to other devices.
+fn window(frame: &Frame, base_id: u16) -> Option<(u16, &[u8])> {
+    let (Id::Standard(id), Some(data)) = (frame.id(), frame.data()) else {
+        return None;
+    };
+
+    let offset = id.as_raw().wrapping_sub(base_id);
+
+    (offset < ID_WINDOW).then_some((offset, &data[..]))
+}
+
+/// Decode the payload of a broadcast message.
+///
+/// `offset` is the frame identifier relative to the device base identifier.
+/// Returns `None` for identifiers that are not decoded BMU broadcasts.
+pub fn decode_bmu(offset: u16, data: &[u8]) -> Result<Option<BmuMessage>, &'static str> {
+    let is_cmu = (ID_BROAD_CMU_STATUS..ID_BROAD_SOC).contains(&offset);
+
+    if !is_cmu
+        && !matches!(
+            offset,
+            ID_BROAD_HEARTBEAT
+                | ID_BROAD_SOC
+                | ID_BROAD_BALANCE_SOC
+                | ID_BROAD_CHG_CTL
+                | ID_BROAD_PRECHARGE
+                | ID_BROAD_MIN_MAX_CELL_VOLT
+                | ID_BROAD_MIN_MAX_CELL_TEMP
+                | ID_BROAD_VOLT_CURR
+                | ID_BROAD_STATUS
+        )
+    {
+        return Ok(None);
+    }
+
+    if data.len() < 8 {
+        return Err("broadcast frame too short");
+    }
+
+    // each CMU broadcasts its status followed by two frames of cell voltages
+    if is_cmu {
+        let cmu = ((offset - ID_BROAD_CMU_STATUS) / CMU_FRAMES) as u8;
+
+        let message = match (offset - ID_BROAD_CMU_STATUS) % CMU_FRAMES {
+            0 => BmuMessage::CmuStatus {
+                cmu,
+                serial_number: u32_at(data, 0),
+                pcb_temperature: u16_at(data, 4),
+                cell_temperature: u16_at(data, 6),
+            },
+            frame => BmuMessage::CellVoltages {
+                cmu,
+                first_cell: (frame as u8 - 1) * 4,
+                voltages: [
+                    i16_at(data, 0),
+                    i16_at(data, 2),
+                    i16_at(data, 4),
+                    i16_at(data, 6),
+                ],
+            },
+        };
+
+        return Ok(Some(message));
+    }
+
+    let message = match offset {
+        ID_BROAD_HEARTBEAT => BmuMessage::Heartbeat {
+            identifier: u32_at(data, 0),
+            serial_number: u32_at(data, 4),
+        },
+
+        ID_BROAD_SOC => BmuMessage::StateOfCharge {
+            amp_hours: f32_at(data, 0),
+            percent: f32_at(data, 4),
+        },
+
+        ID_BROAD_BALANCE_SOC => BmuMessage::BalanceStateOfCharge {
+            amp_hours: f32_at(data, 0),
+            percent: f32_at(data, 4),
+        },
+
+        ID_BROAD_CHG_CTL => BmuMessage::ChargerControl {
+            charging_cell_voltage_error: i16_at(data, 0),
+            cell_temperature_margin: i16_at(data, 2),
+            discharging_cell_voltage_error: i16_at(data, 4),
+            total_pack_capacity: u16_at(data, 6),
+        },
+
+        ID_BROAD_PRECHARGE => BmuMessage::Precharge {
+            contactor_driver_status: ContactorDriverStatus::from_bits(data[0]),
+            precharge_state: PrechargeState::from_u8(data[1]),
+            contactor_supply_voltage: u16_at(data, 2),
+            timer_elapsed: data[6] == 1,
+            timer_counter: data[7],
+        },
+
+        ID_BROAD_MIN_MAX_CELL_VOLT => BmuMessage::CellVoltageExtremes {
+            minimum: CellWithVoltage {
+                cell: Cell {
+                    cmu: data[4],
+                    number: data[5],
+                },
+                voltage: u16_at(data, 0),
+            },
+            maximum: CellWithVoltage {
+                cell: Cell {
+                    cmu: data[6],
+                    number: data[7],
+                },
+                voltage: u16_at(data, 2),
+            },
+        },
+
+        // temperatures are measured per CMU rather than per cell
+        ID_BROAD_MIN_MAX_CELL_TEMP => BmuMessage::CellTemperatureExtremes {
+            minimum: CellWithTemperature {
+                cell: Cell {
+                    cmu: data[4],
+                    number: 0,
+                },
+                temperature: u16_at(data, 0),
+            },
+            maximum: CellWithTemperature {
+                cell: Cell {
+                    cmu: data[6],
+                    number: 0,
+                },
+                temperature: u16_at(data, 2),
+            },
+        },
+
+        ID_BROAD_VOLT_CURR => BmuMessage::PackVoltageCurrent {
+            voltage_mv: u32_at(data, 0),
+            current_ma: u32_at(data, 4) as i32,
+        },
+
+        ID_BROAD_STATUS => BmuMessage::PackStatus {
+            balance_threshold_rising: u16_at(data, 0),
+            balance_threshold_falling: u16_at(data, 2),
+            flags: StatusFlags::from_bits_truncate(data[4]),
+            cmu_count: data[5],
+            firmware_build: u16_at(data, 6),
+        },
+
+        _ => return Ok(None),
+    };
+
+    Ok(Some(message))
 }
 
-struct Bmu {
+/// Battery management unit
+///
+/// Tracks up to `CMUS` cell monitoring units, see [`Bmu::sized`] for packs
+/// with more than [`CMU_CAPACITY_DEFAULT`].
+pub struct Bmu<const CMUS: usize = CMU_CAPACITY_DEFAULT> {
     base_id: u16,
+    cmu_timeout_ms: u32,
 
-    status: Status,
+    status: Status<CMUS>,
+    cmu_last_seen_ms: [Option<u32>; CMUS],
+    cmu_silent: [bool; CMUS],
+    last_heartbeat_ms: Option<u32>,
 }
 
 impl Bmu {
-    pub fn new(base_id: u16) -> Self {
+    /// Create a new BMU instance tracking [`CMU_CAPACITY_DEFAULT`] CMUs.
+    ///
+    /// Fails if the device's identifier window starting at `base_id` does
+    /// not fit within the standard identifier range.
+    pub fn new(base_id: u16) -> Result<Self, &'static str> {
+        Self::sized(base_id)
+    }
+}
+
+impl<const CMUS: usize> Bmu<CMUS> {
+    const CAPACITY_FITS: () = assert!(CMUS <= MAX_CMUS, "BMU reports at most MAX_CMUS CMUs");
+
+    /// Create a new BMU instance tracking `CMUS` CMUs.
+    ///
+    /// See [`Bmu::new`].
+    pub fn sized(base_id: u16) -> Result<Self, &'static str> {
+        let () = Self::CAPACITY_FITS;
+
+        if StandardId::new(base_id.saturating_add(ID_WINDOW - 1)).is_none() {
+            return Err("identifier window exceeds standard identifier range");
+        }
+
+        Ok(Self::unchecked(base_id))
+    }
+
+    fn unchecked(base_id: u16) -> Self {
         Self {
             base_id,
-            status: Status {
-                ..Default::default()
-            },
+            cmu_timeout_ms: CMU_TIMEOUT_MS_DEFAULT,
+            status: Status::default(),
+            cmu_last_seen_ms: [None; CMUS],
+            cmu_silent: [false; CMUS],
+            last_heartbeat_ms: None,
         }
     }
 
-    pub fn status(self) -> Status {
+    /// Set the silence after which a CMU is considered to have stopped
+    /// reporting by [`check_cmus`](Self::check_cmus).
+    pub fn with_cmu_timeout_ms(mut self, timeout_ms: u32) -> Self {
+        self.cmu_timeout_ms = timeout_ms;
+        self
+    }
+
+    /// Get the current status state of the device
+    pub fn status(&self) -> Status<CMUS> {
+        self.status
+    }
+
+    /// Number of CMUs reporting, excluding any that have gone silent.
+    pub fn cmu_count(&self) -> usize {
         self.status
+            .cmu_status
+            .iter()
+            .zip(self.cmu_silent)
+            .filter(|(status, silent)| status.is_some() && !silent)
+            .count()
+    }
+
+    /// Every cell whose voltage has been received, in CMU then cell order.
+    pub fn cells(&self) -> impl Iterator<Item = CellReading> + '_ {
+        self.status.cells()
+    }
+
+    /// CMUs that reported previously but have since gone silent.
+    pub fn silent_cmus(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..CMUS)
+            .filter(|cmu| self.cmu_silent[*cmu])
+            .map(|cmu| cmu as u8)
     }
 
+    /// Check for CMUs that have stopped reporting, returning how many are
+    /// silent.
+    ///
+    /// Only CMUs seen by [`receive_at`](Self::receive_at) are checked.
+    pub fn check_cmus(&mut self, now_ms: u32) -> usize {
+        for (cmu, (last_seen_ms, silent)) in self
+            .cmu_last_seen_ms
+            .iter()
+            .zip(self.cmu_silent.iter_mut())
+            .enumerate()
+        {
+            let Some(last_seen_ms) = last_seen_ms else {
+                continue;
+            };
+
+            if !*silent && now_ms.wrapping_sub(*last_seen_ms) >= self.cmu_timeout_ms {
+                log_warn!("CMU {} stopped reporting", cmu);
+                *silent = true;
+            }
+        }
+
+        self.cmu_silent.iter().filter(|silent| **silent).count()
+    }
+
+    /// Decode a broadcast frame and apply it to the device status.
     pub fn receive(&mut self, frame: Frame) -> Result<(), &'static str> {
-        match frame.id() {
-            Id::Standard(id) => {
-                if id.as_raw() >= self.base_id {
-                    if let Some(data) = frame.data() {
-                        match id.as_raw() - self.base_id {
-                            ID_BROAD_HEARTBEAT => {
-                                self.status.device_identifier =
-                                    Some(u32::from_le_bytes(data[0..4].try_into().unwrap()));
-                                self.status.device_serial_number =
-                                    Some(u32::from_le_bytes(data[4..8].try_into().unwrap()));
-                            }
-
-                            ID_BROAD_SOC => {
-                                self.status.soc_amp_hours =
-                                    Some(f32::from_le_bytes(data[0..4].try_into().unwrap()));
-                                self.status.soc_percent =
-                                    Some(f32::from_le_bytes(data[4..8].try_into().unwrap()));
-                            }
-
-                            ID_BROAD_BALANCE_SOC => {
-                                self.status.balance_soc_amp_hours =
-                                    Some(f32::from_le_bytes(data[0..4].try_into().unwrap()));
-                                self.status.balance_soc_percent =
-                                    Some(f32::from_le_bytes(data[4..8].try_into().unwrap()));
-                            }
-
-                            ID_BROAD_CHG_CTL => {
-                                self.status.charging_cell_voltage_error =
-                                    Some(u16::from_le_bytes(data[0..2].try_into().unwrap()));
-                                self.status.cell_temperature_margin =
-                                    Some(u16::from_le_bytes(data[2..4].try_into().unwrap()));
-                                self.status.discharging_cell_voltage_error =
-                                    Some(u16::from_le_bytes(data[4..6].try_into().unwrap()));
-                                self.status.discharging_cell_voltage_error =
-                                    Some(u16::from_le_bytes(data[6..8].try_into().unwrap()));
-                            }
-
-                            ID_BROAD_PRECHARGE => {
-                                self.status.contactor_driver_status =
-                                    ContactorDriverStatus::from_bits(data[0]);
-                                self.status.precharge_state = PrechargeState::from_u8(data[1]);
-                                self.status.contactor_supply_voltage =
-                                    Some(u16::from_le_bytes(data[2..4].try_into().unwrap()));
-                                self.status.precharge_timer_elapsed = Some(data[6] == 1);
-                                self.status.precharge_timer_counter = Some(data[7]);
-                            }
-
-                            _ => {}
-                        }
-                    }
+        if let Some(message) = self.decode(&frame)? {
+            self.status.apply(message);
+        }
+
+        Ok(())
+    }
+
+    /// Time of the latest heartbeat seen by [`receive_at`](Self::receive_at).
+    pub fn last_heartbeat_ms(&self) -> Option<u32> {
+        self.last_heartbeat_ms
+    }
+
+    /// Decode a broadcast frame received at `now_ms` and apply it to the
+    /// device status, noting when each CMU and the heartbeat last reported.
+    pub fn receive_at(&mut self, now_ms: u32, frame: Frame) -> Result<(), &'static str> {
+        if let Some(message) = self.decode(&frame)? {
+            self.apply_at(now_ms, message);
+        }
+
+        Ok(())
+    }
+
+    fn apply_at(&mut self, now_ms: u32, message: BmuMessage) {
+        if let BmuMessage::Heartbeat { .. } = message {
+            self.last_heartbeat_ms = Some(now_ms);
+        }
+
+        if let BmuMessage::CmuStatus { cmu, .. } | BmuMessage::CellVoltages { cmu, .. } = message {
+            if let Some(last_seen_ms) = self.cmu_last_seen_ms.get_mut(cmu as usize) {
+                *last_seen_ms = Some(now_ms);
+
+                if core::mem::take(&mut self.cmu_silent[cmu as usize]) {
+                    log_info!("CMU {} reporting again", cmu);
                 }
             }
-            Id::Extended(_) => {}
+        }
+
+        self.status.apply(message);
+    }
+
+    fn decode(&self, frame: &Frame) -> Result<Option<BmuMessage>, &'static str> {
+        match window(frame, self.base_id) {
+            Some((offset, data)) => self.decode_offset(offset, data),
+            None => Ok(None),
+        }
+    }
+
+    fn decode_offset(&self, offset: u16, data: &[u8]) -> Result<Option<BmuMessage>, &'static str> {
+        let message = decode_bmu(offset, data);
+
+        match &message {
+            Err(error) => log_warn!("BMU frame {:#05x}: {}", self.base_id + offset, error),
+            Ok(None) => log_debug!("BMU frame {:#05x} not decoded", self.base_id + offset),
+            Ok(Some(_)) => {}
+        }
+
+        message
+    }
+}
+
+/// Bmu with a base identifier fixed at compile time
+///
+/// The identifier window is validated when the program is compiled, so
+/// construction cannot fail. Dereferences to [`Bmu`] for everything else.
+pub struct FixedBmu<const BASE: u16, const CMUS: usize = CMU_CAPACITY_DEFAULT>(Bmu<CMUS>);
+
+impl<const BASE: u16, const CMUS: usize> FixedBmu<BASE, CMUS> {
+    const WINDOW_FITS: () = assert!(
+        BASE as u32 + ID_WINDOW as u32 <= 0x800,
+        "identifier window exceeds standard identifier range"
+    );
+
+    /// Create a new instance.
+    pub fn new() -> Self {
+        let () = Self::WINDOW_FITS;
+        let () = Bmu::<CMUS>::CAPACITY_FITS;
+
+        Self(Bmu::unchecked(BASE))
+    }
+
+    /// See [`Bmu::with_cmu_timeout_ms`].
+    pub fn with_cmu_timeout_ms(self, timeout_ms: u32) -> Self {
+        Self(self.0.with_cmu_timeout_ms(timeout_ms))
+    }
+
+    /// Decode a broadcast frame and apply it to the device status.
+    ///
+    /// The identifier window is resolved from `BASE` at compile time.
+    pub fn receive(&mut self, frame: Frame) -> Result<(), &'static str> {
+        if let Some(message) = self.decode(&frame)? {
+            self.0.status.apply(message);
         }
 
         Ok(())
     }
+
+    /// See [`Bmu::receive_at`].
+    pub fn receive_at(&mut self, now_ms: u32, frame: Frame) -> Result<(), &'static str> {
+        if let Some(message) = self.decode(&frame)? {
+            self.0.apply_at(now_ms, message);
+        }
+
+        Ok(())
+    }
+
+    fn decode(&self, frame: &Frame) -> Result<Option<BmuMessage>, &'static str> {
+        match window(frame, BASE) {
+            Some((offset, data)) => self.0.decode_offset(offset, data),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<const BASE: u16, const CMUS: usize> Default for FixedBmu<BASE, CMUS> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const BASE: u16, const CMUS: usize> core::ops::Deref for FixedBmu<BASE, CMUS> {
+    type Target = Bmu<CMUS>;
+
+    fn deref(&self) -> &Bmu<CMUS> {
+        &self.0
+    }
+}
+
+impl<const BASE: u16, const CMUS: usize> core::ops::DerefMut for FixedBmu<BASE, CMUS> {
+    fn deref_mut(&mut self) -> &mut Bmu<CMUS> {
+        &mut self.0
+    }
 }
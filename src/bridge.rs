@@ -0,0 +1,93 @@
+//! SocketCAN frame conversions
+//!
+//! Converts between `bxcan` frames and SocketCAN frames, so gateways bridging
+//! an embedded bus to Linux can use this crate on both sides. The orphan
+//! rule prevents converting the two foreign types directly, so SocketCAN
+//! frames are wrapped in [`SocketFrame`].
+//!
+//! SocketCAN marks identifiers above the standard range as extended, so an
+//! extended identifier of `0x7FF` or less becomes standard on the way
+//! through. Error frames have no `bxcan` equivalent and are rejected.
+//!
+//! Enabled with the `socketcan` feature.
+
+use bxcan::{Data, ExtendedId, Frame, Id, StandardId};
+use socketcan::CANFrame;
+
+/// SocketCAN frame convertible to and from a `bxcan` frame
+#[derive(Debug, Clone, Copy)]
+pub struct SocketFrame(pub CANFrame);
+
+impl From<CANFrame> for SocketFrame {
+    fn from(frame: CANFrame) -> Self {
+        Self(frame)
+    }
+}
+
+impl From<SocketFrame> for CANFrame {
+    fn from(frame: SocketFrame) -> Self {
+        frame.0
+    }
+}
+
+impl TryFrom<&Frame> for SocketFrame {
+    type Error = &'static str;
+
+    fn try_from(frame: &Frame) -> Result<Self, Self::Error> {
+        let id = match frame.id() {
+            Id::Standard(id) => id.as_raw() as u32,
+            Id::Extended(id) => id.as_raw(),
+        };
+
+        // remote frames carry their length as placeholder data
+        let placeholder = [0; 8];
+        let (data, remote) = match frame.data() {
+            Some(data) => (&data[..], false),
+            None => (&placeholder[..frame.dlc() as usize], true),
+        };
+
+        CANFrame::new(id, data, remote, false)
+            .map(Self)
+            .map_err(|_| "frame not representable in SocketCAN")
+    }
+}
+
+impl TryFrom<SocketFrame> for Frame {
+    type Error = &'static str;
+
+    fn try_from(frame: SocketFrame) -> Result<Self, Self::Error> {
+        let frame = frame.0;
+
+        if frame.is_error() {
+            return Err("SocketCAN error frame");
+        }
+
+        let id: Id = if frame.is_extended() {
+            ExtendedId::new(frame.id())
+                .ok_or("extended identifier out of range")?
+                .into()
+        } else {
+            StandardId::new(frame.id() as u16)
+                .ok_or("standard identifier out of range")?
+                .into()
+        };
+
+        if frame.is_rtr() {
+            return Ok(Frame::new_remote(id, frame.data().len() as u8));
+        }
+
+        let data = Data::new(frame.data()).ok_or("more than 8 data bytes")?;
+
+        Ok(Frame::new_data(id, data))
+    }
+}
+
+/// Convert a `bxcan` frame to a SocketCAN frame.
+pub fn to_socketcan(frame: &Frame) -> Result<CANFrame, &'static str> {
+    SocketFrame::try_from(frame).map(CANFrame::from)
+}
+
+/// Convert a SocketCAN frame to a `bxcan` frame.
+pub fn from_socketcan(frame: CANFrame) -> Result<Frame, &'static str> {
+    Frame::try_from(SocketFrame(frame))
+}
@@ -0,0 +1,140 @@
+//! Energy budget
+//!
+//! Finds the fastest steady cruise speed that reaches a distance target on
+//! the energy remaining in the pack plus the solar energy expected on the
+//! way, the calculation at the heart of most race strategies.
+//!
+//! Consumption comes from a [`Consumption`] model in watt-hours per
+//! kilometre, such as the [`RoadLoad`] model or one fitted from logged
+//! drives. Solar power is taken as a steady average, so the slower the car
+//! drives the more solar energy it collects over the distance.
+
+/// Energy consumption model
+pub trait Consumption {
+    /// Energy drawn from the pack at a steady speed, in watt-hours per
+    /// kilometre.
+    fn wh_per_km(&self, speed_kmh: f32) -> f32;
+}
+
+/// Road load consumption model
+///
+/// Rolling resistance costs a fixed energy per kilometre, aerodynamic drag
+/// grows with the square of speed and auxiliary loads draw a fixed power.
+#[derive(Debug, Clone, Copy)]
+pub struct RoadLoad {
+    /// Rolling resistance energy in watt-hours per kilometre
+    pub rolling: f32,
+    /// Aerodynamic energy at 1 km/h in watt-hours per kilometre
+    pub aero: f32,
+    /// Auxiliary load power in watts
+    pub auxiliary: f32,
+    /// Drivetrain efficiency as a fraction
+    pub efficiency: f32,
+}
+
+impl Default for RoadLoad {
+    fn default() -> Self {
+        Self {
+            rolling: 10.0,
+            aero: 0.0025,
+            auxiliary: 30.0,
+            efficiency: 0.95,
+        }
+    }
+}
+
+impl Consumption for RoadLoad {
+    fn wh_per_km(&self, speed_kmh: f32) -> f32 {
+        let traction = (self.rolling + self.aero * speed_kmh * speed_kmh) / self.efficiency;
+
+        traction + self.auxiliary / speed_kmh.max(f32::EPSILON)
+    }
+}
+
+/// Energy budget configuration
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Slowest speed worth driving in km/h
+    pub min_speed_kmh: f32,
+    /// Fastest speed allowed in km/h
+    pub max_speed_kmh: f32,
+    /// Pack energy kept in reserve at the target in watt-hours
+    pub reserve_wh: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            min_speed_kmh: 30.0,
+            max_speed_kmh: 110.0,
+            reserve_wh: 200.0,
+        }
+    }
+}
+
+/// Energy budget planner
+#[derive(Debug, Clone, Copy)]
+pub struct EnergyBudget<M> {
+    config: Config,
+    model: M,
+}
+
+impl<M: Consumption> EnergyBudget<M> {
+    /// Create a new planner using a consumption model.
+    pub fn new(config: Config, model: M) -> Self {
+        Self { config, model }
+    }
+
+    /// Pack energy left on arrival in watt-hours when cruising at
+    /// `speed_kmh`, negative if the pack would run out.
+    pub fn arrival_energy(
+        &self,
+        remaining_wh: f32,
+        solar_w: f32,
+        distance_km: f32,
+        speed_kmh: f32,
+    ) -> f32 {
+        let hours = distance_km / speed_kmh;
+
+        remaining_wh + solar_w * hours - self.model.wh_per_km(speed_kmh) * distance_km
+    }
+
+    /// Fastest cruise speed in km/h reaching `distance_km` with the reserve
+    /// intact, given `remaining_wh` in the pack and `solar_w` of average
+    /// solar power.
+    ///
+    /// Returns `None` if even the minimum speed doesn't get there. The
+    /// model's consumption, net of solar, is assumed to grow with speed.
+    pub fn target_speed(&self, remaining_wh: f32, solar_w: f32, distance_km: f32) -> Option<f32> {
+        if !(remaining_wh.is_finite() && solar_w.is_finite() && distance_km.is_finite()) {
+            return None;
+        }
+
+        let (min, max) = (self.config.min_speed_kmh, self.config.max_speed_kmh);
+        let surplus = |speed| {
+            self.arrival_energy(remaining_wh, solar_w, distance_km, speed) - self.config.reserve_wh
+        };
+
+        if distance_km <= 0.0 || surplus(max) >= 0.0 {
+            return Some(max);
+        }
+
+        if surplus(min) < 0.0 {
+            return None;
+        }
+
+        // bisect to well within a tenth of a km/h
+        let (mut low, mut high) = (min, max);
+        for _ in 0..24 {
+            let mid = (low + high) / 2.0;
+
+            if surplus(mid) >= 0.0 {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+
+        Some(low)
+    }
+}
@@ -0,0 +1,124 @@
+//! CAN bus health
+//!
+//! Trends the receive and transmit error counters broadcast by the
+//! WaveSculptor, surfacing wiring and termination problems before the
+//! controller goes bus-off.
+//!
+//! A CAN controller becomes error passive once either counter reaches 128,
+//! and goes bus-off when the transmit counter exceeds 255.
+
+use crate::wavesculptor::Status;
+
+/// Counter value at which a CAN controller raises its error warning
+pub const ERROR_WARNING_LIMIT: u8 = 96;
+
+/// Counter value at which a CAN controller becomes error passive
+pub const ERROR_PASSIVE_LIMIT: u8 = 128;
+
+/// Bus health monitor configuration
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Period over which error rates are measured in milliseconds
+    pub window_ms: u32,
+    /// Error counter growth considered a rising error rate, in counts per
+    /// second
+    pub rising_rate: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            window_ms: 5000,
+            rising_rate: 1.0,
+        }
+    }
+}
+
+/// Bus health summary
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct BusHealth {
+    /// Latest receive error count
+    pub rx_errors: u8,
+    /// Latest transmit error count
+    pub tx_errors: u8,
+    /// Receive error counter growth over the last window in counts per
+    /// second
+    pub rx_rate: f32,
+    /// Transmit error counter growth over the last window in counts per
+    /// second
+    pub tx_rate: f32,
+    /// Either error counter is growing faster than the configured rate
+    pub rising: bool,
+    /// Either error counter has reached the error warning limit
+    pub warning: bool,
+    /// Either error counter has reached the error passive limit
+    pub error_passive: bool,
+}
+
+impl BusHealth {
+    /// Is the bus free of any error indication?
+    pub fn is_healthy(&self) -> bool {
+        !(self.rising || self.warning || self.error_passive)
+    }
+}
+
+/// Bus health monitor
+#[derive(Debug, Clone, Copy)]
+pub struct BusHealthMonitor {
+    config: Config,
+    window_start: Option<(u32, u8, u8)>,
+    health: BusHealth,
+}
+
+impl BusHealthMonitor {
+    /// Create a new monitor with no samples.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            window_start: None,
+            health: BusHealth::default(),
+        }
+    }
+
+    /// Latest bus health summary.
+    pub fn health(&self) -> BusHealth {
+        self.health
+    }
+
+    /// Update from the latest WaveSculptor status.
+    pub fn update(&mut self, now_ms: u32, status: &Status) -> BusHealth {
+        if let (Some(rx), Some(tx)) = (status.can_rx_error_count, status.can_tx_error_count) {
+            self.update_counts(now_ms, rx, tx);
+        }
+
+        self.health
+    }
+
+    /// Update from receive and transmit error counts.
+    pub fn update_counts(&mut self, now_ms: u32, rx_errors: u8, tx_errors: u8) -> BusHealth {
+        self.health.rx_errors = rx_errors;
+        self.health.tx_errors = tx_errors;
+
+        let worst = rx_errors.max(tx_errors);
+        self.health.warning = worst >= ERROR_WARNING_LIMIT;
+        self.health.error_passive = worst >= ERROR_PASSIVE_LIMIT;
+
+        let (start_ms, start_rx, start_tx) = *self
+            .window_start
+            .get_or_insert((now_ms, rx_errors, tx_errors));
+
+        let elapsed_ms = now_ms.wrapping_sub(start_ms);
+        if elapsed_ms >= self.config.window_ms.max(1) {
+            let seconds = elapsed_ms as f32 / 1000.0;
+
+            self.health.rx_rate = (rx_errors as f32 - start_rx as f32) / seconds;
+            self.health.tx_rate = (tx_errors as f32 - start_tx as f32) / seconds;
+            self.health.rising =
+                self.health.rx_rate.max(self.health.tx_rate) > self.config.rising_rate;
+
+            self.window_start = Some((now_ms, rx_errors, tx_errors));
+        }
+
+        self.health
+    }
+}
@@ -0,0 +1,127 @@
+//! Bus-off recovery
+//!
+//! Sequences recovery of the CAN peripheral after it goes bus-off: device
+//! statuses are marked stale straight away, then after an exponentially
+//! growing back-off the peripheral is restarted, its acceptance filters
+//! reinstalled and the receive watchdogs re-armed.
+
+/// Hardware and application hooks used during recovery
+pub trait BusOffHal {
+    /// Is the CAN peripheral bus-off?
+    fn is_bus_off(&self) -> bool;
+
+    /// Restart the CAN peripheral, leaving the bus-off state.
+    fn restart(&mut self);
+
+    /// Reinstall the acceptance filters after a restart.
+    fn configure_filters(&mut self);
+
+    /// Re-arm the watchdogs supervising received broadcasts.
+    fn rearm_watchdogs(&mut self);
+
+    /// Mark device statuses as stale, as broadcasts were missed.
+    fn mark_stale(&mut self);
+}
+
+/// Recovery configuration
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Back-off before the first restart attempt in milliseconds
+    pub initial_backoff_ms: u32,
+    /// Longest back-off between restart attempts in milliseconds
+    pub max_backoff_ms: u32,
+    /// Time without a bus-off after which the back-off starts over, in
+    /// milliseconds
+    pub stable_ms: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            initial_backoff_ms: 100,
+            max_backoff_ms: 5000,
+            stable_ms: 10000,
+        }
+    }
+}
+
+/// Recovery state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    /// Peripheral is on the bus
+    Running,
+    /// Peripheral is bus-off, waiting before the next restart attempt
+    BackOff,
+}
+
+/// Bus-off recovery sequencer
+#[derive(Debug, Clone, Copy)]
+pub struct BusOffRecovery {
+    config: Config,
+    state: State,
+    entered_ms: u32,
+    attempts: u32,
+}
+
+impl BusOffRecovery {
+    /// Create a new sequencer, assuming the peripheral is running.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            state: State::Running,
+            entered_ms: 0,
+            attempts: 0,
+        }
+    }
+
+    /// Current recovery state.
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// Restart attempts since the bus was last stable.
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// Back-off before the next restart attempt in milliseconds.
+    pub fn backoff_ms(&self) -> u32 {
+        let shift = self.attempts.min(31);
+
+        self.config
+            .initial_backoff_ms
+            .saturating_mul(1 << shift)
+            .min(self.config.max_backoff_ms)
+    }
+
+    /// Advance the recovery sequence, calling into `hal` as needed.
+    pub fn update(&mut self, now_ms: u32, hal: &mut impl BusOffHal) -> State {
+        let elapsed = now_ms.wrapping_sub(self.entered_ms);
+
+        match self.state {
+            State::Running => {
+                if hal.is_bus_off() {
+                    hal.mark_stale();
+
+                    self.state = State::BackOff;
+                    self.entered_ms = now_ms;
+                } else if self.attempts > 0 && elapsed >= self.config.stable_ms {
+                    self.attempts = 0;
+                }
+            }
+            State::BackOff => {
+                if elapsed >= self.backoff_ms() {
+                    hal.restart();
+                    hal.configure_filters();
+                    hal.rearm_watchdogs();
+
+                    self.attempts = self.attempts.saturating_add(1);
+                    self.state = State::Running;
+                    self.entered_ms = now_ms;
+                }
+            }
+        }
+
+        self.state
+    }
+}
@@ -0,0 +1,249 @@
+//! Elcon/TC charger
+//!
+//! Implements the CAN protocol shared by Elcon, TC Charger and compatible
+//! chargers: a command frame from the BMS setting the charging voltage and
+//! current limits, and a status frame broadcast by the charger. Both use
+//! extended identifiers and big endian values in 0.1 V and 0.1 A steps.
+//!
+//! The charger stops when commands stop arriving, so they should be sent
+//! about once a second. [`ChargeControl`] derives them from the BMU's
+//! charger control broadcast.
+
+use bitflags::bitflags;
+use bxcan::{Data, ExtendedId, Frame, Id};
+
+use crate::bms::Status;
+
+/// Command identifier, from the BMS to the charger
+pub const ID_COMMAND: u32 = 0x1806_E5F4;
+
+/// Status identifier, broadcast by the charger
+pub const ID_STATUS: u32 = 0x18FF_50E5;
+
+/// Largest value that can be commanded, in volts or amps
+const MAX_VALUE: f32 = u16::MAX as f32 / 10.0;
+
+bitflags! {
+    /// Charger fault flags
+    pub struct ChargerFlags: u8 {
+        const HARDWARE_FAILURE      = 1 << 0;
+        const OVER_TEMPERATURE      = 1 << 1;
+        const INPUT_VOLTAGE         = 1 << 2;
+        const NO_BATTERY            = 1 << 3;
+        const COMMUNICATION_TIMEOUT = 1 << 4;
+    }
+}
+
+/// Status broadcast by the charger
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ChargerStatus {
+    /// Output voltage in volts
+    pub voltage: Option<f32>,
+    /// Output current in amps
+    pub current: Option<f32>,
+    /// Fault flags
+    pub flags: Option<ChargerFlags>,
+}
+
+impl ChargerStatus {
+    /// Is the charger reporting a fault?
+    pub fn is_faulted(&self) -> bool {
+        self.flags.is_some_and(|flags| !flags.is_empty())
+    }
+}
+
+/// Charger command
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChargeCommand {
+    /// Maximum charging voltage in volts
+    pub voltage: f32,
+    /// Maximum charging current in amps
+    pub current: f32,
+    /// Charger output is enabled
+    pub enable: bool,
+}
+
+impl ChargeCommand {
+    /// Command stopping the charger.
+    pub fn stop() -> Self {
+        Self {
+            voltage: 0.0,
+            current: 0.0,
+            enable: false,
+        }
+    }
+}
+
+/// Elcon/TC charger
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Charger {
+    status: ChargerStatus,
+}
+
+impl Charger {
+    /// Create a new charger instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the status most recently broadcast by the charger
+    pub fn status(&self) -> ChargerStatus {
+        self.status
+    }
+
+    /// Decode a frame broadcast by the charger.
+    pub fn receive(&mut self, frame: Frame) -> Result<(), &'static str> {
+        if let Some(status) =
+            decode_status(&frame).inspect_err(|error| log_warn!("charger frame: {}", error))?
+        {
+            if status.is_faulted() && !self.status.is_faulted() {
+                log_warn!("charger fault {:?}", status.flags);
+            }
+
+            self.status = status;
+        }
+
+        Ok(())
+    }
+
+    /// Charger command
+    ///
+    /// Non-finite or negative limits result in a stop command, use
+    /// [`try_command`](Self::try_command) to reject them instead.
+    pub fn command(self, command: ChargeCommand) -> Frame {
+        self.try_command(command)
+            .unwrap_or_else(|_| encode_command(ChargeCommand::stop()))
+    }
+
+    /// Validated charger command
+    ///
+    /// Rejects non-finite or negative limits, and clamps them to the largest
+    /// value the protocol carries.
+    pub fn try_command(self, command: ChargeCommand) -> Result<Frame, &'static str> {
+        if !(command.voltage.is_finite() && command.current.is_finite()) {
+            return Err("charger limits must be finite");
+        }
+
+        if command.voltage < 0.0 || command.current < 0.0 {
+            return Err("charger limits must not be negative");
+        }
+
+        Ok(encode_command(command))
+    }
+
+    /// Command stopping the charger
+    pub fn stop(self) -> Frame {
+        encode_command(ChargeCommand::stop())
+    }
+}
+
+fn encode_command(command: ChargeCommand) -> Frame {
+    let id = ExtendedId::new(ID_COMMAND).unwrap();
+
+    let voltage = to_tenths(command.voltage).to_be_bytes();
+    let current = to_tenths(command.current).to_be_bytes();
+
+    let data = [
+        voltage[0],
+        voltage[1],
+        current[0],
+        current[1],
+        // zero starts charging, one stops it
+        !command.enable as u8,
+        0,
+        0,
+        0,
+    ];
+
+    Frame::new_data(id, Data::new(&data).unwrap())
+}
+
+fn to_tenths(value: f32) -> u16 {
+    // rounded by hand, `f32::round` needs std
+    (value.clamp(0.0, MAX_VALUE) * 10.0 + 0.5) as u16
+}
+
+/// Decode a charger status frame.
+///
+/// Returns `None` for frames that are not charger status broadcasts.
+pub fn decode_status(frame: &Frame) -> Result<Option<ChargerStatus>, &'static str> {
+    let (Id::Extended(id), Some(data)) = (frame.id(), frame.data()) else {
+        return Ok(None);
+    };
+
+    if id.as_raw() != ID_STATUS {
+        return Ok(None);
+    }
+
+    if data.len() < 5 {
+        return Err("charger status frame too short");
+    }
+
+    Ok(Some(ChargerStatus {
+        voltage: Some(u16::from_be_bytes([data[0], data[1]]) as f32 / 10.0),
+        current: Some(u16::from_be_bytes([data[2], data[3]]) as f32 / 10.0),
+        flags: Some(ChargerFlags::from_bits_truncate(data[4])),
+    }))
+}
+
+/// Charge control configuration
+#[derive(Debug, Clone, Copy)]
+pub struct ChargeControlConfig {
+    /// Maximum pack charging voltage in volts
+    pub max_voltage: f32,
+    /// Maximum charging current in amps
+    pub max_current: f32,
+    /// Charging cell voltage error below which the current tapers to zero
+    /// in millivolts
+    pub taper_voltage: u16,
+}
+
+impl Default for ChargeControlConfig {
+    fn default() -> Self {
+        Self {
+            max_voltage: 134.4,
+            max_current: 10.0,
+            taper_voltage: 50,
+        }
+    }
+}
+
+/// Charger control loop driven by the BMU's charger control broadcast
+///
+/// The charging current tapers in proportion to the headroom between the
+/// highest cell and the maximum cell voltage, reaching zero when it is used
+/// up. Charging stops when the headroom or cell temperature margin is
+/// unknown or exhausted.
+#[derive(Debug, Clone, Copy)]
+pub struct ChargeControl {
+    config: ChargeControlConfig,
+}
+
+impl ChargeControl {
+    /// Create a new control loop.
+    pub fn new(config: ChargeControlConfig) -> Self {
+        Self { config }
+    }
+
+    /// Command derived from the latest BMU status.
+    pub fn update<const CMUS: usize>(&self, status: &Status<CMUS>) -> ChargeCommand {
+        let (Some(voltage_error), Some(temperature_margin)) = (
+            status.charging_cell_voltage_error,
+            status.cell_temperature_margin,
+        ) else {
+            return ChargeCommand::stop();
+        };
+
+        if voltage_error <= 0 || temperature_margin <= 0 {
+            return ChargeCommand::stop();
+        }
+
+        let taper = (voltage_error as f32 / self.config.taper_voltage.max(1) as f32).min(1.0);
+
+        ChargeCommand {
+            voltage: self.config.max_voltage,
+            current: self.config.max_current * taper,
+            enable: true,
+        }
+    }
+}
@@ -0,0 +1,247 @@
+//! Charge management
+//!
+//! Sequences a charging session end to end:
+//!
+//! 1. `Precharge`: contactors requested closed until the BMU reports its
+//!    precharge has finished.
+//! 2. `ConstantCurrent`: full charging current until the highest cell
+//!    reaches the constant voltage threshold.
+//! 3. `ConstantVoltage`: current tapered by [`ChargeControl`] until it falls
+//!    below the completion current.
+//! 4. `BalancingHold`: charger stopped with the contactors closed so the BMU
+//!    can balance the cells, until they are within the balance spread or the
+//!    hold times out.
+//! 5. `Complete`: contactors open.
+//!
+//! Pack limits being exceeded, a charger fault or the BMU leaving its run
+//! state while charging move the session into `Fault`, stopping the charger
+//! and opening the contactors.
+
+use crate::bms::{PrechargeState, Status};
+use crate::charger::{ChargeCommand, ChargeControl, ChargeControlConfig, Charger, ChargerStatus};
+use crate::pack::PackLimits;
+use crate::tx::TxSink;
+
+/// Charge management configuration
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Charger limits and constant voltage taper
+    pub control: ChargeControlConfig,
+    /// Pack limits guarding the session
+    pub limits: PackLimits,
+    /// Highest cell voltage at which constant voltage charging starts in
+    /// millivolts
+    pub constant_voltage_threshold: u16,
+    /// Charging current below which constant voltage charging finishes in
+    /// amps
+    pub complete_current: f32,
+    /// Cell voltage spread at which balancing is complete in millivolts
+    pub balance_spread: u16,
+    /// Longest balancing hold in milliseconds
+    pub balance_hold_ms: u32,
+    /// Maximum time allowed for the BMU to precharge in milliseconds
+    pub precharge_timeout_ms: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            control: ChargeControlConfig::default(),
+            limits: PackLimits::default(),
+            constant_voltage_threshold: 4150,
+            complete_current: 0.5,
+            balance_spread: 10,
+            balance_hold_ms: 30 * 60 * 1000,
+            precharge_timeout_ms: 10_000,
+        }
+    }
+}
+
+/// Charging session state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChargeState {
+    Idle,
+    Precharge,
+    ConstantCurrent,
+    ConstantVoltage,
+    BalancingHold,
+    Complete,
+    Fault,
+}
+
+/// Charge manager outputs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChargeOutputs {
+    /// Pack contactors requested closed
+    pub contactors: bool,
+    /// Command to send to the charger
+    pub command: ChargeCommand,
+}
+
+/// Charging session state machine
+#[derive(Debug, Clone, Copy)]
+pub struct ChargeManager {
+    config: Config,
+    control: ChargeControl,
+    state: ChargeState,
+    entered_ms: u32,
+}
+
+impl ChargeManager {
+    /// Create a new, idle, charge manager.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            control: ChargeControl::new(config.control),
+            state: ChargeState::Idle,
+            entered_ms: 0,
+        }
+    }
+
+    /// Current session state.
+    pub fn state(&self) -> ChargeState {
+        self.state
+    }
+
+    /// Begin a charging session.
+    pub fn start(&mut self, now_ms: u32) -> Result<(), &'static str> {
+        if !matches!(self.state, ChargeState::Idle | ChargeState::Complete) {
+            return Err("charging can only start from idle or complete");
+        }
+
+        self.transition(ChargeState::Precharge, now_ms);
+
+        Ok(())
+    }
+
+    /// Stop charging and return to idle, clearing any fault.
+    pub fn stop(&mut self, now_ms: u32) {
+        self.transition(ChargeState::Idle, now_ms);
+    }
+
+    /// Advance the session from the latest BMU and charger status, returning
+    /// the outputs to apply.
+    pub fn update<const CMUS: usize>(
+        &mut self,
+        now_ms: u32,
+        status: &Status<CMUS>,
+        charger: &ChargerStatus,
+    ) -> ChargeOutputs {
+        let elapsed = now_ms.wrapping_sub(self.entered_ms);
+        let charging = matches!(
+            self.state,
+            ChargeState::ConstantCurrent
+                | ChargeState::ConstantVoltage
+                | ChargeState::BalancingHold
+        );
+
+        if charging || self.state == ChargeState::Precharge {
+            let alarms = self.config.limits.check(status);
+
+            if !alarms.is_empty() {
+                log_warn!("charging stopped, pack alarms {:?}", alarms);
+                self.transition(ChargeState::Fault, now_ms);
+            } else if charger.is_faulted() {
+                log_warn!("charging stopped, charger fault {:?}", charger.flags);
+                self.transition(ChargeState::Fault, now_ms);
+            } else if charging && status.precharge_state != Some(PrechargeState::Run) {
+                log_warn!("charging stopped, BMU left run state");
+                self.transition(ChargeState::Fault, now_ms);
+            }
+        }
+
+        match self.state {
+            ChargeState::Precharge => {
+                if status.precharge_state == Some(PrechargeState::Run) {
+                    self.transition(ChargeState::ConstantCurrent, now_ms);
+                } else if elapsed >= self.config.precharge_timeout_ms {
+                    log_warn!("charging stopped, precharge timed out");
+                    self.transition(ChargeState::Fault, now_ms);
+                }
+            }
+            ChargeState::ConstantCurrent => {
+                if status
+                    .maximum_voltage_cell
+                    .is_some_and(|cell| cell.voltage >= self.config.constant_voltage_threshold)
+                {
+                    self.transition(ChargeState::ConstantVoltage, now_ms);
+                }
+            }
+            ChargeState::ConstantVoltage => {
+                let measured = status
+                    .pack_current_ma
+                    .map(|current| -current as f32 / 1000.0);
+                let commanded = self.control.update(status).current;
+
+                if commanded < self.config.complete_current
+                    || measured.is_some_and(|current| current < self.config.complete_current)
+                {
+                    self.transition(ChargeState::BalancingHold, now_ms);
+                }
+            }
+            ChargeState::BalancingHold => {
+                let spread = status
+                    .maximum_voltage_cell
+                    .zip(status.minimum_voltage_cell)
+                    .map(|(max, min)| max.voltage.saturating_sub(min.voltage));
+
+                if spread.is_some_and(|spread| spread <= self.config.balance_spread)
+                    || elapsed >= self.config.balance_hold_ms
+                {
+                    self.transition(ChargeState::Complete, now_ms);
+                }
+            }
+            ChargeState::Idle | ChargeState::Complete | ChargeState::Fault => {}
+        }
+
+        self.outputs(status)
+    }
+
+    /// Outputs for the current state.
+    fn outputs<const CMUS: usize>(&self, status: &Status<CMUS>) -> ChargeOutputs {
+        let command = match self.state {
+            ChargeState::ConstantCurrent => ChargeCommand {
+                voltage: self.config.control.max_voltage,
+                current: self.config.control.max_current,
+                enable: true,
+            },
+            ChargeState::ConstantVoltage => self.control.update(status),
+            _ => ChargeCommand::stop(),
+        };
+
+        ChargeOutputs {
+            contactors: matches!(
+                self.state,
+                ChargeState::Precharge
+                    | ChargeState::ConstantCurrent
+                    | ChargeState::ConstantVoltage
+                    | ChargeState::BalancingHold
+            ),
+            command,
+        }
+    }
+
+    /// Advance the session from the latest BMU status and the charger's
+    /// reported status, sending the charger command through `sink`.
+    pub fn update_into<const CMUS: usize>(
+        &mut self,
+        now_ms: u32,
+        status: &Status<CMUS>,
+        charger: &Charger,
+        sink: &mut impl TxSink,
+    ) -> Result<ChargeOutputs, &'static str> {
+        let outputs = self.update(now_ms, status, &charger.status());
+        sink.transmit(&charger.command(outputs.command))?;
+
+        Ok(outputs)
+    }
+
+    fn transition(&mut self, state: ChargeState, now_ms: u32) {
+        if state != self.state {
+            log_info!("charging {:?} -> {:?}", self.state, state);
+        }
+
+        self.state = state;
+        self.entered_ms = now_ms;
+    }
+}
@@ -0,0 +1,19 @@
+//! Time source
+//!
+//! Every monitor takes the time as a `now_ms` argument, so callers read it
+//! from a [`Clock`] once per control loop iteration. Time is a wrapping
+//! millisecond counter, compared with `wrapping_sub`.
+//!
+//! The `test_support` feature provides a manually advanced virtual clock.
+
+/// Millisecond time source
+pub trait Clock {
+    /// Current time in milliseconds, wrapping.
+    fn now_ms(&self) -> u32;
+}
+
+impl<T: Clock + ?Sized> Clock for &T {
+    fn now_ms(&self) -> u32 {
+        (**self).now_ms()
+    }
+}
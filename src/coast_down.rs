@@ -0,0 +1,183 @@
+//! Coast-down test capture
+//!
+//! Records the vehicle velocity while coasting with no bus current and fits
+//! the decay to `-dv/dt = A + B v²`, where `A` comes from rolling
+//! resistance and `B` from aerodynamic drag. With the vehicle mass these
+//! give the rolling resistance coefficient and drag area for aero and
+//! rolling testing.
+//!
+//! Each coasting run replaces the previous one, so the fit is of the most
+//! recent run. Runs should be made on flat ground in still air.
+
+use crate::wavesculptor::Status;
+
+/// Standard gravity in m/s²
+const GRAVITY: f32 = 9.80665;
+
+/// Coast-down capture configuration
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Vehicle mass including driver in kilograms
+    pub mass_kg: f32,
+    /// Air density in kg/m³
+    pub air_density: f32,
+    /// Bus current magnitude below which the vehicle is coasting in amps
+    pub coast_current: f32,
+    /// Speed below which samples are not recorded in m/s
+    pub min_speed: f32,
+    /// Time between samples in milliseconds
+    pub sample_interval_ms: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            mass_kg: 300.0,
+            air_density: 1.2,
+            coast_current: 0.5,
+            min_speed: 3.0,
+            sample_interval_ms: 500,
+        }
+    }
+}
+
+/// Velocity sample
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Sample {
+    /// Timestamp in milliseconds
+    pub time_ms: u32,
+    /// Vehicle velocity in m/s
+    pub velocity: f32,
+}
+
+/// Road load parameters fitted to a coast-down run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CoastDownFit {
+    /// Rolling resistance coefficient
+    pub rolling_resistance: f32,
+    /// Drag coefficient times frontal area in m²
+    pub drag_area: f32,
+}
+
+/// Coast-down capture of up to `N` samples
+#[derive(Debug, Clone, Copy)]
+pub struct CoastDown<const N: usize> {
+    config: Config,
+    samples: [Sample; N],
+    len: usize,
+    coasting: bool,
+}
+
+impl<const N: usize> CoastDown<N> {
+    /// Create a new capture with no samples.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            samples: [Sample::default(); N],
+            len: 0,
+            coasting: false,
+        }
+    }
+
+    /// Is the vehicle coasting?
+    pub fn is_coasting(&self) -> bool {
+        self.coasting
+    }
+
+    /// Samples of the latest run, oldest first.
+    pub fn samples(&self) -> &[Sample] {
+        &self.samples[..self.len]
+    }
+
+    /// Forget the latest run.
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Update from the latest WaveSculptor status and return whether the
+    /// vehicle is coasting.
+    pub fn update(&mut self, now_ms: u32, status: &Status) -> bool {
+        match (status.vehicle_velocity, status.bus_current) {
+            (Some(velocity), Some(bus_current)) => {
+                self.update_sample(now_ms, velocity, bus_current)
+            }
+            _ => {
+                self.coasting = false;
+                false
+            }
+        }
+    }
+
+    /// Update from a vehicle velocity in m/s and bus current in amps and
+    /// return whether the vehicle is coasting.
+    ///
+    /// Once the buffer is full further samples of the run are dropped.
+    pub fn update_sample(&mut self, now_ms: u32, velocity: f32, bus_current: f32) -> bool {
+        let coasting = bus_current.abs() < self.config.coast_current
+            && velocity.abs() >= self.config.min_speed;
+
+        if coasting && !self.coasting {
+            log_debug!("coast-down run started at {} m/s", velocity);
+            self.len = 0;
+        }
+        self.coasting = coasting;
+
+        if !coasting || self.len == N {
+            return coasting;
+        }
+
+        let due = self.len == 0
+            || now_ms.wrapping_sub(self.samples[self.len - 1].time_ms)
+                >= self.config.sample_interval_ms;
+
+        if due {
+            self.samples[self.len] = Sample {
+                time_ms: now_ms,
+                velocity: velocity.abs(),
+            };
+            self.len += 1;
+        }
+
+        coasting
+    }
+
+    /// Fit the road load parameters to the latest run.
+    ///
+    /// Needs at least three samples over a range of speeds.
+    pub fn fit(&self) -> Option<CoastDownFit> {
+        if self.len < 3 {
+            return None;
+        }
+
+        // least squares fit of deceleration against speed squared, taken
+        // between consecutive samples
+        let (mut n, mut sx, mut sy, mut sxx, mut sxy) = (0.0, 0.0, 0.0, 0.0, 0.0);
+
+        for pair in self.samples().windows(2) {
+            let dt = pair[1].time_ms.wrapping_sub(pair[0].time_ms) as f32 / 1000.0;
+            let mean = (pair[0].velocity + pair[1].velocity) / 2.0;
+
+            let x = mean * mean;
+            let y = (pair[0].velocity - pair[1].velocity) / dt;
+
+            n += 1.0;
+            sx += x;
+            sy += y;
+            sxx += x * x;
+            sxy += x * y;
+        }
+
+        let denominator = n * sxx - sx * sx;
+        if denominator <= f32::EPSILON {
+            return None;
+        }
+
+        let drag = (n * sxy - sx * sy) / denominator;
+        let rolling = (sy - drag * sx) / n;
+
+        Some(CoastDownFit {
+            rolling_resistance: rolling / GRAVITY,
+            drag_area: 2.0 * self.config.mass_kg * drag / self.config.air_density,
+        })
+    }
+}
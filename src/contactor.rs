@@ -0,0 +1,341 @@
+//! Contactor control with feedback verification
+//!
+//! Commands contactors and verifies the result against the BMU's contactor
+//! driver status and, where fitted, auxiliary feedback contacts. A contactor
+//! that fails to close, fails to open (welded) or whose driver reports an
+//! error raises a latched fault.
+//!
+//! [`BrownoutMonitor`] warns of a sagging contactor supply before the
+//! contactors drop out.
+
+use crate::bms::{ContactorDriverStatus, Status};
+
+/// Contactor output
+pub trait Contactor {
+    /// Drive the contactor coil.
+    fn set(&mut self, closed: bool);
+
+    /// Auxiliary feedback contact state, `None` when not fitted.
+    fn feedback(&self) -> Option<bool> {
+        None
+    }
+}
+
+/// BMU contactor driver channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    One,
+    Two,
+    Three,
+}
+
+impl Channel {
+    /// Driver error flag for this channel.
+    pub fn error_flag(self) -> ContactorDriverStatus {
+        match self {
+            Channel::One => ContactorDriverStatus::CONTACTOR_1_DRIVER_ERROR,
+            Channel::Two => ContactorDriverStatus::CONTACTOR_2_DRIVER_ERROR,
+            Channel::Three => ContactorDriverStatus::CONTACTOR_3_DRIVER_ERROR,
+        }
+    }
+
+    /// Output on flag for this channel.
+    pub fn output_flag(self) -> ContactorDriverStatus {
+        match self {
+            Channel::One => ContactorDriverStatus::CONTACTOR_1_OUTPUT_ON,
+            Channel::Two => ContactorDriverStatus::CONTACTOR_2_OUTPUT_ON,
+            Channel::Three => ContactorDriverStatus::CONTACTOR_3_OUTPUT_ON,
+        }
+    }
+}
+
+/// Contactor faults
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// The BMU reports an error on the contactor driver
+    DriverError,
+    /// The contactor did not close when commanded
+    FailedToClose,
+    /// The contactor did not open when commanded, likely welded
+    Welded,
+}
+
+/// Supervisor configuration
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Time allowed for the contactor to change state in milliseconds
+    pub verify_time_ms: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            verify_time_ms: 200,
+        }
+    }
+}
+
+/// Contactor supervisor
+#[derive(Debug)]
+pub struct Supervisor<C> {
+    contactor: C,
+    channel: Option<Channel>,
+    config: Config,
+    commanded: bool,
+    commanded_ms: u32,
+    fault: Option<Fault>,
+}
+
+impl<C: Contactor> Supervisor<C> {
+    /// Create a new supervisor with the contactor open.
+    ///
+    /// `channel` is the BMU contactor driver channel the contactor is wired
+    /// to, if any.
+    pub fn new(mut contactor: C, channel: Option<Channel>, config: Config) -> Self {
+        contactor.set(false);
+
+        Self {
+            contactor,
+            channel,
+            config,
+            commanded: false,
+            commanded_ms: 0,
+            fault: None,
+        }
+    }
+
+    /// Commanded contactor state.
+    pub fn is_commanded_closed(&self) -> bool {
+        self.commanded
+    }
+
+    /// Latched fault, if any.
+    pub fn fault(&self) -> Option<Fault> {
+        self.fault
+    }
+
+    /// Clear a latched fault.
+    pub fn clear_fault(&mut self) {
+        self.fault = None;
+    }
+
+    /// Command the contactor.
+    ///
+    /// Requests to close are refused while a fault is latched.
+    pub fn command(&mut self, now_ms: u32, closed: bool) -> Result<(), &'static str> {
+        if closed && self.fault.is_some() {
+            return Err("contactor fault latched");
+        }
+
+        if closed != self.commanded {
+            self.commanded = closed;
+            self.commanded_ms = now_ms;
+        }
+
+        self.contactor.set(closed);
+
+        Ok(())
+    }
+
+    /// Verify the contactor state.
+    ///
+    /// `driver_status` is the most recent status from the BMU's precharge
+    /// broadcast. Any fault opens the contactor and is latched.
+    pub fn update(
+        &mut self,
+        now_ms: u32,
+        driver_status: Option<ContactorDriverStatus>,
+    ) -> Option<Fault> {
+        if let (Some(channel), Some(status)) = (self.channel, driver_status) {
+            if status.contains(channel.error_flag()) {
+                self.latch(Fault::DriverError, now_ms);
+            }
+        }
+
+        if now_ms.wrapping_sub(self.commanded_ms) >= self.config.verify_time_ms {
+            let driver_on = match (self.channel, driver_status) {
+                (Some(channel), Some(status)) => Some(status.contains(channel.output_flag())),
+                _ => None,
+            };
+
+            match (self.commanded, self.contactor.feedback(), driver_on) {
+                (true, Some(false), _) | (true, _, Some(false)) => {
+                    self.latch(Fault::FailedToClose, now_ms)
+                }
+                (false, Some(true), _) => self.latch(Fault::Welded, now_ms),
+                _ => {}
+            }
+        }
+
+        self.fault
+    }
+
+    /// Release the underlying contactor.
+    pub fn release(self) -> C {
+        self.contactor
+    }
+
+    fn latch(&mut self, fault: Fault, now_ms: u32) {
+        if self.fault.is_none() {
+            self.fault = Some(fault);
+        }
+
+        if self.commanded {
+            self.commanded = false;
+            self.commanded_ms = now_ms;
+        }
+
+        self.contactor.set(false);
+    }
+}
+
+/// Weld detector configuration
+#[derive(Debug, Clone, Copy)]
+pub struct WeldDetectorConfig {
+    /// Time allowed for the bus to discharge after opening in milliseconds
+    pub discharge_time_ms: u32,
+    /// Fraction of the pack voltage on the bus indicating a welded contactor
+    pub voltage_fraction: f32,
+    /// Bus voltage below which the bus is always considered discharged, in
+    /// volts
+    pub min_voltage: f32,
+}
+
+impl Default for WeldDetectorConfig {
+    fn default() -> Self {
+        Self {
+            discharge_time_ms: 2000,
+            voltage_fraction: 0.5,
+            min_voltage: 10.0,
+        }
+    }
+}
+
+/// Welded contactor detector
+///
+/// Detects likely welded contactors by the pack voltage remaining on the bus
+/// side after the contactors have been commanded open for long enough for the
+/// bus to discharge, for contactors without feedback contacts.
+#[derive(Debug, Clone, Copy)]
+pub struct WeldDetector {
+    config: WeldDetectorConfig,
+    opened_ms: Option<u32>,
+    alarm: bool,
+}
+
+impl WeldDetector {
+    /// Create a new weld detector.
+    pub fn new(config: WeldDetectorConfig) -> Self {
+        Self {
+            config,
+            opened_ms: None,
+            alarm: false,
+        }
+    }
+
+    /// Is the weld alarm latched?
+    pub fn alarm(&self) -> bool {
+        self.alarm
+    }
+
+    /// Clear the latched alarm.
+    pub fn clear(&mut self) {
+        self.alarm = false;
+    }
+
+    /// Update the detector and return the latched alarm state.
+    ///
+    /// `pack_voltage` is measured on the battery side of the contactors, for
+    /// example by the BMU, and `bus_voltage` on the load side, for example by
+    /// the WaveSculptor, both in volts.
+    pub fn update(
+        &mut self,
+        now_ms: u32,
+        commanded_closed: bool,
+        pack_voltage: Option<f32>,
+        bus_voltage: Option<f32>,
+    ) -> bool {
+        if commanded_closed {
+            self.opened_ms = None;
+            return self.alarm;
+        }
+
+        let opened_ms = *self.opened_ms.get_or_insert(now_ms);
+
+        if now_ms.wrapping_sub(opened_ms) >= self.config.discharge_time_ms {
+            if let (Some(pack), Some(bus)) = (pack_voltage, bus_voltage) {
+                if bus >= self.config.min_voltage && bus >= pack * self.config.voltage_fraction {
+                    self.alarm = true;
+                }
+            }
+        }
+
+        self.alarm
+    }
+}
+
+/// Brownout monitor configuration
+#[derive(Debug, Clone, Copy)]
+pub struct BrownoutConfig {
+    /// Supply voltage below which to warn in volts
+    pub warning_voltage: f32,
+    /// Supply voltage above which the warning clears in volts
+    pub clear_voltage: f32,
+}
+
+impl Default for BrownoutConfig {
+    fn default() -> Self {
+        Self {
+            warning_voltage: 10.5,
+            clear_voltage: 11.0,
+        }
+    }
+}
+
+/// Contactor supply brownout monitor
+///
+/// Warns when the contactor supply voltage reported by the BMU sags, before
+/// the contactors drop out. The warning clears once the supply recovers
+/// above a higher voltage, so a supply hovering at the threshold doesn't
+/// toggle it.
+#[derive(Debug, Clone, Copy)]
+pub struct BrownoutMonitor {
+    config: BrownoutConfig,
+    warning: bool,
+}
+
+impl BrownoutMonitor {
+    /// Create a new monitor without a warning.
+    pub fn new(config: BrownoutConfig) -> Self {
+        Self {
+            config,
+            warning: false,
+        }
+    }
+
+    /// Is the supply browning out?
+    pub fn warning(&self) -> bool {
+        self.warning
+    }
+
+    /// Update from the latest BMU status and return the warning state.
+    pub fn update<const CMUS: usize>(&mut self, status: &Status<CMUS>) -> bool {
+        match status.contactor_supply_volts() {
+            Some(voltage) => self.update_voltage(voltage),
+            None => self.warning,
+        }
+    }
+
+    /// Update from a supply voltage in volts and return the warning state.
+    pub fn update_voltage(&mut self, voltage: f32) -> bool {
+        if !self.warning && voltage < self.config.warning_voltage {
+            log_warn!("contactor supply browning out at {} V", voltage);
+            self.warning = true;
+        } else if self.warning && voltage > self.config.clear_voltage {
+            log_info!("contactor supply recovered at {} V", voltage);
+            self.warning = false;
+        }
+
+        self.warning
+    }
+}
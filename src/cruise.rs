@@ -0,0 +1,311 @@
+//! Cruise control
+//!
+//! A PI controller that holds a set vehicle speed using the velocity measured
+//! by a WaveSculptor, producing drive commands to be sent periodically with
+//! [`DriverControls::drive`](crate::driver_controls::DriverControls::drive).
+//!
+//! [`PowerCruise`] instead holds a set electrical power draw, regulating the
+//! bus current command, which suits long flat stints where the strategy is
+//! set in watts rather than speed.
+
+use crate::driver_controls::{DriveCommand, DriverControls};
+use crate::tx::TxSink;
+use crate::wavesculptor::Status;
+
+/// Cruise control tuning and limits
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Proportional gain in percent current per m/s of speed error
+    pub kp: f32,
+    /// Integral gain in percent current per m/s of speed error per second
+    pub ki: f32,
+    /// Maximum drive current in percent
+    pub max_current_percent: f32,
+    /// Maximum regenerative braking current in percent, zero to only coast
+    pub max_regen_percent: f32,
+    /// Maximum rate the target speed moves towards the set speed in m/s²
+    pub max_acceleration: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            kp: 20.0,
+            ki: 2.0,
+            max_current_percent: 100.0,
+            max_regen_percent: 0.0,
+            max_acceleration: 0.5,
+        }
+    }
+}
+
+/// Cruise control
+#[derive(Debug, Clone, Copy)]
+pub struct CruiseControl {
+    config: Config,
+    enabled: bool,
+    set_speed: Option<f32>,
+    target_speed: f32,
+    integral: f32,
+    last_update_ms: Option<u32>,
+}
+
+impl CruiseControl {
+    /// Create a new, disabled, cruise control instance.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            enabled: false,
+            set_speed: None,
+            target_speed: 0.0,
+            integral: 0.0,
+            last_update_ms: None,
+        }
+    }
+
+    /// Is cruise control currently holding speed?
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// The speed cruise control holds when enabled in m/s.
+    pub fn set_speed(&self) -> Option<f32> {
+        self.set_speed
+    }
+
+    /// Set the held speed to the current vehicle velocity and enable.
+    pub fn set(&mut self, status: &Status) -> Result<(), &'static str> {
+        let velocity = status
+            .vehicle_velocity
+            .ok_or("vehicle velocity not available")?;
+
+        self.set_speed = Some(velocity);
+        self.engage(velocity);
+
+        Ok(())
+    }
+
+    /// Change the held speed in m/s without changing the enabled state.
+    pub fn set_to(&mut self, speed: f32) {
+        self.set_speed = Some(speed);
+    }
+
+    /// Enable at the previously set speed.
+    pub fn resume(&mut self, status: &Status) -> Result<(), &'static str> {
+        if self.set_speed.is_none() {
+            return Err("no set speed to resume");
+        }
+
+        let velocity = status
+            .vehicle_velocity
+            .ok_or("vehicle velocity not available")?;
+
+        self.engage(velocity);
+
+        Ok(())
+    }
+
+    /// Disable, keeping the set speed for a later resume.
+    pub fn disable(&mut self) {
+        self.enabled = false;
+    }
+
+    /// Run the controller.
+    ///
+    /// Returns `None` when disabled, in which case the driver's input should
+    /// be used instead. Loss of velocity feedback disables cruise control.
+    pub fn update(&mut self, now_ms: u32, status: &Status) -> Option<DriveCommand> {
+        if !self.enabled {
+            return None;
+        }
+
+        let (Some(set_speed), Some(velocity)) = (self.set_speed, status.vehicle_velocity) else {
+            self.disable();
+            return None;
+        };
+
+        let dt = match self.last_update_ms {
+            Some(last) => now_ms.wrapping_sub(last) as f32 / 1000.0,
+            None => 0.0,
+        };
+        self.last_update_ms = Some(now_ms);
+
+        // ramp the target towards the set speed to limit acceleration
+        let max_step = self.config.max_acceleration * dt;
+        self.target_speed += (set_speed - self.target_speed).clamp(-max_step, max_step);
+
+        let error = self.target_speed - velocity;
+        let integral = self.integral + error * dt;
+        let output = self.config.kp * error + self.config.ki * integral;
+        let limited = output.clamp(
+            -self.config.max_regen_percent,
+            self.config.max_current_percent,
+        );
+
+        // only integrate while unsaturated to avoid wind-up
+        if output == limited {
+            self.integral = integral;
+        }
+
+        if limited >= 0.0 {
+            Some(DriveCommand::torque(limited))
+        } else {
+            Some(DriveCommand::regen(-limited))
+        }
+    }
+
+    /// Run the controller, sending the drive command through `sink` when
+    /// enabled.
+    pub fn update_into(
+        &mut self,
+        now_ms: u32,
+        status: &Status,
+        controls: DriverControls,
+        sink: &mut impl TxSink,
+    ) -> Result<Option<DriveCommand>, &'static str> {
+        let command = self.update(now_ms, status);
+
+        if let Some(command) = command {
+            sink.transmit(&controls.drive(command))?;
+        }
+
+        Ok(command)
+    }
+
+    fn engage(&mut self, velocity: f32) {
+        self.enabled = true;
+        self.target_speed = velocity;
+        self.integral = 0.0;
+        self.last_update_ms = None;
+    }
+}
+
+/// Constant-power cruise tuning and limits
+#[derive(Debug, Clone, Copy)]
+pub struct PowerConfig {
+    /// Proportional gain in percent bus current per watt of power error
+    pub kp: f32,
+    /// Integral gain in percent bus current per watt of power error per
+    /// second
+    pub ki: f32,
+    /// Maximum bus current in percent
+    pub max_bus_current_percent: f32,
+}
+
+impl Default for PowerConfig {
+    fn default() -> Self {
+        Self {
+            kp: 0.01,
+            ki: 0.02,
+            max_bus_current_percent: 100.0,
+        }
+    }
+}
+
+/// Constant-power cruise control
+///
+/// Produces a bus current command to be sent periodically with
+/// [`DriverControls::motor_power`](crate::driver_controls::DriverControls::motor_power),
+/// alongside a full current torque command such as
+/// [`DriveCommand::torque(100.0)`](DriveCommand::torque).
+#[derive(Debug, Clone, Copy)]
+pub struct PowerCruise {
+    config: PowerConfig,
+    target_power: Option<f32>,
+    integral: f32,
+    last_update_ms: Option<u32>,
+}
+
+impl PowerCruise {
+    /// Create a new, disabled, constant-power cruise instance.
+    pub fn new(config: PowerConfig) -> Self {
+        Self {
+            config,
+            target_power: None,
+            integral: 0.0,
+            last_update_ms: None,
+        }
+    }
+
+    /// Is constant-power cruise currently holding power?
+    pub fn is_enabled(&self) -> bool {
+        self.target_power.is_some()
+    }
+
+    /// The power held in watts, when enabled.
+    pub fn target_power(&self) -> Option<f32> {
+        self.target_power
+    }
+
+    /// Hold `power` in watts, enabling if needed.
+    pub fn set(&mut self, power: f32) -> Result<(), &'static str> {
+        if !power.is_finite() || power < 0.0 {
+            return Err("target power must be finite and non-negative");
+        }
+
+        if self.target_power.is_none() {
+            self.integral = 0.0;
+            self.last_update_ms = None;
+        }
+
+        self.target_power = Some(power);
+
+        Ok(())
+    }
+
+    /// Disable.
+    pub fn disable(&mut self) {
+        self.target_power = None;
+    }
+
+    /// Run the controller, returning the bus current command in percent.
+    ///
+    /// Returns `None` when disabled, in which case the driver's input should
+    /// be used instead. Loss of bus voltage or current feedback disables
+    /// constant-power cruise.
+    pub fn update(&mut self, now_ms: u32, status: &Status) -> Option<f32> {
+        let target_power = self.target_power?;
+
+        let (Some(voltage), Some(current)) = (status.bus_voltage, status.bus_current) else {
+            self.disable();
+            return None;
+        };
+
+        let dt = match self.last_update_ms {
+            Some(last) => now_ms.wrapping_sub(last) as f32 / 1000.0,
+            None => 0.0,
+        };
+        self.last_update_ms = Some(now_ms);
+
+        let error = target_power - voltage * current;
+        let integral = self.integral + error * dt;
+        let output = self.config.kp * error + self.config.ki * integral;
+        let limited = output.clamp(0.0, self.config.max_bus_current_percent);
+
+        // only integrate while unsaturated to avoid wind-up
+        if output == limited {
+            self.integral = integral;
+        }
+
+        Some(limited)
+    }
+
+    /// Run the controller, sending a full current torque command and the
+    /// bus current command through `sink` when enabled.
+    pub fn update_into(
+        &mut self,
+        now_ms: u32,
+        status: &Status,
+        controls: DriverControls,
+        sink: &mut impl TxSink,
+    ) -> Result<Option<f32>, &'static str> {
+        let bus_current_percent = self.update(now_ms, status);
+
+        if let Some(bus_current_percent) = bus_current_percent {
+            sink.transmit(&controls.drive(DriveCommand::torque(100.0)))?;
+            sink.transmit(&controls.motor_power(bus_current_percent))?;
+        }
+
+        Ok(bus_current_percent)
+    }
+}
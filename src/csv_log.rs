@@ -0,0 +1,290 @@
+//! CSV logger
+//!
+//! Streams rows of signal snapshots as CSV to files on removable storage,
+//! typically an SD card through the `sdmmc` module. Rows are batched in
+//! an `N` byte buffer and written a buffer at a time, so a slow card write
+//! happens once per batch rather than on every control loop iteration.
+//! Files are rotated once they reach a configured size and named
+//! `LOG00000.CSV`, `LOG00001.CSV`, and so on. Files already holding data,
+//! such as those written before a power cycle, are skipped so each file has
+//! a single header.
+//!
+//! Each file starts with a header row of `time_ms` followed by every signal
+//! name in `Signal::ALL` order. Unknown signals are left as empty cells.
+
+use core::fmt::{self, Write};
+
+use crate::thresholds::{Measurements, Signal};
+
+/// Longest CSV row in bytes
+pub const MAX_ROW_LEN: usize = 1024;
+
+/// File system holding the log files
+pub trait FileSystem {
+    /// Open the named file for appending, creating it if needed, returning
+    /// its existing length in bytes.
+    fn open(&mut self, name: &str) -> Result<u32, &'static str>;
+
+    /// Append `data` to the open file.
+    fn write(&mut self, data: &[u8]) -> Result<(), &'static str>;
+
+    /// Close the open file, committing its contents.
+    fn close(&mut self) -> Result<(), &'static str>;
+}
+
+/// CSV logger configuration
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// File size at which logging moves to the next file, in bytes
+    pub max_file_bytes: u32,
+    /// Longest time rows are buffered before being written, in milliseconds
+    pub flush_interval_ms: u32,
+    /// Time between closing and reopening the file to commit its contents,
+    /// in milliseconds
+    pub sync_interval_ms: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            max_file_bytes: 8 * 1024 * 1024,
+            flush_interval_ms: 1000,
+            sync_interval_ms: 10000,
+        }
+    }
+}
+
+/// Fixed capacity text buffer
+struct Line<const N: usize> {
+    buf: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> Line<N> {
+    fn new() -> Self {
+        Self {
+            buf: [0; N],
+            len: 0,
+        }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    fn as_str(&self) -> &str {
+        // only ever written through `write_str`
+        core::str::from_utf8(self.as_bytes()).unwrap_or_default()
+    }
+}
+
+impl<const N: usize> Write for Line<N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        if end > N {
+            return Err(fmt::Error);
+        }
+
+        self.buf[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+
+        Ok(())
+    }
+}
+
+/// CSV logger writing to `F`, batching rows in an `N` byte buffer
+pub struct CsvLogger<F, const N: usize> {
+    config: Config,
+    fs: F,
+    buf: [u8; N],
+    len: usize,
+    file_index: u16,
+    file_bytes: u32,
+    open: bool,
+    flushed_ms: u32,
+    synced_ms: u32,
+}
+
+impl<F: FileSystem, const N: usize> CsvLogger<F, N> {
+    /// Create a new logger, starting with the first empty file from
+    /// `LOG00000.CSV`.
+    pub fn new(config: Config, fs: F) -> Self {
+        Self {
+            config,
+            fs,
+            buf: [0; N],
+            len: 0,
+            file_index: 0,
+            file_bytes: 0,
+            open: false,
+            flushed_ms: 0,
+            synced_ms: 0,
+        }
+    }
+
+    /// Start searching for an empty file from the given file number.
+    pub fn with_file_index(mut self, file_index: u16) -> Self {
+        self.file_index = file_index;
+        self
+    }
+
+    /// Number of the file being written.
+    pub fn file_index(&self) -> u16 {
+        self.file_index
+    }
+
+    /// Log every signal known by `sources`.
+    ///
+    /// Where several sources know a signal the first one wins.
+    pub fn log(&mut self, now_ms: u32, sources: &[&dyn Measurements]) -> Result<(), &'static str> {
+        let mut row = Line::<MAX_ROW_LEN>::new();
+        write_row(&mut row, now_ms, sources).map_err(|_| "CSV row too long")?;
+
+        if !self.open {
+            self.open_file(now_ms)?;
+        } else if self.file_bytes + (self.len + row.len) as u32 > self.config.max_file_bytes {
+            self.flush()?;
+            self.fs.close()?;
+            self.open = false;
+
+            self.file_index = self.file_index.wrapping_add(1);
+            self.open_file(now_ms)?;
+        }
+
+        self.push(row.as_bytes())?;
+
+        if now_ms.wrapping_sub(self.flushed_ms) >= self.config.flush_interval_ms {
+            self.flushed_ms = now_ms;
+            self.flush()?;
+        }
+
+        if now_ms.wrapping_sub(self.synced_ms) >= self.config.sync_interval_ms {
+            self.synced_ms = now_ms;
+            self.sync()?;
+        }
+
+        Ok(())
+    }
+
+    /// Write any buffered rows.
+    pub fn flush(&mut self) -> Result<(), &'static str> {
+        if self.len > 0 {
+            self.fs.write(&self.buf[..self.len])?;
+            self.file_bytes += self.len as u32;
+            self.len = 0;
+        }
+
+        Ok(())
+    }
+
+    /// Write any buffered rows and commit the file contents.
+    pub fn sync(&mut self) -> Result<(), &'static str> {
+        if self.open {
+            self.flush()?;
+            self.fs.close()?;
+            self.file_bytes = self.fs.open(self.file_name().as_str())?;
+        }
+
+        Ok(())
+    }
+
+    /// Write any buffered rows and close the file, for example before
+    /// power down. Logging again reopens the file.
+    pub fn close(&mut self) -> Result<(), &'static str> {
+        if self.open {
+            self.flush()?;
+            self.fs.close()?;
+            self.open = false;
+
+            // continue in a new file, with its own header
+            self.file_index = self.file_index.wrapping_add(1);
+        }
+
+        Ok(())
+    }
+
+    /// Release the file system, without flushing.
+    pub fn into_inner(self) -> F {
+        self.fs
+    }
+
+    fn file_name(&self) -> Line<12> {
+        let mut name = Line::new();
+        // eight character name fits the FAT short file name limit
+        let _ = write!(name, "LOG{:05}.CSV", self.file_index);
+
+        name
+    }
+
+    fn open_file(&mut self, now_ms: u32) -> Result<(), &'static str> {
+        let mut skipped: u32 = 0;
+
+        loop {
+            self.file_bytes = self.fs.open(self.file_name().as_str())?;
+            if self.file_bytes == 0 {
+                break;
+            }
+
+            self.fs.close()?;
+
+            skipped += 1;
+            if skipped > u16::MAX as u32 {
+                return Err("no empty CSV log file");
+            }
+
+            self.file_index = self.file_index.wrapping_add(1);
+        }
+
+        self.open = true;
+        self.flushed_ms = now_ms;
+        self.synced_ms = now_ms;
+
+        let mut header = Line::<MAX_ROW_LEN>::new();
+        write_header(&mut header).map_err(|_| "CSV header too long")?;
+
+        self.push(header.as_bytes())
+    }
+
+    fn push(&mut self, data: &[u8]) -> Result<(), &'static str> {
+        if self.len + data.len() > N {
+            self.flush()?;
+        }
+
+        // rows longer than the buffer bypass it
+        if data.len() > N {
+            self.fs.write(data)?;
+            self.file_bytes += data.len() as u32;
+
+            return Ok(());
+        }
+
+        self.buf[self.len..self.len + data.len()].copy_from_slice(data);
+        self.len += data.len();
+
+        Ok(())
+    }
+}
+
+fn write_header(out: &mut impl Write) -> fmt::Result {
+    out.write_str("time_ms")?;
+
+    for signal in Signal::ALL {
+        write!(out, ",{}", signal.name())?;
+    }
+
+    out.write_str("\r\n")
+}
+
+fn write_row(out: &mut impl Write, now_ms: u32, sources: &[&dyn Measurements]) -> fmt::Result {
+    write!(out, "{}", now_ms)?;
+
+    for signal in Signal::ALL {
+        out.write_char(',')?;
+
+        if let Some(value) = sources.iter().find_map(|source| source.value(signal)) {
+            write!(out, "{}", value)?;
+        }
+    }
+
+    out.write_str("\r\n")
+}
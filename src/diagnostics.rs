@@ -0,0 +1,339 @@
+//! Motor controller diagnostics
+//!
+//! Consistency checks on the WaveSculptor measurements that indicate sensing,
+//! encoder or wiring faults before the controller itself reports an error.
+//! The consistency checks raise an alarm once their condition has persisted
+//! for a configured time, and clear as soon as the condition goes away. The
+//! rail monitor uses hysteresis instead.
+
+use bitflags::bitflags;
+
+use crate::wavesculptor::Status;
+
+/// Back-EMF consistency check configuration
+#[derive(Debug, Clone, Copy)]
+pub struct BackEmfConfig {
+    /// Motor back-EMF constant in volts per RPM, the inverse of the speed
+    /// constant
+    pub ke: f32,
+    /// Allowed deviation from the expected back-EMF as a fraction
+    pub tolerance: f32,
+    /// Motor velocity below which the check is skipped in RPM
+    pub min_velocity_rpm: f32,
+    /// Time the deviation must persist before raising the alarm in
+    /// milliseconds
+    pub hold_ms: u32,
+}
+
+impl Default for BackEmfConfig {
+    fn default() -> Self {
+        Self {
+            ke: 0.0,
+            tolerance: 0.25,
+            min_velocity_rpm: 100.0,
+            hold_ms: 500,
+        }
+    }
+}
+
+/// Back-EMF versus motor velocity consistency check
+///
+/// The back-EMF of a permanent magnet motor is proportional to its velocity,
+/// so a large divergence between the two indicates a position sensing fault.
+#[derive(Debug, Clone, Copy)]
+pub struct BackEmfCheck {
+    config: BackEmfConfig,
+    deviating_since_ms: Option<u32>,
+    alarm: bool,
+}
+
+impl BackEmfCheck {
+    /// Create a new check with no alarm raised.
+    pub fn new(config: BackEmfConfig) -> Self {
+        Self {
+            config,
+            deviating_since_ms: None,
+            alarm: false,
+        }
+    }
+
+    /// Is the alarm raised?
+    pub fn is_alarm(&self) -> bool {
+        self.alarm
+    }
+
+    /// Expected back-EMF magnitude in volts for a motor velocity in RPM.
+    pub fn expected_back_emf(&self, velocity_rpm: f32) -> f32 {
+        velocity_rpm.abs() * self.config.ke
+    }
+
+    /// Update from the latest WaveSculptor status and return whether the
+    /// alarm is raised.
+    ///
+    /// The state is held while either measurement is unknown.
+    pub fn update(&mut self, now_ms: u32, status: &Status) -> bool {
+        let (Some(velocity), Some(back_emf)) =
+            (status.motor_velocity, status.motor_back_emf_vector)
+        else {
+            return self.alarm;
+        };
+
+        let expected = self.expected_back_emf(velocity);
+        let deviating = velocity.abs() >= self.config.min_velocity_rpm
+            && expected > 0.0
+            && ((back_emf.norm() - expected).abs() / expected > self.config.tolerance);
+
+        let alarm = sustained(
+            &mut self.deviating_since_ms,
+            deviating,
+            now_ms,
+            self.config.hold_ms,
+        );
+
+        if alarm != self.alarm {
+            if alarm {
+                log_warn!("back-EMF inconsistent with motor velocity");
+            } else {
+                log_info!("back-EMF consistent with motor velocity");
+            }
+        }
+
+        self.alarm = alarm;
+
+        self.alarm
+    }
+}
+
+/// Phase current imbalance check configuration
+#[derive(Debug, Clone, Copy)]
+pub struct PhaseImbalanceConfig {
+    /// Allowed difference between the phase currents as a fraction of the
+    /// larger one
+    pub threshold: f32,
+    /// Phase current below which the check is skipped in amps RMS
+    pub min_current: f32,
+    /// Time the imbalance must persist before raising the alarm in
+    /// milliseconds
+    pub hold_ms: u32,
+}
+
+impl Default for PhaseImbalanceConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 0.2,
+            min_current: 10.0,
+            hold_ms: 2000,
+        }
+    }
+}
+
+/// Phase current imbalance check
+///
+/// A sustained difference between the phase B and C currents is an early
+/// indicator of winding or connector failures.
+#[derive(Debug, Clone, Copy)]
+pub struct PhaseImbalanceCheck {
+    config: PhaseImbalanceConfig,
+    imbalanced_since_ms: Option<u32>,
+    alarm: bool,
+}
+
+impl PhaseImbalanceCheck {
+    /// Create a new check with no alarm raised.
+    pub fn new(config: PhaseImbalanceConfig) -> Self {
+        Self {
+            config,
+            imbalanced_since_ms: None,
+            alarm: false,
+        }
+    }
+
+    /// Is the alarm raised?
+    pub fn is_alarm(&self) -> bool {
+        self.alarm
+    }
+
+    /// Difference between the phase currents as a fraction of the larger
+    /// one.
+    pub fn imbalance(phase_b: f32, phase_c: f32) -> f32 {
+        let (b, c) = (phase_b.abs(), phase_c.abs());
+        let larger = b.max(c);
+
+        if larger > 0.0 {
+            (b - c).abs() / larger
+        } else {
+            0.0
+        }
+    }
+
+    /// Update from the latest WaveSculptor status and return whether the
+    /// alarm is raised.
+    ///
+    /// The state is held while either current is unknown.
+    pub fn update(&mut self, now_ms: u32, status: &Status) -> bool {
+        let (Some(phase_b), Some(phase_c)) = (status.phase_b_current, status.phase_c_current)
+        else {
+            return self.alarm;
+        };
+
+        let imbalanced = phase_b.abs().max(phase_c.abs()) >= self.config.min_current
+            && Self::imbalance(phase_b, phase_c) > self.config.threshold;
+
+        let alarm = sustained(
+            &mut self.imbalanced_since_ms,
+            imbalanced,
+            now_ms,
+            self.config.hold_ms,
+        );
+
+        if alarm != self.alarm {
+            if alarm {
+                log_warn!(
+                    "phase currents imbalanced: B {} A, C {} A",
+                    phase_b,
+                    phase_c
+                );
+            } else {
+                log_info!("phase currents balanced");
+            }
+        }
+
+        self.alarm = alarm;
+
+        self.alarm
+    }
+}
+
+/// Allowed range of a supply rail
+#[derive(Debug, Clone, Copy)]
+pub struct RailLimits {
+    /// Lowest allowed voltage in volts
+    pub min: f32,
+    /// Highest allowed voltage in volts
+    pub max: f32,
+    /// Distance back inside the range needed to clear the alarm in volts
+    pub hysteresis: f32,
+}
+
+impl RailLimits {
+    fn check(&self, alarm: bool, voltage: f32) -> bool {
+        if alarm {
+            !(voltage >= self.min + self.hysteresis && voltage <= self.max - self.hysteresis)
+        } else {
+            !(voltage >= self.min && voltage <= self.max)
+        }
+    }
+}
+
+/// Supply rail monitor configuration
+#[derive(Debug, Clone, Copy)]
+pub struct RailConfig {
+    /// 15V rail limits
+    pub rail_15v: RailLimits,
+    /// 3.3V rail limits
+    pub rail_3v3: RailLimits,
+    /// 1.9V rail limits
+    pub rail_1v9: RailLimits,
+}
+
+impl Default for RailConfig {
+    fn default() -> Self {
+        Self {
+            rail_15v: RailLimits {
+                min: 13.5,
+                max: 16.5,
+                hysteresis: 0.2,
+            },
+            rail_3v3: RailLimits {
+                min: 3.1,
+                max: 3.5,
+                hysteresis: 0.05,
+            },
+            rail_1v9: RailLimits {
+                min: 1.8,
+                max: 2.0,
+                hysteresis: 0.02,
+            },
+        }
+    }
+}
+
+bitflags! {
+    /// Supply rails outside their allowed range
+    pub struct RailAlarms: u8 {
+        const RAIL_15V = 1 << 0;
+        const RAIL_3V3 = 1 << 1;
+        const RAIL_1V9 = 1 << 2;
+    }
+}
+
+/// Supply rail voltage monitor
+///
+/// Raises an alarm for each rail outside its configured range, ahead of the
+/// controller faulting on under voltage lock out. Out of range rails are
+/// reported immediately; the alarm clears once the rail is back inside the
+/// range by the hysteresis margin.
+#[derive(Debug, Clone, Copy)]
+pub struct RailMonitor {
+    config: RailConfig,
+    alarms: RailAlarms,
+}
+
+impl RailMonitor {
+    /// Create a new monitor with no alarms raised.
+    pub fn new(config: RailConfig) -> Self {
+        Self {
+            config,
+            alarms: RailAlarms::empty(),
+        }
+    }
+
+    /// Rails currently in alarm.
+    pub fn alarms(&self) -> RailAlarms {
+        self.alarms
+    }
+
+    /// Update from the latest WaveSculptor status and return the rails in
+    /// alarm.
+    ///
+    /// Each rail's state is held while its measurement is unknown.
+    pub fn update(&mut self, status: &Status) -> RailAlarms {
+        let rails = [
+            (RailAlarms::RAIL_15V, self.config.rail_15v, status.rail_15v),
+            (RailAlarms::RAIL_3V3, self.config.rail_3v3, status.rail_3v3),
+            (RailAlarms::RAIL_1V9, self.config.rail_1v9, status.rail_1v9),
+        ];
+
+        for (flag, limits, voltage) in rails {
+            if let Some(voltage) = voltage {
+                let was_alarm = self.alarms.contains(flag);
+                let alarm = limits.check(was_alarm, voltage);
+
+                if alarm != was_alarm {
+                    if alarm {
+                        log_warn!("{:?} out of range at {} V", flag, voltage);
+                    } else {
+                        log_info!("{:?} back in range at {} V", flag, voltage);
+                    }
+                }
+
+                self.alarms.set(flag, alarm);
+            }
+        }
+
+        self.alarms
+    }
+}
+
+/// Track how long a condition has held, returning true once it has held for
+/// `hold_ms`.
+fn sustained(since_ms: &mut Option<u32>, condition: bool, now_ms: u32, hold_ms: u32) -> bool {
+    if !condition {
+        *since_ms = None;
+        return false;
+    }
+
+    let since_ms = *since_ms.get_or_insert(now_ms);
+
+    now_ms.wrapping_sub(since_ms) >= hold_ms
+}
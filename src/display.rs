@@ -0,0 +1,263 @@
+//! Driver display
+//!
+//! Formats the aggregated vehicle snapshot into fixed width lines for
+//! character LCD and OLED dashboards, such as 16x2 and 20x4 modules. Each
+//! line is `W` characters wide, padded with spaces and truncated to fit, so
+//! it can be written to the display without clearing it first.
+//!
+//! Unknown values are shown as `--`. [`DashboardSummary`] picks out the
+//! values and alarm worth showing for firmware doing its own layout.
+
+use core::fmt::{self, Write};
+
+use crate::thresholds::{Level, Signal, Threshold, Thresholds};
+use crate::vehicle::Snapshot;
+
+/// Fixed width line of ASCII text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Line<const W: usize> {
+    buf: [u8; W],
+}
+
+impl<const W: usize> Line<W> {
+    /// Create a blank line.
+    pub fn new() -> Self {
+        Self { buf: [b' '; W] }
+    }
+
+    /// Line with `label` on the left and `value` right aligned, covering the
+    /// label if the line is too narrow for both.
+    pub fn labelled(label: &str, value: fmt::Arguments) -> Self {
+        let mut line = Self::new();
+        line.put(0, label);
+
+        let mut value_text = Field::<W>::new();
+        // overflowing values are truncated
+        let _ = value_text.write_fmt(value);
+
+        let value = value_text.as_str();
+        line.put(W.saturating_sub(value.len()), value);
+
+        line
+    }
+
+    /// Line bytes, `W` long.
+    pub fn as_bytes(&self) -> &[u8; W] {
+        &self.buf
+    }
+
+    /// Line text, `W` characters long.
+    pub fn as_str(&self) -> &str {
+        // only ever written with ASCII
+        core::str::from_utf8(&self.buf).unwrap_or_default()
+    }
+
+    /// Write ASCII `text` starting at column `start`, truncated to fit.
+    fn put(&mut self, start: usize, text: &str) {
+        for (slot, byte) in self.buf.iter_mut().skip(start).zip(text.bytes()) {
+            *slot = if byte.is_ascii() { byte } else { b'?' };
+        }
+    }
+}
+
+impl<const W: usize> Default for Line<W> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Formatted value, up to `W` bytes
+struct Field<const W: usize> {
+    buf: [u8; W],
+    len: usize,
+}
+
+impl<const W: usize> Field<W> {
+    fn new() -> Self {
+        Self {
+            buf: [0; W],
+            len: 0,
+        }
+    }
+
+    fn as_str(&self) -> &str {
+        // only ever written through `write_str`
+        core::str::from_utf8(&self.buf[..self.len]).unwrap_or_default()
+    }
+}
+
+impl<const W: usize> Write for Field<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        if end > W {
+            return Err(fmt::Error);
+        }
+
+        self.buf[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+
+        Ok(())
+    }
+}
+
+/// Vehicle speed, such as `SPD     72 km/h`.
+pub fn speed<const W: usize>(snapshot: &Snapshot) -> Line<W> {
+    match snapshot.speed_kmh {
+        Some(speed) => Line::labelled("SPD", format_args!("{:.0} km/h", speed)),
+        None => Line::labelled("SPD", format_args!("-- km/h")),
+    }
+}
+
+/// Pack state of charge, such as `SOC       85 %`.
+pub fn soc<const W: usize>(snapshot: &Snapshot) -> Line<W> {
+    match snapshot.soc {
+        Some(soc) => Line::labelled("SOC", format_args!("{:.0} %", soc * 100.0)),
+        None => Line::labelled("SOC", format_args!("-- %")),
+    }
+}
+
+/// Motor power, such as `PWR    1.2 kW`, negative while regenerating.
+pub fn power<const W: usize>(snapshot: &Snapshot) -> Line<W> {
+    match snapshot.motor_power {
+        Some(power) => Line::labelled("PWR", format_args!("{:.1} kW", power / 1000.0)),
+        None => Line::labelled("PWR", format_args!("-- kW")),
+    }
+}
+
+/// Solar array power, such as `SUN    0.8 kW`.
+pub fn solar<const W: usize>(snapshot: &Snapshot) -> Line<W> {
+    match snapshot.array_power {
+        Some(power) => Line::labelled("SUN", format_args!("{:.1} kW", power / 1000.0)),
+        None => Line::labelled("SUN", format_args!("-- kW")),
+    }
+}
+
+/// Highest alarm in a threshold table, such as `FLT MAX CELL T`, or `OK`.
+pub fn alarm<const W: usize, const N: usize>(thresholds: &Thresholds<N>) -> Line<W> {
+    let mut line = Line::new();
+
+    match highest_alarm(thresholds) {
+        Some(alarm) => {
+            let severity = match alarm.severity {
+                Severity::Fault => "FLT",
+                Severity::Warn => "WRN",
+                Severity::Info => "INF",
+            };
+
+            line.put(0, severity);
+            line.put(4, short_name(alarm.signal));
+        }
+        None => line.put(0, "OK"),
+    }
+
+    line
+}
+
+/// Alarm severity, for choosing display colours and buzzer behaviour
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Nothing needing attention
+    #[default]
+    Info,
+    /// Approaching a limit
+    Warn,
+    /// Limit exceeded
+    Fault,
+}
+
+impl From<Level> for Severity {
+    fn from(level: Level) -> Self {
+        match level {
+            Level::Normal => Severity::Info,
+            Level::Warning => Severity::Warn,
+            Level::Fault => Severity::Fault,
+        }
+    }
+}
+
+/// Active alarm
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActiveAlarm {
+    /// Signal outside its limits
+    pub signal: Signal,
+    /// Severity
+    pub severity: Severity,
+}
+
+/// Values and alarm most worth showing the driver
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct DashboardSummary {
+    /// Vehicle speed in km/h
+    pub speed_kmh: Option<f32>,
+    /// Pack state of charge as a fraction
+    pub soc: Option<f32>,
+    /// Motor power in watts, negative while regenerating
+    pub motor_power: Option<f32>,
+    /// Solar array power in watts
+    pub array_power: Option<f32>,
+    /// Highest priority active alarm
+    pub alarm: Option<ActiveAlarm>,
+}
+
+impl DashboardSummary {
+    /// Summarise a vehicle snapshot and the alarms raised in a threshold
+    /// table.
+    ///
+    /// Of several alarms at the same severity, the first in the table is
+    /// chosen, so tables should list their most important signals first.
+    pub fn new<const N: usize>(snapshot: &Snapshot, thresholds: &Thresholds<N>) -> Self {
+        Self {
+            speed_kmh: snapshot.speed_kmh,
+            soc: snapshot.soc,
+            motor_power: snapshot.motor_power,
+            array_power: snapshot.array_power,
+            alarm: highest_alarm(thresholds),
+        }
+    }
+
+    /// Overall severity, `Info` without an active alarm.
+    pub fn severity(&self) -> Severity {
+        self.alarm.map_or(Severity::Info, |alarm| alarm.severity)
+    }
+}
+
+/// First of the highest level alarms in a threshold table.
+fn highest_alarm<const N: usize>(thresholds: &Thresholds<N>) -> Option<ActiveAlarm> {
+    thresholds
+        .at_least(Level::Warning)
+        .fold(
+            None,
+            |highest: Option<&Threshold>, threshold| match highest {
+                Some(highest) if highest.level() >= threshold.level() => Some(highest),
+                _ => Some(threshold),
+            },
+        )
+        .map(|threshold| ActiveAlarm {
+            signal: threshold.signal,
+            severity: threshold.level().into(),
+        })
+}
+
+/// Signal name abbreviated for narrow displays.
+fn short_name(signal: Signal) -> &'static str {
+    match signal {
+        Signal::BusVoltage => "BUS V",
+        Signal::BusCurrent => "BUS I",
+        Signal::MotorVelocity => "MOTOR RPM",
+        Signal::VehicleVelocity => "SPEED",
+        Signal::PhaseBCurrent => "PHASE B I",
+        Signal::PhaseCCurrent => "PHASE C I",
+        Signal::Rail15v => "15V RAIL",
+        Signal::Rail3v3 => "3V3 RAIL",
+        Signal::Rail1v9 => "1V9 RAIL",
+        Signal::MotorTemperature => "MOTOR T",
+        Signal::HeatsinkTemperature => "HEATSINK T",
+        Signal::DspBoardTemperature => "DSP T",
+        Signal::StateOfCharge => "SOC",
+        Signal::MinimumCellVoltage => "MIN CELL V",
+        Signal::MaximumCellVoltage => "MAX CELL V",
+        Signal::MinimumCellTemperature => "MIN CELL T",
+        Signal::MaximumCellTemperature => "MAX CELL T",
+        Signal::PackVoltage => "PACK V",
+    }
+}
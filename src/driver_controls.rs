@@ -1,35 +1,409 @@
 //! EV Driver Controls
 //!
 //! This module lets you emulate driver controls messages to, for example,
-//! control a BMU or WaveSculptor.
+//! control a BMU or WaveSculptor, and decode the messages broadcast by a
+//! physical driver controls unit.
 
-use bxcan::{Frame, StandardId};
+use bitflags::bitflags;
+use bxcan::{Frame, Id, StandardId};
+
+use crate::gear::Gear;
+use crate::tx::{standard_parts, RawParts};
+
+// message identifiers normalized for base id.
+const ID_BROAD_ID: u16 = 0x00;
+const ID_CMD_DRIVE: u16 = 0x01;
+const ID_CMD_POWER: u16 = 0x02;
+const ID_CMD_RESET: u16 = 0x03;
+const ID_CMD_SWITCH: u16 = 0x05;
 
 /// Default base identifier value
 pub const ID_BASE_DEFAULT: u16 = 0x500;
 
+/// Number of identifiers used by a device, starting at its base identifier
+pub const ID_WINDOW: u16 = 0x20;
+
+/// Velocity set point that is never reached, putting the motor controller
+/// into torque (current) control mode.
+pub const TORQUE_MODE_VELOCITY_RPM: f32 = 20000.0;
+
 /// Ignition position options
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum IgnitionPosition {
+    Off,
+    Accessories,
     Run,
     Start,
 }
 
+bitflags! {
+    /// Switch position flags
+    pub struct SwitchFlags: u16 {
+        const REVERSE      = 0x0001;
+        const NEUTRAL      = 0x0002;
+        const REGEN        = 0x0004;
+        const DRIVE        = 0x0008;
+        const ACCESSORIES  = 0x0010;
+        const RUN          = 0x0020;
+        const START        = 0x0040;
+        const BRAKE        = 0x0080;
+        const CHARGE_PORT  = 0x0100;
+        const SPARE_1      = 0x0200;
+        const SPARE_2      = 0x0400;
+        const SPARE_3      = 0x0800;
+        const ACCEL_FAULT  = 0x1000;
+        const CAN_FAULT    = 0x2000;
+        const BRAKE_FAULT  = 0x4000;
+        const REVERSE_FAULT = 0x8000;
+    }
+}
+
+/// Switch position frame builder
+#[derive(Debug, Clone, Copy)]
+pub struct SwitchPosition {
+    flags: SwitchFlags,
+}
+
+impl SwitchPosition {
+    /// Create a new switch position with every switch off.
+    pub fn new() -> Self {
+        Self {
+            flags: SwitchFlags::empty(),
+        }
+    }
+
+    /// Switch flags to be sent.
+    pub fn flags(&self) -> SwitchFlags {
+        self.flags
+    }
+
+    /// Set the ignition key position.
+    pub fn ignition(mut self, position: IgnitionPosition) -> Self {
+        self.flags
+            .remove(SwitchFlags::ACCESSORIES | SwitchFlags::RUN | SwitchFlags::START);
+        self.flags |= match position {
+            IgnitionPosition::Off => SwitchFlags::empty(),
+            IgnitionPosition::Accessories => SwitchFlags::ACCESSORIES,
+            IgnitionPosition::Run => SwitchFlags::RUN,
+            IgnitionPosition::Start => SwitchFlags::START,
+        };
+        self
+    }
+
+    /// Set the selected gear.
+    pub fn gear(mut self, gear: Gear) -> Self {
+        self.flags
+            .remove(SwitchFlags::REVERSE | SwitchFlags::NEUTRAL | SwitchFlags::DRIVE);
+        self.flags |= match gear {
+            Gear::Reverse => SwitchFlags::REVERSE,
+            Gear::Neutral => SwitchFlags::NEUTRAL,
+            Gear::Drive => SwitchFlags::DRIVE,
+        };
+        self
+    }
+
+    /// Set the regen switch.
+    pub fn regen(self, on: bool) -> Self {
+        self.with(SwitchFlags::REGEN, on)
+    }
+
+    /// Set the brake switch.
+    pub fn brake(self, on: bool) -> Self {
+        self.with(SwitchFlags::BRAKE, on)
+    }
+
+    /// Set the charge port switch.
+    pub fn charge_port(self, open: bool) -> Self {
+        self.with(SwitchFlags::CHARGE_PORT, open)
+    }
+
+    /// Set arbitrary flags, for example the spare inputs.
+    pub fn with(mut self, flags: SwitchFlags, on: bool) -> Self {
+        self.flags.set(flags, on);
+        self
+    }
+}
+
+impl Default for SwitchPosition {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Motor drive command set points
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct DriveCommand {
+    /// Motor velocity set point in RPM
+    pub velocity_rpm: f32,
+    /// Motor current set point as a percentage of the maximum current, from
+    /// 0 to 100
+    pub current_percent: f32,
+}
+
+impl DriveCommand {
+    /// Create a new drive command.
+    pub fn new(velocity_rpm: f32, current_percent: f32) -> Self {
+        Self {
+            velocity_rpm,
+            current_percent,
+        }
+    }
+
+    /// Velocity control mode command.
+    ///
+    /// The motor is driven to `velocity_rpm` using up to the maximum current.
+    pub fn velocity(velocity_rpm: f32) -> Self {
+        Self::new(velocity_rpm, 100.0)
+    }
+
+    /// Torque control mode command.
+    ///
+    /// The velocity set point is placed out of reach so the motor controller
+    /// regulates current. The sign of `current_percent` selects the direction.
+    pub fn torque(current_percent: f32) -> Self {
+        if current_percent < 0.0 {
+            Self::new(-TORQUE_MODE_VELOCITY_RPM, -current_percent)
+        } else {
+            Self::new(TORQUE_MODE_VELOCITY_RPM, current_percent)
+        }
+    }
+
+    /// Regenerative braking command.
+    ///
+    /// A zero velocity set point makes the motor controller brake using the
+    /// given current.
+    pub fn regen(current_percent: f32) -> Self {
+        Self::new(0.0, current_percent)
+    }
+}
+
+/// Command frames sent by driver controls
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    /// Motor drive command
+    Drive(DriveCommand),
+    /// Motor power command with the bus current in percent
+    Power(f32),
+    /// Reset WaveSculptor
+    Reset,
+    /// Switch positions
+    Switches(SwitchFlags),
+}
+
+/// Status broadcast by a driver controls unit
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct DriverControlsStatus {
+    /// Device identifier
+    pub identifier: Option<u32>,
+    /// Device serial number, allocated at manufacture
+    pub serial_number: Option<u32>,
+    /// Most recent motor drive command, reflecting the pedal positions
+    pub drive: Option<DriveCommand>,
+    /// Most recent bus current command in percent
+    pub bus_current_percent: Option<f32>,
+    /// Most recent switch positions
+    pub switches: Option<SwitchFlags>,
+}
+
+impl DriverControlsStatus {
+    /// Selected ignition position.
+    pub fn ignition(&self) -> Option<IgnitionPosition> {
+        self.switches.map(|switches| {
+            if switches.contains(SwitchFlags::START) {
+                IgnitionPosition::Start
+            } else if switches.contains(SwitchFlags::RUN) {
+                IgnitionPosition::Run
+            } else if switches.contains(SwitchFlags::ACCESSORIES) {
+                IgnitionPosition::Accessories
+            } else {
+                IgnitionPosition::Off
+            }
+        })
+    }
+
+    /// Is the brake switch active?
+    pub fn brake(&self) -> Option<bool> {
+        self.switches
+            .map(|switches| switches.contains(SwitchFlags::BRAKE))
+    }
+}
+
 /// EV Driver Controls
 #[derive(Debug, Clone, Copy)]
 pub struct DriverControls {
     base_id: u16,
+
+    status: DriverControlsStatus,
+    last_receive_ms: Option<u32>,
 }
 
 impl DriverControls {
     /// Create a new driver controls instance.
-    pub fn new(base_id: u16) -> Self {
-        Self { base_id }
+    ///
+    /// Fails if the device's identifier window starting at `base_id` does
+    /// not fit within the standard identifier range.
+    pub fn new(base_id: u16) -> Result<Self, &'static str> {
+        if StandardId::new(base_id.saturating_add(ID_WINDOW - 1)).is_none() {
+            return Err("identifier window exceeds standard identifier range");
+        }
+
+        Ok(Self::unchecked(base_id))
+    }
+
+    fn unchecked(base_id: u16) -> Self {
+        Self {
+            base_id,
+            status: DriverControlsStatus {
+                ..Default::default()
+            },
+            last_receive_ms: None,
+        }
+    }
+
+    /// Get the status most recently broadcast by a driver controls unit
+    pub fn status(&self) -> DriverControlsStatus {
+        self.status
+    }
+
+    /// Decode a frame broadcast by a driver controls unit.
+    pub fn receive(&mut self, frame: Frame) -> Result<(), &'static str> {
+        match window(&frame, self.base_id) {
+            Some((offset, data)) => self.receive_offset(offset, data),
+            None => Ok(()),
+        }
+    }
+
+    /// Decode a frame broadcast by a driver controls unit at `now_ms`, noting
+    /// when the unit last reported.
+    pub fn receive_at(&mut self, now_ms: u32, frame: Frame) -> Result<(), &'static str> {
+        match window(&frame, self.base_id) {
+            Some((offset, data)) => self.receive_offset_at(now_ms, offset, data),
+            None => Ok(()),
+        }
+    }
+
+    fn receive_offset_at(
+        &mut self,
+        now_ms: u32,
+        offset: u16,
+        data: &[u8],
+    ) -> Result<(), &'static str> {
+        self.receive_offset(offset, data)?;
+        self.last_receive_ms = Some(now_ms);
+
+        Ok(())
+    }
+
+    fn receive_offset(&mut self, offset: u16, data: &[u8]) -> Result<(), &'static str> {
+        let command = decode_offset(offset, data)
+            .inspect_err(|error| log_warn!("driver controls frame: {}", error))?;
+
+        match command {
+            Some(Command::Drive(command)) => self.status.drive = Some(command),
+            Some(Command::Power(bus_current_percent)) => {
+                self.status.bus_current_percent = Some(bus_current_percent)
+            }
+            Some(Command::Switches(switches)) => self.status.switches = Some(switches),
+            Some(Command::Reset) => {}
+            None => {
+                if offset == ID_BROAD_ID && data.len() >= 8 {
+                    self.status.identifier =
+                        Some(u32::from_le_bytes(data[0..4].try_into().unwrap()));
+                    self.status.serial_number =
+                        Some(u32::from_le_bytes(data[4..8].try_into().unwrap()));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Time of the latest frame noted by [`receive_at`](Self::receive_at).
+    pub fn last_receive_ms(&self) -> Option<u32> {
+        self.last_receive_ms
+    }
+
+    /// Decode a command frame, as produced by the frame builders.
+    ///
+    /// Returns `None` for frames that are not driver controls commands.
+    pub fn decode(&self, frame: &Frame) -> Result<Option<Command>, &'static str> {
+        match window(frame, self.base_id) {
+            Some((offset, data)) => decode_offset(offset, data),
+            None => Ok(None),
+        }
     }
 
     /// Motor drive command
+    ///
+    /// The set points are sent unchanged, use
+    /// [`try_motor_drive`](Self::try_motor_drive) to validate them.
     pub fn motor_drive(self, velocity_rpm: f32, current_percent: f32) -> Frame {
-        let id = StandardId::new(self.base_id + 0x01).unwrap();
+        self.encode_drive(velocity_rpm, current_percent)
+    }
+
+    /// Validated motor drive command
+    ///
+    /// Rejects non-finite set points and clamps `current_percent` to 0 to 100
+    /// percent.
+    pub fn try_motor_drive(
+        self,
+        velocity_rpm: f32,
+        current_percent: f32,
+    ) -> Result<Frame, &'static str> {
+        if !velocity_rpm.is_finite() {
+            return Err("velocity must be finite");
+        }
+
+        Ok(self.encode_drive(velocity_rpm, validate_percent(current_percent)?))
+    }
+
+    /// Motor drive command from a set of drive set points
+    pub fn drive(self, command: DriveCommand) -> Frame {
+        self.motor_drive(command.velocity_rpm, command.current_percent)
+    }
+
+    /// Validated motor drive command from a set of drive set points
+    pub fn try_drive(self, command: DriveCommand) -> Result<Frame, &'static str> {
+        self.try_motor_drive(command.velocity_rpm, command.current_percent)
+    }
+
+    /// Velocity control mode drive command
+    ///
+    /// See [`DriveCommand::velocity`].
+    pub fn drive_velocity(self, velocity_rpm: f32) -> Frame {
+        self.drive(DriveCommand::velocity(velocity_rpm))
+    }
+
+    /// Torque control mode drive command
+    ///
+    /// See [`DriveCommand::torque`].
+    pub fn drive_torque(self, current_percent: f32) -> Frame {
+        self.drive(DriveCommand::torque(current_percent))
+    }
+
+    /// Regenerative braking command
+    pub fn regen(self, current_percent: f32) -> Frame {
+        self.drive(DriveCommand::regen(current_percent))
+    }
+
+    /// Motor power command
+    ///
+    /// `bus_current_percent` is sent unchanged, use
+    /// [`try_motor_power`](Self::try_motor_power) to validate it.
+    pub fn motor_power(self, bus_current_percent: f32) -> Frame {
+        self.encode_power(bus_current_percent)
+    }
+
+    /// Validated motor power command
+    ///
+    /// Rejects a non-finite value and clamps `bus_current_percent` to 0 to 100
+    /// percent.
+    pub fn try_motor_power(self, bus_current_percent: f32) -> Result<Frame, &'static str> {
+        Ok(self.encode_power(validate_percent(bus_current_percent)?))
+    }
+
+    fn encode_drive(self, velocity_rpm: f32, current_percent: f32) -> Frame {
+        let id = StandardId::new(self.base_id + ID_CMD_DRIVE).unwrap();
 
         let vel = velocity_rpm.to_le_bytes();
         let cur = current_percent.to_le_bytes();
@@ -41,9 +415,8 @@ impl DriverControls {
         Frame::new_data(id, data)
     }
 
-    /// Motor power command
-    pub fn motor_power(self, bus_current_percent: f32) -> Frame {
-        let id = StandardId::new(self.base_id + 0x02).unwrap();
+    fn encode_power(self, bus_current_percent: f32) -> Frame {
+        let id = StandardId::new(self.base_id + ID_CMD_POWER).unwrap();
 
         let bus = bus_current_percent.to_le_bytes();
 
@@ -54,21 +427,217 @@ impl DriverControls {
 
     /// Reset WaveSculptor
     pub fn reset_wavesculptor(self) -> Frame {
-        let id = StandardId::new(self.base_id + 0x03).unwrap();
+        let id = StandardId::new(self.base_id + ID_CMD_RESET).unwrap();
 
         Frame::new_data(id, [0; 8])
     }
 
     /// Form a switch position frame
     pub fn switch_position(self, ignition_position: IgnitionPosition) -> Frame {
-        let id = StandardId::new(self.base_id + 0x05).unwrap();
+        self.switches(SwitchPosition::new().ignition(ignition_position))
+    }
 
-        let data: u8 = match ignition_position {
-            IgnitionPosition::Run => 0x0020,
-            IgnitionPosition::Start => 0x0040,
-        };
+    /// Form a switch position frame from a complete set of switches
+    pub fn switches(self, switches: SwitchPosition) -> Frame {
+        let id = StandardId::new(self.base_id + ID_CMD_SWITCH).unwrap();
+
+        let flags = switches.flags().bits().to_le_bytes();
+
+        Frame::new_data(id, [flags[0], flags[1], 0, 0, 0, 0, 0, 0])
+    }
+
+    // raw variants for HALs without bxcan, see `crate::tx::RawParts`
+
+    /// Raw parts of [`motor_drive`](Self::motor_drive).
+    pub fn motor_drive_raw(self, velocity_rpm: f32, current_percent: f32) -> RawParts {
+        standard_parts(&self.motor_drive(velocity_rpm, current_percent))
+    }
+
+    /// Raw parts of [`try_motor_drive`](Self::try_motor_drive).
+    pub fn try_motor_drive_raw(
+        self,
+        velocity_rpm: f32,
+        current_percent: f32,
+    ) -> Result<RawParts, &'static str> {
+        self.try_motor_drive(velocity_rpm, current_percent)
+            .map(|frame| standard_parts(&frame))
+    }
+
+    /// Raw parts of [`drive`](Self::drive).
+    pub fn drive_raw(self, command: DriveCommand) -> RawParts {
+        standard_parts(&self.drive(command))
+    }
+
+    /// Raw parts of [`try_drive`](Self::try_drive).
+    pub fn try_drive_raw(self, command: DriveCommand) -> Result<RawParts, &'static str> {
+        self.try_drive(command).map(|frame| standard_parts(&frame))
+    }
+
+    /// Raw parts of [`drive_velocity`](Self::drive_velocity).
+    pub fn drive_velocity_raw(self, velocity_rpm: f32) -> RawParts {
+        standard_parts(&self.drive_velocity(velocity_rpm))
+    }
+
+    /// Raw parts of [`drive_torque`](Self::drive_torque).
+    pub fn drive_torque_raw(self, current_percent: f32) -> RawParts {
+        standard_parts(&self.drive_torque(current_percent))
+    }
+
+    /// Raw parts of [`regen`](Self::regen).
+    pub fn regen_raw(self, current_percent: f32) -> RawParts {
+        standard_parts(&self.regen(current_percent))
+    }
+
+    /// Raw parts of [`motor_power`](Self::motor_power).
+    pub fn motor_power_raw(self, bus_current_percent: f32) -> RawParts {
+        standard_parts(&self.motor_power(bus_current_percent))
+    }
+
+    /// Raw parts of [`try_motor_power`](Self::try_motor_power).
+    pub fn try_motor_power_raw(self, bus_current_percent: f32) -> Result<RawParts, &'static str> {
+        self.try_motor_power(bus_current_percent)
+            .map(|frame| standard_parts(&frame))
+    }
+
+    /// Raw parts of [`reset_wavesculptor`](Self::reset_wavesculptor).
+    pub fn reset_wavesculptor_raw(self) -> RawParts {
+        standard_parts(&self.reset_wavesculptor())
+    }
+
+    /// Raw parts of [`switch_position`](Self::switch_position).
+    pub fn switch_position_raw(self, ignition_position: IgnitionPosition) -> RawParts {
+        standard_parts(&self.switch_position(ignition_position))
+    }
+
+    /// Raw parts of [`switches`](Self::switches).
+    pub fn switches_raw(self, switches: SwitchPosition) -> RawParts {
+        standard_parts(&self.switches(switches))
+    }
+}
+
+/// Decode a command frame at `offset` within the identifier window.
+fn decode_offset(offset: u16, data: &[u8]) -> Result<Option<Command>, &'static str> {
+    let command = match offset {
+        ID_CMD_DRIVE => {
+            if data.len() < 8 {
+                return Err("motor drive frame too short");
+            }
+
+            Command::Drive(DriveCommand::new(
+                f32::from_le_bytes(data[0..4].try_into().unwrap()),
+                f32::from_le_bytes(data[4..8].try_into().unwrap()),
+            ))
+        }
+
+        ID_CMD_POWER => {
+            if data.len() < 8 {
+                return Err("motor power frame too short");
+            }
+
+            Command::Power(f32::from_le_bytes(data[4..8].try_into().unwrap()))
+        }
+
+        ID_CMD_RESET => Command::Reset,
+
+        ID_CMD_SWITCH => {
+            if data.len() < 2 {
+                return Err("switch position frame too short");
+            }
+
+            Command::Switches(SwitchFlags::from_bits_truncate(u16::from_le_bytes(
+                data[0..2].try_into().unwrap(),
+            )))
+        }
+
+        _ => return Ok(None),
+    };
+
+    Ok(Some(command))
+}
+
+/// Offset and payload of a data frame within the window starting at
+/// `base_id`, `None` for frames belonging to other devices.
+fn window(frame: &Frame, base_id: u16) -> Option<(u16, &[u8])> {
+    let (Id::Standard(id), Some(data)) = (frame.id(), frame.data()) else {
+        return None;
+    };
+
+    let offset = id.as_raw().wrapping_sub(base_id);
+
+    (offset < ID_WINDOW).then_some((offset, &data[..]))
+}
+
+/// Check a percentage is finite and clamp it to 0 to 100 percent.
+fn validate_percent(percent: f32) -> Result<f32, &'static str> {
+    if !percent.is_finite() {
+        return Err("percentage must be finite");
+    }
+
+    Ok(percent.clamp(0.0, 100.0))
+}
+
+/// DriverControls with a base identifier fixed at compile time
+///
+/// The identifier window is validated when the program is compiled, so
+/// construction cannot fail. Dereferences to [`DriverControls`] for everything else.
+pub struct FixedDriverControls<const BASE: u16>(DriverControls);
+
+impl<const BASE: u16> FixedDriverControls<BASE> {
+    const WINDOW_FITS: () = assert!(
+        BASE as u32 + ID_WINDOW as u32 <= 0x800,
+        "identifier window exceeds standard identifier range"
+    );
+
+    /// Create a new instance.
+    pub fn new() -> Self {
+        let () = Self::WINDOW_FITS;
+
+        Self(DriverControls::unchecked(BASE))
+    }
+
+    /// Decode a frame broadcast by a driver controls unit.
+    ///
+    /// The identifier window is resolved from `BASE` at compile time.
+    pub fn receive(&mut self, frame: Frame) -> Result<(), &'static str> {
+        match window(&frame, BASE) {
+            Some((offset, data)) => self.0.receive_offset(offset, data),
+            None => Ok(()),
+        }
+    }
+
+    /// See [`DriverControls::receive_at`].
+    pub fn receive_at(&mut self, now_ms: u32, frame: Frame) -> Result<(), &'static str> {
+        match window(&frame, BASE) {
+            Some((offset, data)) => self.0.receive_offset_at(now_ms, offset, data),
+            None => Ok(()),
+        }
+    }
+
+    /// See [`DriverControls::decode`].
+    pub fn decode(&self, frame: &Frame) -> Result<Option<Command>, &'static str> {
+        match window(frame, BASE) {
+            Some((offset, data)) => decode_offset(offset, data),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<const BASE: u16> Default for FixedDriverControls<BASE> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const BASE: u16> core::ops::Deref for FixedDriverControls<BASE> {
+    type Target = DriverControls;
+
+    fn deref(&self) -> &DriverControls {
+        &self.0
+    }
+}
 
-        // only first byte is occupied, manual shows all bytes used
-        Frame::new_data(id, [data, 0, 0, 0, 0, 0, 0, 0])
+impl<const BASE: u16> core::ops::DerefMut for FixedDriverControls<BASE> {
+    fn deref_mut(&mut self) -> &mut DriverControls {
+        &mut self.0
     }
 }
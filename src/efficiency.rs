@@ -0,0 +1,138 @@
+//! Efficiency map
+//!
+//! Bins the drivetrain's operating points by vehicle speed and quadrature
+//! (Q) axis motor current, which sets torque, and accumulates the bus power
+//! measured in each bin. Over many drives this builds an on-car map of power
+//! drawn at each operating point for later strategy modelling.
+//!
+//! The map has `SPEED_BINS` by `CURRENT_BINS` bins. Operating points outside
+//! the map are not recorded.
+
+use crate::wavesculptor::Status;
+
+/// Efficiency map configuration
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Width of each speed bin in km/h, the first starting at zero
+    pub speed_bin_kmh: f32,
+    /// Width of each current bin in amps
+    pub current_bin: f32,
+    /// Lowest current mapped in amps, negative to include regeneration
+    pub min_current: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            speed_bin_kmh: 10.0,
+            current_bin: 10.0,
+            min_current: -50.0,
+        }
+    }
+}
+
+/// Accumulated measurements of one operating point bin
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Bin {
+    /// Number of samples
+    pub samples: u32,
+    /// Sum of the bus power samples in watts
+    pub power_sum: f32,
+}
+
+impl Bin {
+    /// Mean bus power in watts, `None` without samples.
+    pub fn mean_power(&self) -> Option<f32> {
+        (self.samples > 0).then(|| self.power_sum / self.samples as f32)
+    }
+}
+
+/// Operating point efficiency map
+#[derive(Debug, Clone, Copy)]
+pub struct EfficiencyMap<const SPEED_BINS: usize, const CURRENT_BINS: usize> {
+    config: Config,
+    bins: [[Bin; CURRENT_BINS]; SPEED_BINS],
+}
+
+impl<const SPEED_BINS: usize, const CURRENT_BINS: usize> EfficiencyMap<SPEED_BINS, CURRENT_BINS> {
+    /// Create a new, empty, map.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            bins: [[Bin::default(); CURRENT_BINS]; SPEED_BINS],
+        }
+    }
+
+    /// Forget every sample.
+    pub fn clear(&mut self) {
+        self.bins = [[Bin::default(); CURRENT_BINS]; SPEED_BINS];
+    }
+
+    /// Bin covering `speed_bin` and `current_bin`.
+    pub fn bin(&self, speed_bin: usize, current_bin: usize) -> Option<Bin> {
+        self.bins.get(speed_bin)?.get(current_bin).copied()
+    }
+
+    /// Rows of bins, one per speed bin.
+    pub fn bins(&self) -> &[[Bin; CURRENT_BINS]; SPEED_BINS] {
+        &self.bins
+    }
+
+    /// Lowest speed in km/h and current in amps of a bin.
+    pub fn bin_start(&self, speed_bin: usize, current_bin: usize) -> (f32, f32) {
+        (
+            speed_bin as f32 * self.config.speed_bin_kmh,
+            self.config.min_current + current_bin as f32 * self.config.current_bin,
+        )
+    }
+
+    /// Speed and current bin covering an operating point.
+    pub fn locate(&self, speed_kmh: f32, current: f32) -> Option<(usize, usize)> {
+        let speed = speed_kmh.abs() / self.config.speed_bin_kmh;
+        let current = (current - self.config.min_current) / self.config.current_bin;
+
+        // also rejects non-finite values
+        if !(speed >= 0.0
+            && speed < SPEED_BINS as f32
+            && current >= 0.0
+            && current < CURRENT_BINS as f32)
+        {
+            return None;
+        }
+
+        Some((speed as usize, current as usize))
+    }
+
+    /// Record an operating point and its bus power in watts, returning
+    /// whether it fell within the map.
+    pub fn record(&mut self, speed_kmh: f32, current: f32, bus_power: f32) -> bool {
+        if !bus_power.is_finite() {
+            return false;
+        }
+
+        let Some((speed_bin, current_bin)) = self.locate(speed_kmh, current) else {
+            return false;
+        };
+
+        let bin = &mut self.bins[speed_bin][current_bin];
+        bin.samples = bin.samples.saturating_add(1);
+        bin.power_sum += bus_power;
+
+        true
+    }
+
+    /// Record the operating point of the latest WaveSculptor status,
+    /// returning whether it fell within the map.
+    pub fn update(&mut self, status: &Status) -> bool {
+        let (Some(speed), Some(current), Some(voltage), Some(bus_current)) = (
+            status.vehicle_speed_kmh(),
+            status.motor_current_vector,
+            status.bus_voltage,
+            status.bus_current,
+        ) else {
+            return false;
+        };
+
+        self.record(speed, current.im, voltage * bus_current)
+    }
+}
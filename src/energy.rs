@@ -0,0 +1,239 @@
+//! Energy accounting
+//!
+//! The WaveSculptor bus amp-hour measurement restarts from zero whenever the
+//! controller resets. These accumulators detect resets and keep totals that
+//! can be persisted and restored across power cycles, and convert them to
+//! watt-hours. Regenerated energy is counted separately so the braking
+//! energy recovered can be quantified.
+
+use crate::wavesculptor::Status;
+
+/// Bus amp-hour accumulator configuration
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Drop between consecutive readings treated as a controller reset in
+    /// amp-hours
+    ///
+    /// Regeneration lowers the reading too, but far slower than a reset.
+    pub reset_drop: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self { reset_drop: 0.5 }
+    }
+}
+
+/// Bus amp-hour accumulator with trip and total counters
+#[derive(Debug, Clone, Copy)]
+pub struct AmpHours {
+    config: Config,
+    /// Amp-hours accumulated before the controller's current session
+    base: f64,
+    /// Amp-hours reported during the controller's current session
+    session: f32,
+    /// Total when the trip counter was last reset
+    trip_start: f64,
+}
+
+impl AmpHours {
+    /// Create a new accumulator starting from zero.
+    pub fn new(config: Config) -> Self {
+        Self::with_total(config, 0.0)
+    }
+
+    /// Create an accumulator continuing from a previously persisted total.
+    ///
+    /// The controller is assumed to have restarted since the total was saved.
+    pub fn with_total(config: Config, total: f64) -> Self {
+        Self {
+            config,
+            base: total,
+            session: 0.0,
+            trip_start: total,
+        }
+    }
+
+    /// Total amp-hours.
+    pub fn total(&self) -> f64 {
+        self.base + self.session as f64
+    }
+
+    /// Amp-hours since the trip counter was last reset.
+    pub fn trip(&self) -> f64 {
+        self.total() - self.trip_start
+    }
+
+    /// Restart the trip counter from zero.
+    pub fn reset_trip(&mut self) {
+        self.trip_start = self.total();
+    }
+
+    /// Update from the latest WaveSculptor status.
+    pub fn update(&mut self, status: &Status) -> f64 {
+        if let Some(amp_hours) = status.bus_amp_hours {
+            self.update_amp_hours(amp_hours);
+        }
+
+        self.total()
+    }
+
+    /// Update from a bus amp-hour reading.
+    ///
+    /// Non-finite readings are ignored.
+    pub fn update_amp_hours(&mut self, amp_hours: f32) -> f64 {
+        if amp_hours.is_finite() {
+            if self.session - amp_hours > self.config.reset_drop {
+                self.base += self.session as f64;
+            }
+
+            self.session = amp_hours;
+        }
+
+        self.total()
+    }
+}
+
+/// Bus energy integrator
+///
+/// Converts the bus amp-hour count into watt-hours by weighting each change
+/// in charge by the bus voltage sampled alongside it.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WattHours {
+    last: Option<(f64, f32)>,
+    total: f64,
+}
+
+impl WattHours {
+    /// Create a new integrator starting from zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Watt-hours consumed, regeneration counting negative.
+    pub fn total(&self) -> f64 {
+        self.total
+    }
+
+    /// Update from an amp-hour accumulator and the latest WaveSculptor status.
+    pub fn update(&mut self, amp_hours: &AmpHours, status: &Status) -> f64 {
+        if let Some(voltage) = status.bus_voltage {
+            self.update_sample(amp_hours.total(), voltage);
+        }
+
+        self.total
+    }
+
+    /// Update from a total amp-hour count and bus voltage in volts.
+    ///
+    /// The voltage is averaged between consecutive samples. Non-finite
+    /// samples are ignored.
+    pub fn update_sample(&mut self, amp_hours: f64, bus_voltage: f32) -> f64 {
+        if amp_hours.is_finite() && bus_voltage.is_finite() {
+            if let Some((last_amp_hours, last_voltage)) = self.last {
+                let voltage = (last_voltage as f64 + bus_voltage as f64) / 2.0;
+
+                self.total += (amp_hours - last_amp_hours) * voltage;
+            }
+
+            self.last = Some((amp_hours, bus_voltage));
+        }
+
+        self.total
+    }
+}
+
+/// Regenerated energy counter configuration
+#[derive(Debug, Clone, Copy)]
+pub struct RegenConfig {
+    /// Longest gap between samples integrated in milliseconds, so energy
+    /// isn't credited across a telemetry dropout
+    pub max_gap_ms: u32,
+}
+
+impl Default for RegenConfig {
+    fn default() -> Self {
+        Self { max_gap_ms: 1000 }
+    }
+}
+
+/// Regenerated energy counter with stint and total counters
+///
+/// Integrates bus power only while the bus current is negative, so the
+/// braking energy recovered is counted on its own rather than netted
+/// against consumption.
+#[derive(Debug, Clone, Copy)]
+pub struct RegenEnergy {
+    config: RegenConfig,
+    last: Option<(u32, f32)>,
+    total: f64,
+    stint_start: f64,
+}
+
+impl RegenEnergy {
+    /// Create a new counter starting from zero.
+    pub fn new(config: RegenConfig) -> Self {
+        Self::with_total(config, 0.0)
+    }
+
+    /// Create a counter continuing from a previously persisted total in
+    /// watt-hours.
+    pub fn with_total(config: RegenConfig, total: f64) -> Self {
+        Self {
+            config,
+            last: None,
+            total,
+            stint_start: total,
+        }
+    }
+
+    /// Total watt-hours regenerated.
+    pub fn total(&self) -> f64 {
+        self.total
+    }
+
+    /// Watt-hours regenerated since the stint counter was last reset.
+    pub fn stint(&self) -> f64 {
+        self.total - self.stint_start
+    }
+
+    /// Restart the stint counter from zero.
+    pub fn reset_stint(&mut self) {
+        self.stint_start = self.total;
+    }
+
+    /// Update from the latest WaveSculptor status.
+    pub fn update(&mut self, now_ms: u32, status: &Status) -> f64 {
+        if let (Some(voltage), Some(current)) = (status.bus_voltage, status.bus_current) {
+            self.update_sample(now_ms, voltage, current);
+        }
+
+        self.total
+    }
+
+    /// Update from a bus voltage in volts and bus current in amps.
+    ///
+    /// The regenerated power is averaged between consecutive samples.
+    /// Non-finite samples are ignored.
+    pub fn update_sample(&mut self, now_ms: u32, bus_voltage: f32, bus_current: f32) -> f64 {
+        if !(bus_voltage.is_finite() && bus_current.is_finite()) {
+            return self.total;
+        }
+
+        let power = (-bus_voltage * bus_current).max(0.0);
+
+        if let Some((last_ms, last_power)) = self.last {
+            let elapsed = now_ms.wrapping_sub(last_ms);
+
+            if elapsed <= self.config.max_gap_ms {
+                let hours = elapsed as f64 / 3_600_000.0;
+
+                self.total += (last_power as f64 + power as f64) / 2.0 * hours;
+            }
+        }
+
+        self.last = Some((now_ms, power));
+
+        self.total
+    }
+}
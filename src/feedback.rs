@@ -0,0 +1,140 @@
+//! Command feedback verification
+//!
+//! Compares the drive command last sent to the motor controller against its
+//! measured bus current and motor velocity, flagging disagreements that
+//! persist for longer than a window. A persistent mismatch points at a stuck
+//! controller, a wrong base identifier or a wiring fault.
+
+use bitflags::bitflags;
+
+use crate::driver_controls::{DriveCommand, TORQUE_MODE_VELOCITY_RPM};
+use crate::wavesculptor::Status;
+
+bitflags! {
+    /// Measurements disagreeing with the command
+    pub struct CommandMismatch: u8 {
+        /// Bus current above the commanded limit
+        const OVER_CURRENT = 1 << 0;
+        /// No bus current despite a current being commanded
+        const NO_RESPONSE = 1 << 1;
+        /// Motor velocity away from the velocity control set point
+        const VELOCITY = 1 << 2;
+    }
+}
+
+/// Command feedback check configuration
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Bus current limit configured in the motor controller in amps
+    pub max_bus_current: f32,
+    /// Bus current tolerance in amps
+    pub current_tolerance: f32,
+    /// Commanded current in percent above which bus current is expected
+    pub response_percent: f32,
+    /// Motor velocity tolerance in RPM
+    pub velocity_tolerance_rpm: f32,
+    /// Time a disagreement must persist before it is flagged in
+    /// milliseconds, long enough for the vehicle to reach a velocity set
+    /// point
+    pub persistence_ms: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            max_bus_current: 100.0,
+            current_tolerance: 5.0,
+            response_percent: 10.0,
+            velocity_tolerance_rpm: 100.0,
+            persistence_ms: 5000,
+        }
+    }
+}
+
+/// Command feedback check
+#[derive(Debug, Clone, Copy)]
+pub struct FeedbackCheck {
+    config: Config,
+    since_ms: [Option<u32>; 3],
+    mismatch: CommandMismatch,
+}
+
+impl FeedbackCheck {
+    /// Create a new check with no disagreement.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            since_ms: [None; 3],
+            mismatch: CommandMismatch::empty(),
+        }
+    }
+
+    /// Disagreements persisting longer than the configured window.
+    pub fn mismatch(&self) -> CommandMismatch {
+        self.mismatch
+    }
+
+    /// Compare the last sent drive command and bus current command in
+    /// percent against the latest motor controller status, returning the
+    /// persistent disagreements.
+    ///
+    /// Unknown measurements are not checked. The velocity is only checked
+    /// in velocity control mode, excluding regenerative braking to a stop.
+    pub fn update(
+        &mut self,
+        now_ms: u32,
+        command: DriveCommand,
+        bus_current_percent: f32,
+        status: &Status,
+    ) -> CommandMismatch {
+        let allowed = self.config.max_bus_current * bus_current_percent.clamp(0.0, 100.0) / 100.0;
+        let tolerance = self.config.current_tolerance;
+
+        let over_current = status
+            .bus_current
+            .map(|current| current.abs() > allowed + tolerance);
+
+        let no_response = status.bus_current.map(|current| {
+            command.current_percent >= self.config.response_percent
+                && bus_current_percent >= self.config.response_percent
+                && current.abs() < tolerance
+        });
+
+        let velocity_mode =
+            command.velocity_rpm != 0.0 && command.velocity_rpm.abs() < TORQUE_MODE_VELOCITY_RPM;
+        let velocity = status.motor_velocity.map(|velocity| {
+            velocity_mode
+                && (velocity - command.velocity_rpm).abs() > self.config.velocity_tolerance_rpm
+        });
+
+        let checks = [
+            (CommandMismatch::OVER_CURRENT, over_current),
+            (CommandMismatch::NO_RESPONSE, no_response),
+            (CommandMismatch::VELOCITY, velocity),
+        ];
+
+        for ((flag, disagrees), since_ms) in checks.into_iter().zip(self.since_ms.iter_mut()) {
+            if disagrees != Some(true) {
+                *since_ms = None;
+
+                if self.mismatch.contains(flag) {
+                    log_info!("motor controller {:?} agrees with command again", flag);
+                    self.mismatch.remove(flag);
+                }
+
+                continue;
+            }
+
+            let since_ms = *since_ms.get_or_insert(now_ms);
+
+            if !self.mismatch.contains(flag)
+                && now_ms.wrapping_sub(since_ms) >= self.config.persistence_ms
+            {
+                log_warn!("motor controller {:?} disagrees with command", flag);
+                self.mismatch.insert(flag);
+            }
+        }
+
+        self.mismatch
+    }
+}
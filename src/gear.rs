@@ -0,0 +1,97 @@
+//! Forward/neutral/reverse gear selection
+//!
+//! Interlocks direction changes against vehicle speed and signs the velocity
+//! set point of drive commands for the selected direction.
+
+use crate::driver_controls::DriveCommand;
+use crate::wavesculptor::Status;
+
+/// Selected gear
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Gear {
+    Reverse,
+    #[default]
+    Neutral,
+    Drive,
+}
+
+/// Gear selector configuration
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Speed above which a direction change is refused in m/s
+    pub max_shift_speed: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            max_shift_speed: 0.5,
+        }
+    }
+}
+
+/// Gear selector state machine
+#[derive(Debug, Clone, Copy)]
+pub struct GearSelector {
+    config: Config,
+    gear: Gear,
+}
+
+impl GearSelector {
+    /// Create a new gear selector in neutral.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            gear: Gear::Neutral,
+        }
+    }
+
+    /// Currently selected gear.
+    pub fn gear(&self) -> Gear {
+        self.gear
+    }
+
+    /// Request a gear change.
+    ///
+    /// Neutral may always be selected. Drive and reverse may only be selected
+    /// when the vehicle is stationary or already moving in that direction.
+    pub fn request(&mut self, gear: Gear, status: &Status) -> Result<Gear, &'static str> {
+        let allowed = match gear {
+            Gear::Neutral => true,
+            _ if gear == self.gear => true,
+            _ => {
+                let velocity = status
+                    .vehicle_velocity
+                    .ok_or("vehicle velocity not available")?;
+
+                let moving_with = match gear {
+                    Gear::Drive => velocity >= 0.0,
+                    _ => velocity <= 0.0,
+                };
+
+                moving_with || velocity.abs() <= self.config.max_shift_speed
+            }
+        };
+
+        if !allowed {
+            return Err("vehicle moving too fast to change direction");
+        }
+
+        self.gear = gear;
+
+        Ok(self.gear)
+    }
+
+    /// Sign a drive command's velocity set point for the selected gear.
+    ///
+    /// Neutral always results in a zero torque command.
+    pub fn apply(&self, command: DriveCommand) -> DriveCommand {
+        match self.gear {
+            Gear::Drive => DriveCommand::new(command.velocity_rpm.abs(), command.current_percent),
+            Gear::Reverse => {
+                DriveCommand::new(-command.velocity_rpm.abs(), command.current_percent)
+            }
+            Gear::Neutral => DriveCommand::default(),
+        }
+    }
+}
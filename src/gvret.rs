@@ -0,0 +1,307 @@
+//! GVRET serial protocol
+//!
+//! Implements the device side of the GVRET binary serial protocol, so a
+//! device using this crate can act as a SavvyCAN capture interface for the
+//! Prohelion bus. Host commands are parsed a byte at a time by [`Gvret`],
+//! and captured frames are streamed to the host with [`encode_frame`].
+//!
+//! Every message starts with `0xF1` followed by a command byte. A single
+//! `0xE7` switches the device to binary mode, after which captured frames
+//! should be streamed. Only one bus is reported to the host.
+
+use bxcan::{Data, ExtendedId, Frame, Id, StandardId};
+
+/// Longest encoded frame in bytes
+pub const MAX_FRAME_LEN: usize = 20;
+
+/// Firmware build number reported to the host
+const BUILD_NUMBER: u16 = 343;
+
+/// Message start byte
+const START: u8 = 0xF1;
+
+/// Byte switching the device to binary mode
+const ENABLE_BINARY: u8 = 0xE7;
+
+/// Identifier flag marking an extended identifier
+const EXTENDED_FLAG: u32 = 1 << 31;
+
+/// Commands
+const BUILD_CAN_FRAME: u8 = 0x00;
+const TIME_SYNC: u8 = 0x01;
+const SET_DIG_OUT: u8 = 0x04;
+const SETUP_CANBUS: u8 = 0x05;
+const GET_CANBUS_PARAMS: u8 = 0x06;
+const GET_DEVICE_INFO: u8 = 0x07;
+const SET_SINGLEWIRE_MODE: u8 = 0x08;
+const KEEPALIVE: u8 = 0x09;
+const SET_SYSTEM_TYPE: u8 = 0x0A;
+const ECHO_CAN_FRAME: u8 = 0x0B;
+const GET_NUMBUSES: u8 = 0x0C;
+const SET_EXT_BUSES: u8 = 0x0E;
+
+/// Encode a captured data frame into `buf`, returning the encoded length.
+///
+/// `timestamp_us` is the capture time in microseconds, wrapping.
+pub fn encode_frame(
+    timestamp_us: u32,
+    frame: &Frame,
+    buf: &mut [u8],
+) -> Result<usize, &'static str> {
+    let Some(data) = frame.data() else {
+        return Err("GVRET cannot carry remote frames");
+    };
+
+    let len = 12 + data.len();
+    if buf.len() < len {
+        return Err("GVRET buffer too small");
+    }
+
+    let id = match frame.id() {
+        Id::Standard(id) => id.as_raw() as u32,
+        Id::Extended(id) => id.as_raw() | EXTENDED_FLAG,
+    };
+
+    buf[0] = START;
+    buf[1] = BUILD_CAN_FRAME;
+    buf[2..6].copy_from_slice(&timestamp_us.to_le_bytes());
+    buf[6..10].copy_from_slice(&id.to_le_bytes());
+    // bus number in the high nibble, always the first bus
+    buf[10] = data.len() as u8;
+    buf[11..len - 1].copy_from_slice(data);
+    // checksum, unused by hosts
+    buf[len - 1] = 0;
+
+    Ok(len)
+}
+
+/// CAN bus configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BusConfig {
+    /// Bit rate in bits per second
+    pub bitrate: u32,
+    /// Bus is enabled
+    pub enabled: bool,
+    /// Bus is listen only, never acknowledging or transmitting
+    pub listen_only: bool,
+}
+
+impl Default for BusConfig {
+    fn default() -> Self {
+        Self {
+            bitrate: 500_000,
+            enabled: true,
+            listen_only: false,
+        }
+    }
+}
+
+/// Reply to send to the host
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reply {
+    buf: [u8; 12],
+    len: u8,
+}
+
+impl Reply {
+    fn new(command: u8, body: &[u8]) -> Self {
+        let mut buf = [0; 12];
+        buf[0] = START;
+        buf[1] = command;
+        buf[2..2 + body.len()].copy_from_slice(body);
+
+        Self {
+            buf,
+            len: 2 + body.len() as u8,
+        }
+    }
+
+    /// Reply bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len as usize]
+    }
+}
+
+/// Action requested by the host
+#[derive(Debug, Clone, PartialEq)]
+pub enum Output {
+    /// Send a reply to the host
+    Reply(Reply),
+    /// Transmit a frame on the bus
+    Transmit(Frame),
+    /// Reconfigure the bus
+    Configure(BusConfig),
+}
+
+/// Parser state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Idle,
+    Command,
+    Collect { command: u8, need: usize },
+}
+
+/// GVRET host command parser
+#[derive(Debug, Clone, Copy)]
+pub struct Gvret {
+    config: BusConfig,
+    binary: bool,
+    state: State,
+    buf: [u8; 16],
+    len: usize,
+}
+
+impl Gvret {
+    /// Create a new parser, reporting `config` to the host.
+    pub fn new(config: BusConfig) -> Self {
+        Self {
+            config,
+            binary: false,
+            state: State::Idle,
+            buf: [0; 16],
+            len: 0,
+        }
+    }
+
+    /// Has the host switched to binary mode? Captured frames should only be
+    /// streamed once it has.
+    pub fn is_binary(&self) -> bool {
+        self.binary
+    }
+
+    /// Bus configuration reported to the host.
+    pub fn bus_config(&self) -> BusConfig {
+        self.config
+    }
+
+    /// Process a byte received from the host.
+    ///
+    /// `timestamp_us` is the current time in microseconds, as used for
+    /// captured frames.
+    pub fn receive(&mut self, byte: u8, timestamp_us: u32) -> Option<Output> {
+        match self.state {
+            State::Idle => {
+                match byte {
+                    START => self.state = State::Command,
+                    ENABLE_BINARY => self.binary = true,
+                    _ => {}
+                }
+
+                None
+            }
+            State::Command => {
+                self.state = State::Idle;
+                self.command(byte, timestamp_us)
+            }
+            State::Collect { command, need } => {
+                self.buf[self.len] = byte;
+                self.len += 1;
+
+                // frame length is only known once the header is in
+                if (command == BUILD_CAN_FRAME || command == ECHO_CAN_FRAME) && self.len == 6 {
+                    let len = (self.buf[5] & 0x0F).min(8) as usize;
+                    self.state = State::Collect {
+                        command,
+                        need: 6 + len + 1,
+                    };
+
+                    return None;
+                }
+
+                if self.len < need {
+                    return None;
+                }
+
+                self.state = State::Idle;
+                self.collected(command)
+            }
+        }
+    }
+
+    /// Handle a command byte.
+    fn command(&mut self, command: u8, timestamp_us: u32) -> Option<Output> {
+        let need = match command {
+            TIME_SYNC => {
+                return Some(Output::Reply(Reply::new(
+                    TIME_SYNC,
+                    &timestamp_us.to_le_bytes(),
+                )))
+            }
+            GET_CANBUS_PARAMS => {
+                let flags = self.config.enabled as u8 | (self.config.listen_only as u8) << 4;
+
+                let mut body = [0; 10];
+                body[0] = flags;
+                body[1..5].copy_from_slice(&self.config.bitrate.to_le_bytes());
+
+                return Some(Output::Reply(Reply::new(GET_CANBUS_PARAMS, &body)));
+            }
+            GET_DEVICE_INFO => {
+                let build = BUILD_NUMBER.to_le_bytes();
+
+                return Some(Output::Reply(Reply::new(
+                    GET_DEVICE_INFO,
+                    &[build[0], build[1], 0, 0, 0, 0],
+                )));
+            }
+            KEEPALIVE => return Some(Output::Reply(Reply::new(KEEPALIVE, &[0xDE, 0xAD]))),
+            GET_NUMBUSES => return Some(Output::Reply(Reply::new(GET_NUMBUSES, &[1]))),
+            BUILD_CAN_FRAME | ECHO_CAN_FRAME => 6,
+            SETUP_CANBUS => 8,
+            SET_EXT_BUSES => 12,
+            SET_DIG_OUT | SET_SINGLEWIRE_MODE | SET_SYSTEM_TYPE => 1,
+            _ => {
+                log_debug!("unsupported GVRET command {:#04x}", command);
+                return None;
+            }
+        };
+
+        self.len = 0;
+        self.state = State::Collect { command, need };
+
+        None
+    }
+
+    /// Handle a command's collected arguments.
+    fn collected(&mut self, command: u8) -> Option<Output> {
+        match command {
+            BUILD_CAN_FRAME => {
+                let raw = u32::from_le_bytes(self.buf[0..4].try_into().unwrap());
+                let len = self.len - 7;
+
+                let id: Id = if raw & EXTENDED_FLAG != 0 {
+                    ExtendedId::new(raw & !EXTENDED_FLAG)?.into()
+                } else {
+                    StandardId::new(u16::try_from(raw).ok()?)?.into()
+                };
+
+                // only the first bus is available
+                if self.buf[4] != 0 {
+                    return None;
+                }
+
+                let data = Data::new(&self.buf[6..6 + len])?;
+
+                Some(Output::Transmit(Frame::new_data(id, data)))
+            }
+            SETUP_CANBUS => {
+                let value = u32::from_le_bytes(self.buf[0..4].try_into().unwrap());
+
+                let bitrate = value & 0xF_FFFF;
+                if bitrate != 0 {
+                    self.config.bitrate = bitrate;
+                }
+
+                if value & 1 << 31 != 0 {
+                    self.config.enabled = value & 1 << 30 != 0;
+                    self.config.listen_only = value & 1 << 29 != 0;
+                } else {
+                    self.config.enabled = true;
+                }
+
+                Some(Output::Configure(self.config))
+            }
+            _ => None,
+        }
+    }
+}
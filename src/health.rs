@@ -0,0 +1,203 @@
+//! Pack state of health
+//!
+//! Tracks the pack's usable capacity over its life by measuring the
+//! amp-hours drawn between full charges, and compares it against the rated
+//! capacity. Only cycles discharging the pack to its empty voltage are
+//! measured, since shallower cycles don't show the usable capacity.
+//!
+//! The estimate and any cycle in progress are persisted through a
+//! [`Storage`] so they survive power cycles.
+//!
+//! Pack current is positive while discharging.
+
+use crate::black_box::Storage;
+use crate::bms::Status;
+use crate::telemetry::crc16;
+
+/// Persisted record length in bytes
+pub const RECORD_LEN: usize = 27;
+
+/// Persisted record marker
+const MAGIC: [u8; 4] = *b"SOH1";
+
+/// State of health estimator configuration
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Rated pack capacity in amp-hours
+    pub rated_capacity: f32,
+    /// Highest cell voltage at which the pack is full in millivolts
+    pub full_cell_voltage: u16,
+    /// Charge current below which the pack is full once at the full
+    /// voltage in amps
+    pub full_current: f32,
+    /// Lowest cell voltage at which the pack is empty in millivolts
+    pub empty_cell_voltage: u16,
+    /// Weight given to each new measurement, from 0 to 1
+    pub smoothing: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            rated_capacity: 40.0,
+            full_cell_voltage: 4150,
+            full_current: 2.0,
+            empty_cell_voltage: 3000,
+            smoothing: 0.25,
+        }
+    }
+}
+
+/// Pack state of health estimator
+#[derive(Debug, Clone, Copy)]
+pub struct StateOfHealth {
+    config: Config,
+    estimate: Option<f32>,
+    cycles: u32,
+    /// Net amp-hours drawn since the last full charge, `None` until one
+    discharged: Option<f32>,
+    /// Most amp-hours drawn since the last full charge
+    deepest: f32,
+    reached_empty: bool,
+    full: bool,
+    last_ms: Option<u32>,
+}
+
+impl StateOfHealth {
+    /// Create a new estimator with no measurements.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            estimate: None,
+            cycles: 0,
+            discharged: None,
+            deepest: 0.0,
+            reached_empty: false,
+            full: false,
+            last_ms: None,
+        }
+    }
+
+    /// Create an estimator restored from the record at `offset`, or a new
+    /// one if there is no valid record.
+    pub fn load<S: Storage>(
+        config: Config,
+        storage: &mut S,
+        offset: u32,
+    ) -> Result<Self, &'static str> {
+        let mut bytes = [0; RECORD_LEN];
+        storage.read(offset, &mut bytes)?;
+
+        let crc = u16::from_le_bytes([bytes[RECORD_LEN - 2], bytes[RECORD_LEN - 1]]);
+        if bytes[..4] != MAGIC || crc16(&bytes[..RECORD_LEN - 2]) != crc {
+            log_info!("no state of health record");
+            return Ok(Self::new(config));
+        }
+
+        let f32_at =
+            |offset: usize| f32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+        let flags = bytes[24];
+
+        Ok(Self {
+            estimate: Some(f32_at(4)).filter(|estimate| !estimate.is_nan()),
+            cycles: u32::from_le_bytes(bytes[8..12].try_into().unwrap()),
+            discharged: Some(f32_at(12)).filter(|_| flags & 1 << 0 != 0),
+            deepest: f32_at(16),
+            reached_empty: flags & 1 << 1 != 0,
+            full: flags & 1 << 2 != 0,
+            ..Self::new(config)
+        })
+    }
+
+    /// Persist the estimator to the record at `offset`.
+    pub fn save<S: Storage>(&self, storage: &mut S, offset: u32) -> Result<(), &'static str> {
+        let mut bytes = [0; RECORD_LEN];
+        bytes[..4].copy_from_slice(&MAGIC);
+        bytes[4..8].copy_from_slice(&self.estimate.unwrap_or(f32::NAN).to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.cycles.to_le_bytes());
+        bytes[12..16].copy_from_slice(&self.discharged.unwrap_or(0.0).to_le_bytes());
+        bytes[16..20].copy_from_slice(&self.deepest.to_le_bytes());
+        // 20..24 reserved
+        bytes[24] = self.discharged.is_some() as u8
+            | (self.reached_empty as u8) << 1
+            | (self.full as u8) << 2;
+
+        let crc = crc16(&bytes[..RECORD_LEN - 2]);
+        bytes[RECORD_LEN - 2..].copy_from_slice(&crc.to_le_bytes());
+
+        storage.write(offset, &bytes)
+    }
+
+    /// State of health as a fraction of the rated capacity, 1.0 being new.
+    pub fn state_of_health(&self) -> Option<f32> {
+        self.estimate
+    }
+
+    /// Usable capacity in amp-hours.
+    pub fn capacity(&self) -> Option<f32> {
+        Some(self.estimate? * self.config.rated_capacity)
+    }
+
+    /// Number of cycles measured.
+    pub fn cycles(&self) -> u32 {
+        self.cycles
+    }
+
+    /// Update from the latest BMU status and return the state of health.
+    pub fn update<const CMUS: usize>(&mut self, now_ms: u32, status: &Status<CMUS>) -> Option<f32> {
+        let elapsed_ms = self
+            .last_ms
+            .replace(now_ms)
+            .map_or(0, |last_ms| now_ms.wrapping_sub(last_ms));
+
+        let Some(current_ma) = status.pack_current_ma else {
+            return self.estimate;
+        };
+        let current = current_ma as f32 / 1000.0;
+
+        if let Some(discharged) = &mut self.discharged {
+            *discharged += current * elapsed_ms as f32 / 3_600_000.0;
+            self.deepest = self.deepest.max(*discharged);
+        }
+
+        if status
+            .minimum_voltage_cell
+            .is_some_and(|cell| cell.voltage <= self.config.empty_cell_voltage)
+        {
+            self.reached_empty = true;
+        }
+
+        // full once the charge current tapers off, not while discharging
+        let full = status
+            .maximum_voltage_cell
+            .is_some_and(|cell| cell.voltage >= self.config.full_cell_voltage)
+            && current <= 0.0
+            && -current < self.config.full_current;
+
+        if full && !self.full {
+            self.full_charge();
+        }
+        self.full = full;
+
+        self.estimate
+    }
+
+    /// Finish the cycle since the previous full charge and start another.
+    fn full_charge(&mut self) {
+        if self.discharged.is_some() && self.reached_empty {
+            let measured = self.deepest / self.config.rated_capacity;
+
+            self.cycles = self.cycles.saturating_add(1);
+            self.estimate = Some(match self.estimate {
+                Some(estimate) => estimate + self.config.smoothing * (measured - estimate),
+                None => measured,
+            });
+
+            log_info!("pack delivered {} Ah between full charges", self.deepest);
+        }
+
+        self.discharged = Some(0.0);
+        self.deepest = 0.0;
+        self.reached_empty = false;
+    }
+}
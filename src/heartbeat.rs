@@ -0,0 +1,176 @@
+//! Heartbeat supervision
+//!
+//! Watches a device's periodic broadcasts and puts the vehicle into a safe
+//! state, through a caller supplied callback, when they stop. The callback
+//! might open the contactors or command zero torque.
+//!
+//! [`DriveFallback`] applies the same supervision to the motor controller,
+//! replacing drive commands with zero torque while it is silent.
+//!
+//! Supervision starts with the first check, so a device that never reports
+//! trips the supervisor once the grace period has passed.
+
+use bxcan::Frame;
+
+use crate::bms::Bmu;
+use crate::driver_controls::{DriveCommand, DriverControls};
+use crate::wavesculptor::WaveSculptor;
+
+/// Heartbeat supervisor configuration
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Silence after which the heartbeat is lost in milliseconds
+    pub grace_ms: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        // the BMU heartbeat is broadcast once a second
+        Self { grace_ms: 3000 }
+    }
+}
+
+/// Heartbeat supervisor invoking `F` when the heartbeat is lost
+pub struct HeartbeatSupervisor<F> {
+    config: Config,
+    safe_state: F,
+    last_beat_ms: Option<u32>,
+    period_ms: Option<u32>,
+    lost: bool,
+}
+
+impl<F: FnMut()> HeartbeatSupervisor<F> {
+    /// Create a new supervisor calling `safe_state` each time the heartbeat
+    /// is lost.
+    pub fn new(config: Config, safe_state: F) -> Self {
+        Self {
+            config,
+            safe_state,
+            last_beat_ms: None,
+            period_ms: None,
+            lost: false,
+        }
+    }
+
+    /// Interval between the latest two heartbeats in milliseconds.
+    pub fn period_ms(&self) -> Option<u32> {
+        self.period_ms
+    }
+
+    /// Has the heartbeat been lost?
+    pub fn is_lost(&self) -> bool {
+        self.lost
+    }
+
+    /// Note a heartbeat received at `now_ms`.
+    pub fn beat(&mut self, now_ms: u32) {
+        if let Some(last_beat_ms) = self.last_beat_ms {
+            self.period_ms = Some(now_ms.wrapping_sub(last_beat_ms));
+        }
+
+        self.last_beat_ms = Some(now_ms);
+
+        if self.lost {
+            log_info!("heartbeat restored");
+            self.lost = false;
+        }
+    }
+
+    /// Check for a lost heartbeat, invoking the safe state callback when it
+    /// is first lost, and return whether it is lost.
+    pub fn check(&mut self, now_ms: u32) -> bool {
+        let last_beat_ms = *self.last_beat_ms.get_or_insert(now_ms);
+
+        if !self.lost && now_ms.wrapping_sub(last_beat_ms) >= self.config.grace_ms {
+            log_warn!("heartbeat lost, entering safe state");
+            self.lost = true;
+            (self.safe_state)();
+        }
+
+        self.lost
+    }
+
+    /// Supervise a BMU's heartbeat, as noted by
+    /// [`Bmu::receive_at`](crate::bms::Bmu::receive_at), and return whether
+    /// it is lost.
+    pub fn update<const CMUS: usize>(&mut self, now_ms: u32, bmu: &Bmu<CMUS>) -> bool {
+        self.update_last_seen(now_ms, bmu.last_heartbeat_ms())
+    }
+
+    /// Supervise a device last heard from at `last_seen_ms` and return
+    /// whether its heartbeat is lost.
+    pub fn update_last_seen(&mut self, now_ms: u32, last_seen_ms: Option<u32>) -> bool {
+        if let Some(last_seen_ms) = last_seen_ms {
+            if self.last_beat_ms != Some(last_seen_ms) {
+                self.beat(last_seen_ms);
+            }
+        }
+
+        self.check(now_ms)
+    }
+}
+
+/// Drive command fallback for a silent motor controller
+///
+/// While the WaveSculptor's broadcasts are lost drive commands are replaced
+/// with zero torque, and an alarm is latched until cleared. Its status is
+/// broadcast several times a second, so a grace period well below the
+/// BMU's suits it.
+pub struct DriveFallback {
+    supervisor: HeartbeatSupervisor<fn()>,
+    alarm: bool,
+}
+
+impl DriveFallback {
+    /// Create a new fallback.
+    pub fn new(config: Config) -> Self {
+        Self {
+            supervisor: HeartbeatSupervisor::new(config, || {}),
+            alarm: false,
+        }
+    }
+
+    /// Are the motor controller's broadcasts lost?
+    pub fn is_offline(&self) -> bool {
+        self.supervisor.is_lost()
+    }
+
+    /// Has the motor controller gone offline since the alarm was cleared?
+    pub fn alarm(&self) -> bool {
+        self.alarm
+    }
+
+    /// Clear the offline alarm.
+    pub fn clear_alarm(&mut self) {
+        self.alarm = false;
+    }
+
+    /// Supervise a WaveSculptor's broadcasts, as noted by
+    /// [`WaveSculptor::receive_at`], and return whether they are lost.
+    pub fn update(&mut self, now_ms: u32, ws: &WaveSculptor) -> bool {
+        self.update_last_seen(now_ms, ws.last_receive_ms())
+    }
+
+    /// Supervise a motor controller last heard from at `last_seen_ms` and
+    /// return whether its broadcasts are lost.
+    pub fn update_last_seen(&mut self, now_ms: u32, last_seen_ms: Option<u32>) -> bool {
+        let offline = self.supervisor.update_last_seen(now_ms, last_seen_ms);
+        self.alarm |= offline;
+
+        offline
+    }
+
+    /// Drive command to send, zero torque while offline.
+    pub fn command(&self, command: DriveCommand) -> DriveCommand {
+        if self.is_offline() {
+            DriveCommand::torque(0.0)
+        } else {
+            command
+        }
+    }
+
+    /// Motor drive frame to send, zero torque while offline.
+    pub fn drive(&self, controls: DriverControls, command: DriveCommand) -> Frame {
+        controls.drive(self.command(command))
+    }
+}
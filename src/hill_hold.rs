@@ -0,0 +1,92 @@
+//! Hill hold and anti-rollback assist
+//!
+//! Detects the vehicle rolling against the selected gear and holds it with a
+//! zero velocity command until the driver applies power.
+
+use crate::driver_controls::DriveCommand;
+use crate::gear::Gear;
+use crate::wavesculptor::Status;
+
+/// Hill hold configuration
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Enable hill hold
+    pub enabled: bool,
+    /// Speed against the selected gear considered rolling back in m/s
+    pub rollback_speed: f32,
+    /// Current used to hold the vehicle in percent
+    pub hold_current_percent: f32,
+    /// Driver current request that releases the hold in percent
+    pub release_current_percent: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            rollback_speed: 0.2,
+            hold_current_percent: 20.0,
+            release_current_percent: 5.0,
+        }
+    }
+}
+
+/// Hill hold assist
+#[derive(Debug, Clone, Copy)]
+pub struct HillHold {
+    config: Config,
+    holding: bool,
+}
+
+impl HillHold {
+    /// Create a new hill hold assist.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            holding: false,
+        }
+    }
+
+    /// Enable or disable hill hold, releasing any active hold.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.config.enabled = enabled;
+        self.holding = false;
+    }
+
+    /// Is the vehicle currently being held?
+    pub fn is_holding(&self) -> bool {
+        self.holding
+    }
+
+    /// Replace the driver's command with a holding command while rolling back.
+    pub fn apply(&mut self, gear: Gear, command: DriveCommand, status: &Status) -> DriveCommand {
+        if !self.config.enabled || gear == Gear::Neutral {
+            self.holding = false;
+            return command;
+        }
+
+        if let Some(velocity) = status.vehicle_velocity {
+            let rolling_back = match gear {
+                Gear::Drive => velocity < -self.config.rollback_speed,
+                _ => velocity > self.config.rollback_speed,
+            };
+
+            if rolling_back {
+                self.holding = true;
+            }
+        }
+
+        let driving = command.velocity_rpm != 0.0
+            && command.current_percent >= self.config.release_current_percent;
+
+        if self.holding && driving {
+            self.holding = false;
+        }
+
+        if self.holding {
+            DriveCommand::regen(self.config.hold_current_percent)
+        } else {
+            command
+        }
+    }
+}
@@ -0,0 +1,210 @@
+//! Launch control
+//!
+//! Standing-start acceleration profile for events such as autocross. Once
+//! armed with the vehicle stationary, a launch ramps the commanded current
+//! from an initial value, holding the ramp while the bus current is at its
+//! limit and scaling it back by traction control while the wheel slips.
+//! The launch completes at a hand-over speed, after which the driver's
+//! input should be used again.
+
+use bxcan::Frame;
+
+use crate::driver_controls::{DriveCommand, DriverControls};
+use crate::traction::{self, TractionControl};
+use crate::tx::TxSink;
+use crate::wavesculptor::Status;
+
+/// Launch control configuration
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Current commanded as the launch starts in percent
+    pub initial_percent: f32,
+    /// Current ramp rate in percent per second
+    pub ramp_rate: f32,
+    /// Highest current commanded in percent
+    pub max_percent: f32,
+    /// Bus current in amps at which the ramp is held
+    pub bus_current_limit: f32,
+    /// Speed above which arming is refused in m/s
+    pub max_arm_speed: f32,
+    /// Speed at which the launch completes in m/s
+    pub end_speed: f32,
+    /// Slip detection shaping the ramp
+    pub traction: traction::Config,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            initial_percent: 20.0,
+            ramp_rate: 100.0,
+            max_percent: 100.0,
+            bus_current_limit: 80.0,
+            max_arm_speed: 0.5,
+            end_speed: 15.0,
+            traction: traction::Config::default(),
+        }
+    }
+}
+
+/// Launch control state
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LaunchState {
+    /// Not in use
+    #[default]
+    Idle,
+    /// Holding zero torque, waiting for the launch
+    Armed,
+    /// Ramping the current
+    Launching,
+    /// Reached the hand-over speed
+    Complete,
+}
+
+/// Launch control
+#[derive(Debug, Clone, Copy)]
+pub struct LaunchControl {
+    config: Config,
+    state: LaunchState,
+    traction: TractionControl,
+    ramp_percent: f32,
+    last_update_ms: Option<u32>,
+}
+
+impl LaunchControl {
+    /// Create a new, idle, launch control instance.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            state: LaunchState::Idle,
+            traction: TractionControl::new(config.traction),
+            ramp_percent: 0.0,
+            last_update_ms: None,
+        }
+    }
+
+    /// Current state.
+    pub fn state(&self) -> LaunchState {
+        self.state
+    }
+
+    /// Arm, holding zero torque until [`launch`](Self::launch).
+    ///
+    /// Fails unless the vehicle is known to be stationary.
+    pub fn arm(&mut self, status: &Status) -> Result<(), &'static str> {
+        let velocity = status
+            .vehicle_velocity
+            .ok_or("vehicle velocity not available")?;
+
+        if velocity.abs() > self.config.max_arm_speed {
+            return Err("vehicle not stationary");
+        }
+
+        self.state = LaunchState::Armed;
+        log_info!("launch control armed");
+
+        Ok(())
+    }
+
+    /// Start the launch, for example as the brake is released.
+    pub fn launch(&mut self) -> Result<(), &'static str> {
+        if self.state != LaunchState::Armed {
+            return Err("launch control not armed");
+        }
+
+        self.state = LaunchState::Launching;
+        self.traction = TractionControl::new(self.config.traction);
+        self.ramp_percent = self.config.initial_percent.min(self.config.max_percent);
+        self.last_update_ms = None;
+
+        Ok(())
+    }
+
+    /// Abandon an armed or running launch.
+    pub fn abort(&mut self) {
+        if matches!(self.state, LaunchState::Armed | LaunchState::Launching) {
+            log_info!("launch aborted");
+        }
+
+        self.state = LaunchState::Idle;
+    }
+
+    /// Run the launch profile.
+    ///
+    /// Returns `None` unless armed or launching, in which case the driver's
+    /// input should be used instead. Loss of velocity feedback aborts the
+    /// launch.
+    pub fn update(
+        &mut self,
+        now_ms: u32,
+        status: &Status,
+        reference_speed: Option<f32>,
+    ) -> Option<DriveCommand> {
+        match self.state {
+            LaunchState::Armed => return Some(DriveCommand::torque(0.0)),
+            LaunchState::Launching => {}
+            _ => return None,
+        }
+
+        let Some(velocity) = status.vehicle_velocity else {
+            self.abort();
+            return None;
+        };
+
+        if velocity >= self.config.end_speed {
+            log_info!("launch complete");
+            self.state = LaunchState::Complete;
+            return None;
+        }
+
+        let dt = match self.last_update_ms {
+            Some(last) => now_ms.wrapping_sub(last) as f32 / 1000.0,
+            None => 0.0,
+        };
+        self.last_update_ms = Some(now_ms);
+
+        let at_limit = status
+            .bus_current
+            .is_some_and(|current| current >= self.config.bus_current_limit);
+
+        if !at_limit {
+            self.ramp_percent =
+                (self.ramp_percent + self.config.ramp_rate * dt).min(self.config.max_percent);
+        }
+
+        let factor = self.traction.update(now_ms, status, reference_speed);
+
+        Some(DriveCommand::torque(self.ramp_percent * factor))
+    }
+
+    /// Run the launch profile, returning the drive frame to send.
+    pub fn update_frame(
+        &mut self,
+        now_ms: u32,
+        controls: DriverControls,
+        status: &Status,
+        reference_speed: Option<f32>,
+    ) -> Option<Frame> {
+        self.update(now_ms, status, reference_speed)
+            .map(|command| controls.drive(command))
+    }
+
+    /// Run the launch profile, sending the drive command through `sink`
+    /// when armed or launching.
+    pub fn update_into(
+        &mut self,
+        now_ms: u32,
+        controls: DriverControls,
+        status: &Status,
+        reference_speed: Option<f32>,
+        sink: &mut impl TxSink,
+    ) -> Result<Option<DriveCommand>, &'static str> {
+        let command = self.update(now_ms, status, reference_speed);
+
+        if let Some(command) = command {
+            sink.transmit(&controls.drive(command))?;
+        }
+
+        Ok(command)
+    }
+}
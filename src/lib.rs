@@ -1,6 +1,98 @@
 #![no_std]
 #![allow(dead_code)]
 
+#[cfg(feature = "std")]
+extern crate std;
+
+// Diagnostics forwarded to the `log` crate when the `log` feature is enabled,
+// and otherwise compiled out.
+macro_rules! log_at {
+    ($level:ident, $($arg:tt)*) => {{
+        #[cfg(feature = "log")]
+        log::$level!($($arg)*);
+        #[cfg(not(feature = "log"))]
+        let _ = format_args!($($arg)*);
+    }};
+}
+
+macro_rules! log_warn {
+    ($($arg:tt)*) => { log_at!(warn, $($arg)*) };
+}
+
+macro_rules! log_info {
+    ($($arg:tt)*) => { log_at!(info, $($arg)*) };
+}
+
+macro_rules! log_debug {
+    ($($arg:tt)*) => { log_at!(debug, $($arg)*) };
+}
+
+pub mod acceleration;
+pub mod alerts;
+pub mod arbiter;
+pub mod balancing;
+pub mod black_box;
 pub mod bms;
+#[cfg(feature = "socketcan")]
+pub mod bridge;
+pub mod budget;
+pub mod bus_health;
+pub mod bus_off;
+pub mod charger;
+pub mod charging;
+pub mod clock;
+pub mod coast_down;
+pub mod contactor;
+pub mod cruise;
+pub mod csv_log;
+pub mod diagnostics;
+pub mod display;
 pub mod driver_controls;
+pub mod efficiency;
+pub mod energy;
+pub mod feedback;
+pub mod gear;
+pub mod gvret;
+pub mod health;
+pub mod heartbeat;
+pub mod hill_hold;
+pub mod launch;
+pub mod limp;
+#[cfg(feature = "std")]
+pub mod mdf;
+pub mod motor_constant;
+pub mod motor_profile;
+pub mod mppt;
+#[cfg(feature = "std")]
+pub mod mqtt;
+pub mod odometer;
+pub mod pack;
+pub mod pedal;
+pub mod pedal_map;
+pub mod precharge;
+#[cfg(feature = "std")]
+pub mod profinity;
+#[cfg(feature = "std")]
+pub mod prometheus;
+pub mod ramp;
+pub mod regen;
+pub mod resistance;
+pub mod safety;
+#[cfg(feature = "sdmmc")]
+pub mod sdmmc;
+pub mod serial;
+pub mod slip;
+pub mod speed_limiter;
+pub mod telemetry;
+pub mod thermal;
+pub mod thresholds;
+pub mod traction;
+pub mod tx;
+pub mod vectoring;
+pub mod vehicle;
 pub mod wavesculptor;
+
+#[cfg(feature = "proptest")]
+pub mod strategies;
+#[cfg(feature = "test_support")]
+pub mod test_support;
@@ -0,0 +1,78 @@
+//! Low state of charge limp mode
+//!
+//! Limits the bus current once the pack state of charge falls below a
+//! threshold, keeping the vehicle moving without over-discharging cells.
+
+use crate::bms::Status;
+
+/// Limp mode configuration
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// State of charge fraction below which limp mode activates
+    pub soc_threshold: f32,
+    /// State of charge fraction above the threshold needed to deactivate
+    pub hysteresis: f32,
+    /// Fraction of the requested bus current allowed in limp mode
+    pub factor: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            soc_threshold: 0.1,
+            hysteresis: 0.02,
+            factor: 0.5,
+        }
+    }
+}
+
+/// Low state of charge limp mode
+#[derive(Debug, Clone, Copy)]
+pub struct LimpMode {
+    config: Config,
+    active: bool,
+}
+
+impl LimpMode {
+    /// Create a new, inactive, limp mode.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            active: false,
+        }
+    }
+
+    /// Is limp mode active?
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Update from the BMU state of charge and return whether limp mode is
+    /// active.
+    ///
+    /// The state is held while the state of charge is unknown.
+    pub fn update<const CMUS: usize>(&mut self, status: &Status<CMUS>) -> bool {
+        if let Some(soc) = status.soc_percent {
+            if soc < self.config.soc_threshold {
+                self.active = true;
+            } else if soc > self.config.soc_threshold + self.config.hysteresis {
+                self.active = false;
+            }
+        }
+
+        self.active
+    }
+
+    /// Update and scale a requested bus current in percent.
+    pub fn bus_current_percent<const CMUS: usize>(
+        &mut self,
+        requested: f32,
+        status: &Status<CMUS>,
+    ) -> f32 {
+        if self.update(status) {
+            requested * self.config.factor
+        } else {
+            requested
+        }
+    }
+}
@@ -0,0 +1,291 @@
+//! MDF4 export
+//!
+//! Converts recorded snapshot streams and frame logs to ASAM MDF 4.10 files,
+//! for analysing drives in measurement tools such as CANape, vMeasure or
+//! asammdf.
+//!
+//! Each export holds a single channel group with a `time` master channel in
+//! seconds since the start of the recording. Snapshots have a float channel
+//! per signal, invalidated where a snapshot doesn't know the signal. Frames
+//! have `can_id`, `dlc` and `data` channels.
+//!
+//! Enabled with the `std` feature.
+
+use std::string::String;
+use std::vec::Vec;
+
+use crate::black_box::RawFrame;
+use crate::thresholds::{Measurements, Signal};
+
+/// Block header length in bytes
+const HEADER_LEN: usize = 24;
+
+/// Channel types
+const CN_TYPE_VALUE: u8 = 0;
+const CN_TYPE_MASTER: u8 = 2;
+
+/// Channel synchronization types
+const SYNC_NONE: u8 = 0;
+const SYNC_TIME: u8 = 1;
+
+/// Channel data types
+const DATA_UNSIGNED: u8 = 0;
+const DATA_FLOAT: u8 = 4;
+const DATA_BYTES: u8 = 10;
+
+/// Channel flag marking its invalidation bit as valid
+const CN_FLAG_INVALIDATION: u32 = 1 << 1;
+
+/// Channel layout within a record
+struct Channel {
+    name: &'static str,
+    unit: Option<&'static str>,
+    cn_type: u8,
+    sync_type: u8,
+    data_type: u8,
+    byte_offset: u32,
+    bit_count: u32,
+    invalidation_bit: Option<u32>,
+}
+
+impl Channel {
+    fn time() -> Self {
+        Self {
+            name: "time",
+            unit: Some("s"),
+            cn_type: CN_TYPE_MASTER,
+            sync_type: SYNC_TIME,
+            data_type: DATA_FLOAT,
+            byte_offset: 0,
+            bit_count: 64,
+            invalidation_bit: None,
+        }
+    }
+
+    fn value(name: &'static str, data_type: u8, byte_offset: u32, bit_count: u32) -> Self {
+        Self {
+            name,
+            unit: None,
+            cn_type: CN_TYPE_VALUE,
+            sync_type: SYNC_NONE,
+            data_type,
+            byte_offset,
+            bit_count,
+            invalidation_bit: None,
+        }
+    }
+}
+
+/// Export snapshots, each paired with its timestamp in milliseconds.
+///
+/// `start_time_ns` is the recording start time in nanoseconds since the
+/// UNIX epoch, UTC, and timestamps are relative to the first snapshot.
+pub fn export_snapshots(start_time_ns: u64, snapshots: &[(u32, &dyn Measurements)]) -> Vec<u8> {
+    let value_bytes = 8 + 4 * Signal::ALL.len();
+    let inval_bytes = Signal::ALL.len().div_ceil(8);
+
+    let mut channels = Vec::with_capacity(Signal::ALL.len() + 1);
+    channels.push(Channel::time());
+
+    for (index, signal) in Signal::ALL.into_iter().enumerate() {
+        let mut channel = Channel::value(signal.name(), DATA_FLOAT, 8 + 4 * index as u32, 32);
+        channel.invalidation_bit = Some(index as u32);
+
+        channels.push(channel);
+    }
+
+    let start_ms = snapshots.first().map_or(0, |(time_ms, _)| *time_ms);
+    let mut records = Vec::with_capacity(snapshots.len() * (value_bytes + inval_bytes));
+
+    for (time_ms, source) in snapshots {
+        let mut inval = [0u8; Signal::ALL.len().div_ceil(8)];
+
+        records.extend_from_slice(&seconds(start_ms, *time_ms).to_le_bytes());
+
+        for (index, signal) in Signal::ALL.into_iter().enumerate() {
+            let value = source.value(signal);
+            if value.is_none() {
+                inval[index / 8] |= 1 << (index % 8);
+            }
+
+            records.extend_from_slice(&value.unwrap_or(0.0).to_le_bytes());
+        }
+
+        records.extend_from_slice(&inval);
+    }
+
+    write(
+        start_time_ns,
+        &channels,
+        value_bytes as u32,
+        inval_bytes as u32,
+        snapshots.len() as u64,
+        &records,
+    )
+}
+
+/// Export frames, each paired with its timestamp in milliseconds.
+///
+/// See [`export_snapshots`].
+pub fn export_frames(start_time_ns: u64, frames: &[(u32, RawFrame)]) -> Vec<u8> {
+    let channels = [
+        Channel::time(),
+        Channel::value("can_id", DATA_UNSIGNED, 8, 16),
+        Channel::value("dlc", DATA_UNSIGNED, 10, 8),
+        Channel::value("data", DATA_BYTES, 11, 64),
+    ];
+
+    let value_bytes = 19;
+    let start_ms = frames.first().map_or(0, |(time_ms, _)| *time_ms);
+    let mut records = Vec::with_capacity(frames.len() * value_bytes);
+
+    for (time_ms, frame) in frames {
+        records.extend_from_slice(&seconds(start_ms, *time_ms).to_le_bytes());
+        records.extend_from_slice(&frame.id.to_le_bytes());
+        records.push(frame.len);
+        records.extend_from_slice(&frame.data);
+    }
+
+    write(
+        start_time_ns,
+        &channels,
+        value_bytes as u32,
+        0,
+        frames.len() as u64,
+        &records,
+    )
+}
+
+/// Seconds from `start_ms` to `time_ms`.
+fn seconds(start_ms: u32, time_ms: u32) -> f64 {
+    time_ms.wrapping_sub(start_ms) as f64 / 1000.0
+}
+
+/// Block writer
+struct Writer {
+    out: Vec<u8>,
+}
+
+impl Writer {
+    /// Append a block, returning its address.
+    fn block(&mut self, id: &[u8; 2], links: &[u64], data: &[u8]) -> u64 {
+        let address = self.out.len() as u64;
+        let len = HEADER_LEN + 8 * links.len() + data.len();
+
+        self.out.extend_from_slice(b"##");
+        self.out.extend_from_slice(id);
+        self.out.extend_from_slice(&[0; 4]);
+        self.out.extend_from_slice(&(len as u64).to_le_bytes());
+        self.out
+            .extend_from_slice(&(links.len() as u64).to_le_bytes());
+
+        for link in links {
+            self.out.extend_from_slice(&link.to_le_bytes());
+        }
+
+        self.out.extend_from_slice(data);
+
+        // blocks start 8 byte aligned
+        self.out.resize(self.out.len().next_multiple_of(8), 0);
+
+        address
+    }
+
+    /// Append a zero terminated text block, returning its address.
+    fn text(&mut self, id: &[u8; 2], text: &str) -> u64 {
+        let mut data = Vec::with_capacity(text.len() + 8);
+        data.extend_from_slice(text.as_bytes());
+        data.resize((text.len() + 1).next_multiple_of(8), 0);
+
+        self.block(id, &[], &data)
+    }
+
+    /// Set the `index`th link of the block at `address`.
+    fn set_link(&mut self, address: u64, index: usize, link: u64) {
+        let offset = address as usize + HEADER_LEN + 8 * index;
+        self.out[offset..offset + 8].copy_from_slice(&link.to_le_bytes());
+    }
+}
+
+/// Write a file holding a single channel group.
+fn write(
+    start_time_ns: u64,
+    channels: &[Channel],
+    value_bytes: u32,
+    inval_bytes: u32,
+    cycles: u64,
+    records: &[u8],
+) -> Vec<u8> {
+    let mut writer = Writer {
+        out: Vec::with_capacity(records.len() + 4096),
+    };
+
+    // identification block
+    writer.out.extend_from_slice(b"MDF     4.10    phln    ");
+    writer.out.extend_from_slice(&[0; 4]);
+    writer.out.extend_from_slice(&410u16.to_le_bytes());
+    writer.out.extend_from_slice(&[0; 34]);
+
+    // header block, linked up once the blocks below are written
+    let mut data = Vec::with_capacity(32);
+    data.extend_from_slice(&start_time_ns.to_le_bytes());
+    data.extend_from_slice(&[0; 24]);
+    let hd = writer.block(b"HD", &[0; 6], &data);
+
+    let mut comment = String::from("<FHcomment><TX>exported</TX><tool_id>phln</tool_id>");
+    comment.push_str("<tool_vendor>Team Arrow Racing</tool_vendor><tool_version>");
+    comment.push_str(env!("CARGO_PKG_VERSION"));
+    comment.push_str("</tool_version></FHcomment>");
+    let md = writer.text(b"MD", &comment);
+
+    let mut data = Vec::with_capacity(16);
+    data.extend_from_slice(&start_time_ns.to_le_bytes());
+    data.extend_from_slice(&[0; 8]);
+    let fh = writer.block(b"FH", &[0, md], &data);
+
+    let dt = writer.block(b"DT", &[], records);
+
+    // channels link to the next, so are written last first
+    let mut next = 0;
+
+    for channel in channels.iter().rev() {
+        let name = writer.text(b"TX", channel.name);
+        let unit = channel.unit.map_or(0, |unit| writer.text(b"TX", unit));
+
+        let mut data = Vec::with_capacity(72);
+        data.push(channel.cn_type);
+        data.push(channel.sync_type);
+        data.push(channel.data_type);
+        data.push(0);
+        data.extend_from_slice(&channel.byte_offset.to_le_bytes());
+        data.extend_from_slice(&channel.bit_count.to_le_bytes());
+
+        match channel.invalidation_bit {
+            Some(bit) => {
+                data.extend_from_slice(&CN_FLAG_INVALIDATION.to_le_bytes());
+                data.extend_from_slice(&bit.to_le_bytes());
+            }
+            None => data.extend_from_slice(&[0; 8]),
+        }
+
+        data.extend_from_slice(&[0; 4]);
+        data.extend_from_slice(&[0; 48]);
+
+        next = writer.block(b"CN", &[next, 0, name, 0, 0, 0, unit, 0], &data);
+    }
+
+    let mut data = Vec::with_capacity(32);
+    data.extend_from_slice(&0u64.to_le_bytes());
+    data.extend_from_slice(&cycles.to_le_bytes());
+    data.extend_from_slice(&[0; 8]);
+    data.extend_from_slice(&value_bytes.to_le_bytes());
+    data.extend_from_slice(&inval_bytes.to_le_bytes());
+    let cg = writer.block(b"CG", &[0, next, 0, 0, 0, 0], &data);
+
+    let dg = writer.block(b"DG", &[0, cg, dt, 0], &[0; 8]);
+
+    writer.set_link(hd, 0, dg);
+    writer.set_link(hd, 1, fh);
+
+    writer.out
+}
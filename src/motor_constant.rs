@@ -0,0 +1,126 @@
+//! Motor constant identification
+//!
+//! Fits the back-EMF constant Ke of a motor from the WaveSculptor's back-EMF
+//! vector and motor velocity while coasting, when the small motor current
+//! leaves the back-EMF undisturbed. The fit is a least squares line through
+//! the origin of back-EMF magnitude against angular velocity.
+//!
+//! In SI units the torque constant equals Ke, so the result can stand in
+//! for a datasheet value in [`Status::estimated_torque`] and in sanity
+//! checks of the measured back-EMF.
+
+use core::f32::consts::TAU;
+
+use crate::wavesculptor::Status;
+
+/// Identification configuration
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Motor current magnitude below which the motor is coasting in amps
+    pub max_current: f32,
+    /// Motor velocity below which samples are ignored in RPM
+    pub min_velocity_rpm: f32,
+    /// Samples needed before a constant is reported
+    pub min_samples: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            max_current: 1.0,
+            min_velocity_rpm: 100.0,
+            min_samples: 20,
+        }
+    }
+}
+
+/// Back-EMF constant estimator
+#[derive(Debug, Clone, Copy)]
+pub struct KeEstimator {
+    config: Config,
+    samples: u32,
+    sum_ew: f32,
+    sum_ww: f32,
+}
+
+impl KeEstimator {
+    /// Create a new estimator with no samples.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            samples: 0,
+            sum_ew: 0.0,
+            sum_ww: 0.0,
+        }
+    }
+
+    /// Number of samples fitted.
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    /// Forget all samples.
+    pub fn reset(&mut self) {
+        *self = Self::new(self.config);
+    }
+
+    /// Back-EMF constant in volts per radian/second, once enough samples
+    /// have been fitted.
+    pub fn ke(&self) -> Option<f32> {
+        (self.samples >= self.config.min_samples && self.sum_ww > 0.0)
+            .then(|| self.sum_ew / self.sum_ww)
+    }
+
+    /// Torque constant in Nm/A, equal to [`ke`](Self::ke).
+    pub fn kt(&self) -> Option<f32> {
+        self.ke()
+    }
+
+    /// Speed constant in RPM/V.
+    pub fn kv(&self) -> Option<f32> {
+        self.ke().filter(|ke| *ke > 0.0).map(|ke| 60.0 / (TAU * ke))
+    }
+
+    /// Back-EMF magnitude in volts expected at `velocity_rpm`.
+    pub fn expected_back_emf(&self, velocity_rpm: f32) -> Option<f32> {
+        Some(self.ke()? * velocity_rpm.abs() * TAU / 60.0)
+    }
+
+    /// Update from the latest WaveSculptor status, returning the constant.
+    pub fn update(&mut self, status: &Status) -> Option<f32> {
+        if let (Some(vectors), Some(velocity_rpm)) = (status.motor_vectors(), status.motor_velocity)
+        {
+            self.update_sample(
+                velocity_rpm,
+                vectors.back_emf_magnitude(),
+                vectors.current_magnitude(),
+            );
+        }
+
+        self.ke()
+    }
+
+    /// Update from a motor velocity in RPM, back-EMF magnitude in volts and
+    /// motor current magnitude in amps, returning the constant.
+    ///
+    /// Samples taken while driving, too slowly or that are not finite are
+    /// ignored.
+    pub fn update_sample(&mut self, velocity_rpm: f32, back_emf: f32, current: f32) -> Option<f32> {
+        let velocity_rpm = velocity_rpm.abs();
+
+        let valid = velocity_rpm.is_finite()
+            && back_emf.is_finite()
+            && current.abs() < self.config.max_current
+            && velocity_rpm >= self.config.min_velocity_rpm;
+
+        if valid {
+            let omega = velocity_rpm * TAU / 60.0;
+
+            self.samples = self.samples.saturating_add(1);
+            self.sum_ew += back_emf * omega;
+            self.sum_ww += omega * omega;
+        }
+
+        self.ke()
+    }
+}
@@ -0,0 +1,138 @@
+//! Motor profiles
+//!
+//! Describes a motor and the limits the WaveSculptor applies when driving it,
+//! for managing profiles from Rust tooling.
+//!
+//! The WaveSculptor's own configuration format is undocumented, so profiles
+//! serialize to a crate-defined little-endian layout of [`PROFILE_LEN`]
+//! bytes, versioned by its first byte.
+
+/// Serialized profile length in bytes
+pub const PROFILE_LEN: usize = 28;
+
+/// Serialized profile layout version
+const PROFILE_VERSION: u8 = 1;
+
+/// Motor type
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MotorType {
+    /// Brushless permanent magnet motor
+    #[default]
+    Brushless,
+    /// Induction motor
+    Induction,
+}
+
+impl MotorType {
+    fn from_u8(value: u8) -> Option<MotorType> {
+        match value {
+            0 => Some(MotorType::Brushless),
+            1 => Some(MotorType::Induction),
+            _ => None,
+        }
+    }
+}
+
+/// Motor profile
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MotorProfile {
+    /// Motor type
+    pub motor_type: MotorType,
+    /// Number of rotor pole pairs
+    pub pole_pairs: u8,
+    /// Torque constant in Nm/A
+    pub kt: f32,
+    /// Speed constant in RPM/V
+    pub kv: f32,
+    /// Maximum phase current in amps RMS
+    pub max_phase_current: f32,
+    /// Maximum bus current in amps
+    pub max_bus_current: f32,
+    /// Maximum regenerative bus current in amps
+    pub max_regen_current: f32,
+    /// Maximum motor velocity in RPM
+    pub max_velocity_rpm: f32,
+}
+
+impl MotorProfile {
+    /// Check the profile describes a physically plausible motor.
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if self.pole_pairs == 0 {
+            return Err("pole pairs must be at least one");
+        }
+
+        let positive = [
+            self.kt,
+            self.kv,
+            self.max_phase_current,
+            self.max_bus_current,
+            self.max_velocity_rpm,
+        ];
+
+        if positive
+            .iter()
+            .any(|value| !(value.is_finite() && *value > 0.0))
+        {
+            return Err("motor constants and limits must be positive");
+        }
+
+        if !(self.max_regen_current.is_finite() && self.max_regen_current >= 0.0) {
+            return Err("regenerative current limit must not be negative");
+        }
+
+        Ok(())
+    }
+
+    /// Serialize the profile.
+    pub fn to_bytes(&self) -> [u8; PROFILE_LEN] {
+        let mut bytes = [0u8; PROFILE_LEN];
+
+        bytes[0] = PROFILE_VERSION;
+        bytes[1] = self.motor_type as u8;
+        bytes[2] = self.pole_pairs;
+
+        let values = [
+            self.kt,
+            self.kv,
+            self.max_phase_current,
+            self.max_bus_current,
+            self.max_regen_current,
+            self.max_velocity_rpm,
+        ];
+
+        for (chunk, value) in bytes[4..].chunks_exact_mut(4).zip(values) {
+            chunk.copy_from_slice(&value.to_le_bytes());
+        }
+
+        bytes
+    }
+
+    /// Deserialize and validate a profile.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, &'static str> {
+        if bytes.len() < PROFILE_LEN {
+            return Err("profile too short");
+        }
+
+        if bytes[0] != PROFILE_VERSION {
+            return Err("unsupported profile version");
+        }
+
+        let f32_at =
+            |offset: usize| f32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+
+        let profile = Self {
+            motor_type: MotorType::from_u8(bytes[1]).ok_or("unknown motor type")?,
+            pole_pairs: bytes[2],
+            kt: f32_at(4),
+            kv: f32_at(8),
+            max_phase_current: f32_at(12),
+            max_bus_current: f32_at(16),
+            max_regen_current: f32_at(20),
+            max_velocity_rpm: f32_at(24),
+        };
+
+        profile.validate()?;
+
+        Ok(profile)
+    }
+}
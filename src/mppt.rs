@@ -0,0 +1,309 @@
+//! Solar MPPT
+//!
+//! Decodes the telemetry broadcast by Elmar (Prohelion) solar maximum power
+//! point trackers, the MPPT most commonly paired with Prohelion drive
+//! systems. Each MPPT broadcasts its measurements as little endian floats
+//! in a window of identifiers starting at its base identifier, so several
+//! can share a bus with different base identifiers.
+
+use bitflags::bitflags;
+use bxcan::{Frame, Id, StandardId};
+
+// id offsets for broadcast messages
+const ID_BROAD_INPUT: u16 = 0x00;
+const ID_BROAD_OUTPUT: u16 = 0x01;
+const ID_BROAD_TEMPERATURE: u16 = 0x02;
+const ID_BROAD_AUX_SUPPLY: u16 = 0x03;
+const ID_BROAD_LIMITS: u16 = 0x04;
+const ID_BROAD_STATUS: u16 = 0x05;
+const ID_BROAD_POWER_CONNECTOR: u16 = 0x06;
+
+/// Default base identifier value
+pub const ID_BASE_DEFAULT: u16 = 0x600;
+
+/// Number of identifiers used by a device, starting at its base identifier
+pub const ID_WINDOW: u16 = 0x10;
+
+bitflags! {
+    /// Error flags
+    pub struct ErrorFlags: u8 {
+        const LOW_ARRAY_POWER         = 1 << 0;
+        const MOSFET_OVERHEAT         = 1 << 1;
+        const BATTERY_LOW             = 1 << 2;
+        const BATTERY_FULL            = 1 << 3;
+        const SUPPLY_12V_UNDERVOLTAGE = 1 << 4;
+        const HARDWARE_OVERCURRENT    = 1 << 6;
+        const HARDWARE_OVERVOLTAGE    = 1 << 7;
+    }
+}
+
+bitflags! {
+    /// Limit flags
+    pub struct LimitFlags: u8 {
+        const INPUT_CURRENT_MIN  = 1 << 0;
+        const INPUT_CURRENT_MAX  = 1 << 1;
+        const OUTPUT_VOLTAGE_MAX = 1 << 2;
+        const MOSFET_TEMPERATURE = 1 << 3;
+        const DUTY_CYCLE_MIN     = 1 << 4;
+        const DUTY_CYCLE_MAX     = 1 << 5;
+        const LOCAL_MPPT         = 1 << 6;
+        const GLOBAL_MPPT        = 1 << 7;
+    }
+}
+
+/// Status
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Status {
+    /// Array input voltage in volts
+    pub input_voltage: Option<f32>,
+    /// Array input current in amps
+    pub input_current: Option<f32>,
+    /// Output voltage in volts
+    pub output_voltage: Option<f32>,
+    /// Output current in amps
+    pub output_current: Option<f32>,
+    /// MOSFET temperature in degrees celcius
+    pub mosfet_temperature: Option<f32>,
+    /// Controller temperature in degrees celcius
+    pub controller_temperature: Option<f32>,
+    /// 12 V auxiliary supply voltage in volts
+    pub supply_12v: Option<f32>,
+    /// 3 V auxiliary supply voltage in volts
+    pub supply_3v: Option<f32>,
+    /// Maximum output voltage in volts
+    pub max_output_voltage: Option<f32>,
+    /// Maximum input current in amps
+    pub max_input_current: Option<f32>,
+    /// CAN receive error count
+    pub can_rx_error_count: Option<u8>,
+    /// CAN transmit error count
+    pub can_tx_error_count: Option<u8>,
+    /// Error flags
+    pub error_flags: Option<ErrorFlags>,
+    /// Limit flags
+    pub limit_flags: Option<LimitFlags>,
+    /// Tracker is enabled
+    pub enabled: Option<bool>,
+    /// Battery voltage at the power connector in volts
+    pub battery_voltage: Option<f32>,
+    /// Power connector temperature in degrees celcius
+    pub power_connector_temperature: Option<f32>,
+}
+
+impl Status {
+    /// Array input power in watts.
+    pub fn input_power(&self) -> Option<f32> {
+        Some(self.input_voltage? * self.input_current?)
+    }
+
+    /// Output power in watts.
+    pub fn output_power(&self) -> Option<f32> {
+        Some(self.output_voltage? * self.output_current?)
+    }
+
+    /// Conversion efficiency as a fraction, unknown without input power.
+    pub fn efficiency(&self) -> Option<f32> {
+        let input = self.input_power()?;
+        if input <= 0.0 {
+            return None;
+        }
+
+        Some(self.output_power()? / input)
+    }
+
+    /// Forget every received value.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Apply a decoded message.
+    pub fn apply(&mut self, message: MpptMessage) {
+        match message {
+            MpptMessage::Input { voltage, current } => {
+                self.input_voltage = Some(voltage);
+                self.input_current = Some(current);
+            }
+            MpptMessage::Output { voltage, current } => {
+                self.output_voltage = Some(voltage);
+                self.output_current = Some(current);
+            }
+            MpptMessage::Temperature { mosfet, controller } => {
+                self.mosfet_temperature = Some(mosfet);
+                self.controller_temperature = Some(controller);
+            }
+            MpptMessage::AuxSupply {
+                supply_12v,
+                supply_3v,
+            } => {
+                self.supply_12v = Some(supply_12v);
+                self.supply_3v = Some(supply_3v);
+            }
+            MpptMessage::Limits {
+                max_output_voltage,
+                max_input_current,
+            } => {
+                self.max_output_voltage = Some(max_output_voltage);
+                self.max_input_current = Some(max_input_current);
+            }
+            MpptMessage::Status {
+                can_rx_error_count,
+                can_tx_error_count,
+                error_flags,
+                limit_flags,
+                enabled,
+            } => {
+                self.can_rx_error_count = Some(can_rx_error_count);
+                self.can_tx_error_count = Some(can_tx_error_count);
+                self.error_flags = Some(error_flags);
+                self.limit_flags = Some(limit_flags);
+                self.enabled = Some(enabled);
+            }
+            MpptMessage::PowerConnector {
+                battery_voltage,
+                temperature,
+            } => {
+                self.battery_voltage = Some(battery_voltage);
+                self.power_connector_temperature = Some(temperature);
+            }
+        }
+    }
+}
+
+/// Broadcast messages sent by an MPPT
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MpptMessage {
+    /// Array input voltage and current
+    Input { voltage: f32, current: f32 },
+    /// Output voltage and current
+    Output { voltage: f32, current: f32 },
+    /// MOSFET and controller temperatures
+    Temperature { mosfet: f32, controller: f32 },
+    /// Auxiliary supply voltages
+    AuxSupply { supply_12v: f32, supply_3v: f32 },
+    /// Configured limits
+    Limits {
+        max_output_voltage: f32,
+        max_input_current: f32,
+    },
+    /// Error counts, flags and mode
+    Status {
+        can_rx_error_count: u8,
+        can_tx_error_count: u8,
+        error_flags: ErrorFlags,
+        limit_flags: LimitFlags,
+        enabled: bool,
+    },
+    /// Battery side of the power connector
+    PowerConnector {
+        battery_voltage: f32,
+        temperature: f32,
+    },
+}
+
+fn f32_at(data: &[u8], offset: usize) -> f32 {
+    f32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+/// Decode an MPPT broadcast frame payload.
+///
+/// `offset` is the frame identifier relative to the device base identifier.
+/// Returns `None` for identifiers that are not decoded MPPT broadcasts.
+pub fn decode_mppt(offset: u16, data: &[u8]) -> Result<Option<MpptMessage>, &'static str> {
+    if offset > ID_BROAD_POWER_CONNECTOR {
+        return Ok(None);
+    }
+
+    if data.len() < 8 {
+        return Err("broadcast frame too short");
+    }
+
+    let message = match offset {
+        ID_BROAD_INPUT => MpptMessage::Input {
+            voltage: f32_at(data, 0),
+            current: f32_at(data, 4),
+        },
+        ID_BROAD_OUTPUT => MpptMessage::Output {
+            voltage: f32_at(data, 0),
+            current: f32_at(data, 4),
+        },
+        ID_BROAD_TEMPERATURE => MpptMessage::Temperature {
+            mosfet: f32_at(data, 0),
+            controller: f32_at(data, 4),
+        },
+        ID_BROAD_AUX_SUPPLY => MpptMessage::AuxSupply {
+            supply_12v: f32_at(data, 0),
+            supply_3v: f32_at(data, 4),
+        },
+        ID_BROAD_LIMITS => MpptMessage::Limits {
+            max_output_voltage: f32_at(data, 0),
+            max_input_current: f32_at(data, 4),
+        },
+        ID_BROAD_STATUS => MpptMessage::Status {
+            can_rx_error_count: data[0],
+            can_tx_error_count: data[1],
+            // data[2] is the transmit overflow count
+            error_flags: ErrorFlags::from_bits_truncate(data[3]),
+            limit_flags: LimitFlags::from_bits_truncate(data[4]),
+            enabled: data[5] != 0,
+        },
+        ID_BROAD_POWER_CONNECTOR => MpptMessage::PowerConnector {
+            battery_voltage: f32_at(data, 0),
+            temperature: f32_at(data, 4),
+        },
+        _ => return Ok(None),
+    };
+
+    Ok(Some(message))
+}
+
+/// Solar maximum power point tracker
+#[derive(Debug, Clone, Copy)]
+pub struct Mppt {
+    base_id: u16,
+
+    status: Status,
+}
+
+impl Mppt {
+    /// Create a new MPPT instance.
+    ///
+    /// Fails if the device's identifier window starting at `base_id` does
+    /// not fit within the standard identifier range.
+    pub fn new(base_id: u16) -> Result<Self, &'static str> {
+        if StandardId::new(base_id.saturating_add(ID_WINDOW - 1)).is_none() {
+            return Err("identifier window exceeds standard identifier range");
+        }
+
+        Ok(Self {
+            base_id,
+            status: Status::default(),
+        })
+    }
+
+    /// Get the current status state of the device
+    pub fn status(&self) -> Status {
+        self.status
+    }
+
+    /// Decode a broadcast frame and apply it to the device status.
+    pub fn receive(&mut self, frame: Frame) -> Result<(), &'static str> {
+        let (Id::Standard(id), Some(data)) = (frame.id(), frame.data()) else {
+            return Ok(());
+        };
+
+        // ignore frames belonging to other devices
+        let offset = id.as_raw().wrapping_sub(self.base_id);
+        if offset >= ID_WINDOW {
+            return Ok(());
+        }
+
+        let message = decode_mppt(offset, data)
+            .inspect_err(|error| log_warn!("MPPT frame {:#05x}: {}", id.as_raw(), error))?;
+
+        if let Some(message) = message {
+            self.status.apply(message);
+        }
+
+        Ok(())
+    }
+}
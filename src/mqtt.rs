@@ -0,0 +1,53 @@
+//! MQTT telemetry encoder
+//!
+//! Maps each decoded signal to an MQTT topic under a configurable prefix with
+//! the value as a plain decimal payload, for gateways publishing telemetry
+//! straight to a broker, for example `car/wavesculptor/bus_voltage` → `"101.5"`.
+//!
+//! Enabled with the `std` feature.
+
+use std::format;
+use std::string::{String, ToString};
+use std::vec::Vec;
+
+use crate::thresholds::{Measurements, Signal};
+
+/// MQTT topic and payload encoder
+#[derive(Debug, Clone)]
+pub struct MqttEncoder {
+    prefix: String,
+}
+
+impl MqttEncoder {
+    /// Create a new encoder publishing under `prefix`.
+    ///
+    /// A trailing `/` on the prefix is ignored.
+    pub fn new(prefix: &str) -> Self {
+        Self {
+            prefix: prefix.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Topic of a signal published by `device`.
+    pub fn topic(&self, device: &str, signal: Signal) -> String {
+        format!("{}/{}/{}", self.prefix, device, signal.name())
+    }
+
+    /// Payload of a signal value.
+    pub fn payload(&self, value: f32) -> String {
+        value.to_string()
+    }
+
+    /// Topics and payloads of every signal known by `source`, published by
+    /// `device`.
+    pub fn encode(&self, device: &str, source: &impl Measurements) -> Vec<(String, String)> {
+        Signal::ALL
+            .iter()
+            .filter_map(|signal| {
+                let value = source.value(*signal)?;
+
+                Some((self.topic(device, *signal), self.payload(value)))
+            })
+            .collect()
+    }
+}
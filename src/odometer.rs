@@ -0,0 +1,72 @@
+//! Odometer accumulation
+//!
+//! The WaveSculptor odometer restarts from zero whenever the controller
+//! resets. This accumulator detects resets and keeps a monotonically
+//! increasing total that can be persisted and restored across power cycles.
+
+use crate::wavesculptor::Status;
+
+/// Odometer accumulator
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Odometer {
+    /// Distance accumulated before the controller's current session in
+    /// millimetres
+    base_mm: u64,
+    /// Distance reported during the controller's current session in
+    /// millimetres
+    session_mm: u64,
+}
+
+impl Odometer {
+    /// Create a new odometer starting from zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an odometer continuing from a previously persisted total.
+    ///
+    /// The controller is assumed to have restarted since the total was saved.
+    pub fn with_total_mm(total_mm: u64) -> Self {
+        Self {
+            base_mm: total_mm,
+            session_mm: 0,
+        }
+    }
+
+    /// Total distance in millimetres.
+    pub fn total_mm(&self) -> u64 {
+        self.base_mm + self.session_mm
+    }
+
+    /// Total distance in meters.
+    pub fn total_m(&self) -> f64 {
+        self.total_mm() as f64 / 1000.0
+    }
+
+    /// Update from the latest WaveSculptor status.
+    pub fn update(&mut self, status: &Status) -> u64 {
+        if let Some(odometer) = status.odometer {
+            self.update_m(odometer);
+        }
+
+        self.total_mm()
+    }
+
+    /// Update from an odometer reading in meters.
+    ///
+    /// A reading lower than the previous one is treated as a controller
+    /// reset. Non-finite and negative readings are ignored.
+    pub fn update_m(&mut self, odometer: f32) -> u64 {
+        if odometer.is_finite() && odometer >= 0.0 {
+            let reading_mm = (odometer as f64 * 1000.0) as u64;
+
+            if reading_mm < self.session_mm {
+                self.base_mm += self.session_mm;
+            }
+
+            self.session_mm = reading_mm;
+        }
+
+        self.total_mm()
+    }
+}
@@ -0,0 +1,524 @@
+//! Battery pack limits
+//!
+//! Safe operating limits of a battery pack, shared by the BMS analysis
+//! helpers so each project configures its thresholds in one place, and the
+//! monitors checking the pack against them.
+//!
+//! Pack current is positive while discharging.
+
+use bitflags::bitflags;
+
+use crate::bms::{
+    Cell, CellWithTemperature, CellWithVoltage, Status, CELLS_PER_CMU, CMU_CAPACITY_DEFAULT,
+};
+use crate::thresholds::{Level, Limits, Signal, Threshold};
+
+bitflags! {
+    /// Pack limits exceeded
+    pub struct PackAlarms: u8 {
+        const CELL_UNDER_VOLTAGE = 1 << 0;
+        const CELL_OVER_VOLTAGE = 1 << 1;
+        const CELL_OVER_TEMPERATURE = 1 << 2;
+        const OVER_CHARGE_CURRENT = 1 << 3;
+        const OVER_DISCHARGE_CURRENT = 1 << 4;
+        const CELL_SENSOR_FAULT = 1 << 5;
+    }
+}
+
+bitflags! {
+    /// BMU summary values disagreeing with the CMU readings
+    pub struct ExtremesMismatch: u8 {
+        const MINIMUM_VOLTAGE = 1 << 0;
+        const MAXIMUM_VOLTAGE = 1 << 1;
+        const MINIMUM_TEMPERATURE = 1 << 2;
+        const MAXIMUM_TEMPERATURE = 1 << 3;
+    }
+}
+
+/// Pack safe operating limits
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PackLimits {
+    /// Minimum cell voltage in millivolts
+    pub min_cell_voltage: u16,
+    /// Maximum cell voltage in millivolts
+    pub max_cell_voltage: u16,
+    /// Maximum cell temperature in 1/10th degrees celcius
+    pub max_cell_temperature: u16,
+    /// Maximum charge current in amps
+    pub max_charge_current: f32,
+    /// Maximum discharge current in amps
+    pub max_discharge_current: f32,
+}
+
+impl Default for PackLimits {
+    fn default() -> Self {
+        Self {
+            min_cell_voltage: 2500,
+            max_cell_voltage: 4200,
+            max_cell_temperature: 600,
+            max_charge_current: 50.0,
+            max_discharge_current: 100.0,
+        }
+    }
+}
+
+impl PackLimits {
+    /// Check a cell voltage in millivolts.
+    pub fn check_cell_voltage(&self, voltage: u16) -> PackAlarms {
+        if voltage < self.min_cell_voltage {
+            PackAlarms::CELL_UNDER_VOLTAGE
+        } else if voltage > self.max_cell_voltage {
+            PackAlarms::CELL_OVER_VOLTAGE
+        } else {
+            PackAlarms::empty()
+        }
+    }
+
+    /// Check a cell temperature in 1/10th degrees celcius.
+    pub fn check_cell_temperature(&self, temperature: u16) -> PackAlarms {
+        if temperature > self.max_cell_temperature {
+            PackAlarms::CELL_OVER_TEMPERATURE
+        } else {
+            PackAlarms::empty()
+        }
+    }
+
+    /// Check a pack current in amps, positive while discharging.
+    pub fn check_current(&self, current: f32) -> PackAlarms {
+        if current > self.max_discharge_current {
+            PackAlarms::OVER_DISCHARGE_CURRENT
+        } else if -current > self.max_charge_current {
+            PackAlarms::OVER_CHARGE_CURRENT
+        } else {
+            PackAlarms::empty()
+        }
+    }
+
+    /// Check the BMU's minimum and maximum cells and pack current.
+    ///
+    /// Unknown values are not checked.
+    pub fn check<const CMUS: usize>(&self, status: &Status<CMUS>) -> PackAlarms {
+        let mut alarms = PackAlarms::empty();
+
+        if let Some(cell) = status.minimum_voltage_cell {
+            alarms |= self.check_cell_voltage(cell.voltage);
+        }
+
+        if let Some(cell) = status.maximum_voltage_cell {
+            alarms |= self.check_cell_voltage(cell.voltage);
+        }
+
+        if let Some(cell) = status.maximum_temperature_cell {
+            alarms |= self.check_cell_temperature(cell.temperature);
+        }
+
+        if let Some(current) = status.pack_current_ma {
+            alarms |= self.check_current(current as f32 / 1000.0);
+        }
+
+        alarms
+    }
+
+    /// Signal thresholds faulting at these limits and warning within the
+    /// given margins of them, for use in a [`Thresholds`] table.
+    ///
+    /// [`Thresholds`]: crate::thresholds::Thresholds
+    pub fn thresholds(&self, voltage_margin: u16, temperature_margin: u16) -> [Threshold; 3] {
+        let min_voltage = self.min_cell_voltage as f32;
+        let max_voltage = self.max_cell_voltage as f32;
+        let max_temperature = self.max_cell_temperature as f32;
+
+        [
+            Threshold::new(
+                Signal::MinimumCellVoltage,
+                Limits::below(min_voltage + voltage_margin as f32, min_voltage),
+            ),
+            Threshold::new(
+                Signal::MaximumCellVoltage,
+                Limits::above(max_voltage - voltage_margin as f32, max_voltage),
+            ),
+            Threshold::new(
+                Signal::MaximumCellTemperature,
+                Limits::above(max_temperature - temperature_margin as f32, max_temperature),
+            ),
+        ]
+    }
+}
+
+/// Cell exceeding a pack limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellAlert {
+    /// Cell location
+    pub cell: Cell,
+    /// Cell voltage in millivolts
+    pub voltage: i16,
+    /// Limit exceeded
+    pub alarm: PackAlarms,
+}
+
+/// Cell voltage monitor configuration
+#[derive(Debug, Clone, Copy)]
+pub struct CellVoltageConfig {
+    /// Pack limits
+    pub limits: PackLimits,
+    /// Number of cells connected to each CMU, counted from the first
+    pub cells_per_cmu: u8,
+}
+
+impl Default for CellVoltageConfig {
+    fn default() -> Self {
+        Self {
+            limits: PackLimits::default(),
+            cells_per_cmu: CELLS_PER_CMU as u8,
+        }
+    }
+}
+
+/// Per-cell under and over voltage monitor
+///
+/// Checks every cell voltage reported by the CMUs, rather than only the
+/// BMU's minimum and maximum, so alerts identify each offending cell.
+#[derive(Debug, Clone, Copy)]
+pub struct CellVoltageMonitor<const CMUS: usize = CMU_CAPACITY_DEFAULT> {
+    config: CellVoltageConfig,
+    alarms: [[PackAlarms; CELLS_PER_CMU]; CMUS],
+    voltages: [[i16; CELLS_PER_CMU]; CMUS],
+}
+
+impl<const CMUS: usize> CellVoltageMonitor<CMUS> {
+    /// Create a new monitor with no alarms raised.
+    pub fn new(config: CellVoltageConfig) -> Self {
+        Self {
+            config,
+            alarms: [[PackAlarms::empty(); CELLS_PER_CMU]; CMUS],
+            voltages: [[0; CELLS_PER_CMU]; CMUS],
+        }
+    }
+
+    /// Alarms raised across all cells.
+    pub fn alarms(&self) -> PackAlarms {
+        self.alarms
+            .iter()
+            .flatten()
+            .fold(PackAlarms::empty(), |alarms, cell| alarms | *cell)
+    }
+
+    /// Cells currently in alarm.
+    pub fn alerts(&self) -> impl Iterator<Item = CellAlert> + '_ {
+        (0..CMUS)
+            .flat_map(|cmu| (0..CELLS_PER_CMU).map(move |number| (cmu, number)))
+            .filter(|(cmu, number)| !self.alarms[*cmu][*number].is_empty())
+            .map(|(cmu, number)| CellAlert {
+                cell: Cell {
+                    cmu: cmu as u8,
+                    number: number as u8,
+                },
+                voltage: self.voltages[cmu][number],
+                alarm: self.alarms[cmu][number],
+            })
+    }
+
+    /// Update from the latest BMU status and return the alarms raised
+    /// across all cells.
+    ///
+    /// Cells of CMUs not reporting keep their previous state.
+    pub fn update(&mut self, status: &Status<CMUS>) -> PackAlarms {
+        let cells = (self.config.cells_per_cmu as usize).min(CELLS_PER_CMU);
+
+        for (cmu, cmu_status) in status.cmu_status.iter().enumerate() {
+            let Some(cmu_status) = cmu_status else {
+                continue;
+            };
+
+            for (number, voltage) in cmu_status.cell_voltage[..cells].iter().enumerate() {
+                if cmu_status.cell_voltage_received & 1 << number == 0 {
+                    continue;
+                }
+
+                // a negative reading can only come from a faulty sense lead
+                let alarm = match u16::try_from(*voltage) {
+                    Ok(voltage) => self.config.limits.check_cell_voltage(voltage),
+                    Err(_) => PackAlarms::CELL_SENSOR_FAULT,
+                };
+
+                if alarm != self.alarms[cmu][number] {
+                    if alarm.is_empty() {
+                        log_info!(
+                            "CMU {} cell {} back in range at {} mV",
+                            cmu,
+                            number,
+                            voltage
+                        );
+                    } else {
+                        log_warn!("CMU {} cell {} {:?} at {} mV", cmu, number, alarm, voltage);
+                    }
+                }
+
+                self.alarms[cmu][number] = alarm;
+                self.voltages[cmu][number] = *voltage;
+            }
+        }
+
+        self.alarms()
+    }
+}
+
+/// CMU temperature sensor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemperatureSensor {
+    /// Cell temperature sensor
+    Cell,
+    /// CMU circuit board temperature sensor
+    Pcb,
+}
+
+/// CMU temperature outside its normal range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TemperatureAlert {
+    /// CMU index
+    pub cmu: u8,
+    /// Sensor
+    pub sensor: TemperatureSensor,
+    /// Temperature in 1/10th degrees celcius
+    pub temperature: u16,
+    /// Level
+    pub level: Level,
+}
+
+/// Temperature monitor configuration
+///
+/// Temperatures are in 1/10th degrees celcius.
+#[derive(Debug, Clone, Copy)]
+pub struct TemperatureConfig {
+    /// Pack limits, faulting above the maximum cell temperature
+    pub limits: PackLimits,
+    /// Cell temperature margin below the maximum at which to warn
+    pub cell_warning_margin: u16,
+    /// PCB temperature above which to warn
+    pub pcb_warning: u16,
+    /// PCB temperature above which to fault
+    pub pcb_fault: u16,
+}
+
+impl Default for TemperatureConfig {
+    fn default() -> Self {
+        Self {
+            limits: PackLimits::default(),
+            cell_warning_margin: 50,
+            pcb_warning: 700,
+            pcb_fault: 850,
+        }
+    }
+}
+
+/// CMU cell and PCB temperature monitor
+#[derive(Debug, Clone, Copy)]
+pub struct TemperatureMonitor<const CMUS: usize = CMU_CAPACITY_DEFAULT> {
+    config: TemperatureConfig,
+    levels: [[Level; 2]; CMUS],
+    temperatures: [[u16; 2]; CMUS],
+}
+
+impl<const CMUS: usize> TemperatureMonitor<CMUS> {
+    /// Create a new monitor at the normal level.
+    pub fn new(config: TemperatureConfig) -> Self {
+        Self {
+            config,
+            levels: [[Level::Normal; 2]; CMUS],
+            temperatures: [[0; 2]; CMUS],
+        }
+    }
+
+    /// Highest level across all CMUs.
+    pub fn level(&self) -> Level {
+        self.levels
+            .iter()
+            .flatten()
+            .copied()
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Temperatures currently outside their normal range.
+    pub fn alerts(&self) -> impl Iterator<Item = TemperatureAlert> + '_ {
+        let sensors = [TemperatureSensor::Cell, TemperatureSensor::Pcb];
+
+        (0..CMUS)
+            .flat_map(move |cmu| sensors.into_iter().enumerate().map(move |s| (cmu, s)))
+            .filter(|(cmu, (index, _))| self.levels[*cmu][*index] != Level::Normal)
+            .map(|(cmu, (index, sensor))| TemperatureAlert {
+                cmu: cmu as u8,
+                sensor,
+                temperature: self.temperatures[cmu][index],
+                level: self.levels[cmu][index],
+            })
+    }
+
+    /// Update from the latest BMU status and return the highest level across
+    /// all CMUs.
+    ///
+    /// CMUs not reporting keep their previous level.
+    pub fn update(&mut self, status: &Status<CMUS>) -> Level {
+        let max_cell = self.config.limits.max_cell_temperature;
+        let cell_limits = Limits::above(
+            max_cell.saturating_sub(self.config.cell_warning_margin) as f32,
+            max_cell as f32,
+        );
+        let pcb_limits =
+            Limits::above(self.config.pcb_warning as f32, self.config.pcb_fault as f32);
+
+        for (cmu, cmu_status) in status.cmu_status.iter().enumerate() {
+            let Some(cmu_status) = cmu_status else {
+                continue;
+            };
+
+            let sensors = [
+                (cell_limits, cmu_status.cell_temperature),
+                (pcb_limits, cmu_status.pcb_temperature),
+            ];
+
+            for (index, (limits, temperature)) in sensors.into_iter().enumerate() {
+                let level = limits.level(temperature as f32);
+
+                if level != self.levels[cmu][index] {
+                    log_warn!(
+                        "CMU {} {} temperature {:?} at {} dC",
+                        cmu,
+                        if index == 0 { "cell" } else { "PCB" },
+                        level,
+                        temperature
+                    );
+                }
+
+                self.levels[cmu][index] = level;
+                self.temperatures[cmu][index] = temperature;
+            }
+        }
+
+        self.level()
+    }
+}
+
+/// Cell extremes cross-check configuration
+#[derive(Debug, Clone, Copy)]
+pub struct ExtremesCheckConfig {
+    /// Voltage difference tolerated in millivolts
+    pub voltage_tolerance: u16,
+    /// Temperature difference tolerated in 1/10th degrees celcius
+    pub temperature_tolerance: u16,
+    /// Time a disagreement must persist before it is reported
+    pub persistence_ms: u32,
+}
+
+impl Default for ExtremesCheckConfig {
+    fn default() -> Self {
+        Self {
+            voltage_tolerance: 20,
+            temperature_tolerance: 20,
+            persistence_ms: 2000,
+        }
+    }
+}
+
+/// Cross-check of the BMU's minimum and maximum cells against those computed
+/// from the CMU readings
+///
+/// The summary and CMU frames are broadcast at different times, so only
+/// disagreements persisting for a while are reported. A persistent
+/// disagreement suggests a BMU fault.
+#[derive(Debug, Clone, Copy)]
+pub struct ExtremesCheck {
+    config: ExtremesCheckConfig,
+    since_ms: [Option<u32>; 4],
+    mismatch: ExtremesMismatch,
+}
+
+impl ExtremesCheck {
+    /// Create a new check with no disagreement.
+    pub fn new(config: ExtremesCheckConfig) -> Self {
+        Self {
+            config,
+            since_ms: [None; 4],
+            mismatch: ExtremesMismatch::empty(),
+        }
+    }
+
+    /// Disagreements persisting longer than the configured time.
+    pub fn mismatch(&self) -> ExtremesMismatch {
+        self.mismatch
+    }
+
+    /// Update from the latest BMU status and return the persistent
+    /// disagreements.
+    ///
+    /// Values not known from both sources are not checked.
+    pub fn update<const CMUS: usize>(
+        &mut self,
+        now_ms: u32,
+        status: &Status<CMUS>,
+    ) -> ExtremesMismatch {
+        let local = status.cell_extremes();
+
+        let voltage = |bmu: Option<CellWithVoltage>, local: Option<CellWithVoltage>| {
+            Some(bmu?.voltage.abs_diff(local?.voltage) > self.config.voltage_tolerance)
+        };
+        let temperature = |bmu: Option<CellWithTemperature>, local: Option<CellWithTemperature>| {
+            Some(bmu?.temperature.abs_diff(local?.temperature) > self.config.temperature_tolerance)
+        };
+
+        let checks = [
+            (
+                ExtremesMismatch::MINIMUM_VOLTAGE,
+                voltage(
+                    status.minimum_voltage_cell,
+                    local.map(|local| local.minimum_voltage),
+                ),
+            ),
+            (
+                ExtremesMismatch::MAXIMUM_VOLTAGE,
+                voltage(
+                    status.maximum_voltage_cell,
+                    local.map(|local| local.maximum_voltage),
+                ),
+            ),
+            (
+                ExtremesMismatch::MINIMUM_TEMPERATURE,
+                temperature(
+                    status.minimum_temperature_cell,
+                    local.map(|local| local.minimum_temperature),
+                ),
+            ),
+            (
+                ExtremesMismatch::MAXIMUM_TEMPERATURE,
+                temperature(
+                    status.maximum_temperature_cell,
+                    local.map(|local| local.maximum_temperature),
+                ),
+            ),
+        ];
+
+        for ((flag, disagrees), since_ms) in checks.into_iter().zip(self.since_ms.iter_mut()) {
+            if disagrees != Some(true) {
+                *since_ms = None;
+
+                if self.mismatch.contains(flag) {
+                    log_info!("BMU {:?} agrees with CMUs again", flag);
+                    self.mismatch.remove(flag);
+                }
+
+                continue;
+            }
+
+            let since_ms = *since_ms.get_or_insert(now_ms);
+
+            if !self.mismatch.contains(flag)
+                && now_ms.wrapping_sub(since_ms) >= self.config.persistence_ms
+            {
+                log_warn!("BMU {:?} disagrees with CMUs", flag);
+                self.mismatch.insert(flag);
+            }
+        }
+
+        self.mismatch
+    }
+}
@@ -0,0 +1,153 @@
+//! Pedal box input processing
+//!
+//! Converts raw readings from a dual-channel accelerator pedal into a
+//! normalized pedal position. The two channels are cross-checked and any
+//! disagreement or out-of-range reading forces zero torque until the fault
+//! clears and the pedal has been released.
+
+use crate::driver_controls::DriveCommand;
+
+/// Calibration of a single pedal sensor channel
+#[derive(Debug, Clone, Copy)]
+pub struct ChannelCalibration {
+    /// Raw reading with the pedal released
+    pub released: u16,
+    /// Raw reading with the pedal fully pressed
+    pub pressed: u16,
+}
+
+impl ChannelCalibration {
+    /// Normalize a raw reading to a pedal position between 0 and 1.
+    ///
+    /// Returns `None` when the reading is further than `margin` counts outside
+    /// the calibrated range, indicating an open or shorted sensor.
+    pub fn normalize(&self, raw: u16, margin: u16) -> Option<f32> {
+        let (low, high) = if self.released <= self.pressed {
+            (self.released, self.pressed)
+        } else {
+            (self.pressed, self.released)
+        };
+
+        if raw < low.saturating_sub(margin) || raw > high.saturating_add(margin) || low == high {
+            return None;
+        }
+
+        let position =
+            (raw as f32 - self.released as f32) / (self.pressed as f32 - self.released as f32);
+
+        Some(position.clamp(0.0, 1.0))
+    }
+}
+
+/// Pedal box configuration
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// First sensor channel calibration
+    pub channel_a: ChannelCalibration,
+    /// Second sensor channel calibration
+    pub channel_b: ChannelCalibration,
+    /// Raw counts a reading may fall outside the calibrated range
+    pub range_margin: u16,
+    /// Maximum allowed difference between channel positions
+    pub plausibility_tolerance: f32,
+    /// How long the channels may disagree before faulting in milliseconds
+    pub plausibility_time_ms: u32,
+    /// Pedal travel ignored at the released end
+    pub deadband: f32,
+    /// Pedal position the driver must release below to clear a fault
+    pub reset_threshold: f32,
+}
+
+/// Pedal faults
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// A channel reading is outside its calibrated range
+    OutOfRange,
+    /// The channels disagree for longer than allowed
+    Implausible,
+}
+
+/// Dual-channel pedal box
+#[derive(Debug, Clone, Copy)]
+pub struct PedalBox {
+    config: Config,
+    disagree_since_ms: Option<u32>,
+    fault: Option<Fault>,
+    position: f32,
+}
+
+impl PedalBox {
+    /// Create a new pedal box.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            disagree_since_ms: None,
+            fault: None,
+            position: 0.0,
+        }
+    }
+
+    /// Active fault, if any.
+    pub fn fault(&self) -> Option<Fault> {
+        self.fault
+    }
+
+    /// Most recent pedal position between 0 and 1.
+    pub fn position(&self) -> f32 {
+        self.position
+    }
+
+    /// Process a pair of raw readings and return the pedal position.
+    ///
+    /// The position is zero while a fault is active.
+    pub fn update(&mut self, now_ms: u32, raw_a: u16, raw_b: u16) -> f32 {
+        let margin = self.config.range_margin;
+        let a = self.config.channel_a.normalize(raw_a, margin);
+        let b = self.config.channel_b.normalize(raw_b, margin);
+
+        let position = match (a, b) {
+            (Some(a), Some(b)) => {
+                if (a - b).abs() > self.config.plausibility_tolerance {
+                    let since = *self.disagree_since_ms.get_or_insert(now_ms);
+
+                    if now_ms.wrapping_sub(since) >= self.config.plausibility_time_ms {
+                        self.fault = Some(Fault::Implausible);
+                    }
+                } else {
+                    self.disagree_since_ms = None;
+                }
+
+                a.min(b)
+            }
+            _ => {
+                self.fault = Some(Fault::OutOfRange);
+                0.0
+            }
+        };
+
+        // faults only clear once the sensors agree and the pedal is released
+        if self.fault.is_some()
+            && self.disagree_since_ms.is_none()
+            && a.is_some()
+            && b.is_some()
+            && position < self.config.reset_threshold
+        {
+            self.fault = None;
+        }
+
+        self.position = if self.fault.is_some() || position <= self.config.deadband {
+            0.0
+        } else {
+            (position - self.config.deadband) / (1.0 - self.config.deadband)
+        };
+
+        self.position
+    }
+
+    /// Process a pair of raw readings into a torque mode drive command.
+    pub fn command(&mut self, now_ms: u32, raw_a: u16, raw_b: u16) -> DriveCommand {
+        let position = self.update(now_ms, raw_a, raw_b);
+
+        DriveCommand::torque(position * 100.0)
+    }
+}
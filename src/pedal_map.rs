@@ -0,0 +1,96 @@
+//! One-pedal driving map
+//!
+//! Converts a normalized accelerator pedal position into drive or
+//! regenerative braking commands. The pedal travel is split into zones:
+//!
+//! ```text
+//! 0.0          regen_end    drive_start            1.0
+//!  |-- regen --|-- deadband --|------- drive -------|
+//! ```
+//!
+//! Regen is strongest with the pedal released and drive current is highest
+//! at full travel. Below the creep speed the regen zone is replaced by a
+//! small forward creep current so the vehicle can be manoeuvred at low speed.
+
+use crate::driver_controls::DriveCommand;
+
+/// Pedal map configuration
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Pedal position where the regen zone ends
+    pub regen_end: f32,
+    /// Pedal position where the drive zone starts
+    pub drive_start: f32,
+    /// Regen current with the pedal released in percent
+    pub max_regen_percent: f32,
+    /// Drive current at full pedal travel in percent
+    pub max_drive_percent: f32,
+    /// Creep current in percent, zero to disable creep
+    pub creep_percent: f32,
+    /// Speed below which creep replaces regen in m/s
+    pub creep_speed: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            regen_end: 0.15,
+            drive_start: 0.25,
+            max_regen_percent: 30.0,
+            max_drive_percent: 100.0,
+            creep_percent: 0.0,
+            creep_speed: 1.5,
+        }
+    }
+}
+
+/// One-pedal driving map
+#[derive(Debug, Clone, Copy)]
+pub struct PedalMap {
+    config: Config,
+}
+
+impl PedalMap {
+    /// Create a new pedal map.
+    pub fn new(config: Config) -> Result<Self, &'static str> {
+        if !(0.0 <= config.regen_end
+            && config.regen_end <= config.drive_start
+            && config.drive_start < 1.0)
+        {
+            return Err("pedal zones must be ordered within 0 to 1");
+        }
+
+        Ok(Self { config })
+    }
+
+    /// Map a pedal position between 0 and 1 to a drive command.
+    ///
+    /// `speed` is the vehicle speed in m/s, when unknown creep is not applied.
+    pub fn command(&self, pedal: f32, speed: Option<f32>) -> DriveCommand {
+        let pedal = if pedal.is_nan() {
+            0.0
+        } else {
+            pedal.clamp(0.0, 1.0)
+        };
+        let config = &self.config;
+
+        if pedal >= config.drive_start {
+            let fraction = (pedal - config.drive_start) / (1.0 - config.drive_start);
+
+            return DriveCommand::torque(fraction * config.max_drive_percent);
+        }
+
+        let creeping = config.creep_percent > 0.0
+            && speed.is_some_and(|speed| speed.abs() < config.creep_speed);
+
+        if creeping {
+            DriveCommand::torque(config.creep_percent)
+        } else if pedal < config.regen_end {
+            let fraction = (config.regen_end - pedal) / config.regen_end;
+
+            DriveCommand::regen(fraction * config.max_regen_percent)
+        } else {
+            DriveCommand::default()
+        }
+    }
+}
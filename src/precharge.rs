@@ -0,0 +1,358 @@
+//! Precharge sequencer
+//!
+//! Sequences the pack contactors through the same states as the BMU's
+//! precharge controller, for vehicles replacing the stock BMU logic:
+//!
+//! 1. `Idle`: all contactors open.
+//! 2. `EnablePack`: negative contactor closed.
+//! 3. `Measure`: pack voltage measured.
+//! 4. `Precharge`: precharge contactor closed until the bus voltage
+//!    approaches the pack voltage.
+//! 5. `Run`: positive contactor closed, precharge contactor opened.
+//!
+//! Timeouts and invalid measurements move the sequencer into `Error` with all
+//! contactors open.
+//!
+//! [`Monitor`] watches the same states when they are sequenced by a BMU.
+
+use crate::bms::{PrechargeState, Status};
+
+/// Default precharge time above which [`Monitor`] raises its alarm
+const PRECHARGE_LIMIT_MS_DEFAULT: u32 = 5000;
+
+/// Sequencer configuration
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Time to wait after closing the negative contactor in milliseconds
+    pub enable_pack_time_ms: u32,
+    /// Time to wait for a pack voltage measurement in milliseconds
+    pub measure_time_ms: u32,
+    /// Maximum time allowed to precharge in milliseconds
+    pub precharge_timeout_ms: u32,
+    /// Fraction of the pack voltage the bus must reach to finish precharging
+    pub voltage_match: f32,
+    /// Minimum plausible pack voltage in volts
+    pub min_pack_voltage: f32,
+    /// Time the precharge and positive contactors are closed together in
+    /// milliseconds
+    pub overlap_time_ms: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enable_pack_time_ms: 100,
+            measure_time_ms: 500,
+            precharge_timeout_ms: 5000,
+            voltage_match: 0.95,
+            min_pack_voltage: 10.0,
+            overlap_time_ms: 100,
+        }
+    }
+}
+
+/// Contactor output states
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Outputs {
+    /// Negative contactor closed
+    pub negative: bool,
+    /// Precharge contactor closed
+    pub precharge: bool,
+    /// Positive contactor closed
+    pub positive: bool,
+}
+
+/// Precharge sequencer
+#[derive(Debug, Clone, Copy)]
+pub struct Sequencer {
+    config: Config,
+    state: PrechargeState,
+    entered_ms: u32,
+    pack_voltage: Option<f32>,
+}
+
+impl Sequencer {
+    /// Create a new, idle, precharge sequencer.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            state: PrechargeState::Idle,
+            entered_ms: 0,
+            pack_voltage: None,
+        }
+    }
+
+    /// Current sequencer state.
+    pub fn state(&self) -> PrechargeState {
+        self.state
+    }
+
+    /// Begin the precharge sequence.
+    pub fn start(&mut self, now_ms: u32) -> Result<(), &'static str> {
+        if self.state != PrechargeState::Idle {
+            return Err("precharge sequence can only start from idle");
+        }
+
+        self.transition(PrechargeState::EnablePack, now_ms);
+
+        Ok(())
+    }
+
+    /// Open all contactors and return to idle.
+    pub fn stop(&mut self, now_ms: u32) {
+        self.transition(PrechargeState::Idle, now_ms);
+    }
+
+    /// Advance the sequence.
+    ///
+    /// `pack_voltage` is measured on the battery side of the contactors and
+    /// `bus_voltage` on the load side, both in volts. Returns the contactor
+    /// outputs to apply.
+    pub fn update(
+        &mut self,
+        now_ms: u32,
+        pack_voltage: Option<f32>,
+        bus_voltage: Option<f32>,
+    ) -> Outputs {
+        let elapsed = now_ms.wrapping_sub(self.entered_ms);
+
+        match self.state {
+            PrechargeState::EnablePack => {
+                if elapsed >= self.config.enable_pack_time_ms {
+                    self.transition(PrechargeState::Measure, now_ms);
+                }
+            }
+            PrechargeState::Measure => match pack_voltage {
+                Some(voltage) if voltage >= self.config.min_pack_voltage => {
+                    self.pack_voltage = Some(voltage);
+                    self.transition(PrechargeState::Precharge, now_ms);
+                }
+                _ if elapsed >= self.config.measure_time_ms => {
+                    self.transition(PrechargeState::Error, now_ms);
+                }
+                _ => {}
+            },
+            PrechargeState::Precharge => {
+                let target = self.pack_voltage.unwrap_or(f32::INFINITY) * self.config.voltage_match;
+
+                if bus_voltage.is_some_and(|voltage| voltage >= target) {
+                    self.transition(PrechargeState::Run, now_ms);
+                } else if elapsed >= self.config.precharge_timeout_ms {
+                    self.transition(PrechargeState::Error, now_ms);
+                }
+            }
+            PrechargeState::Idle | PrechargeState::Run | PrechargeState::Error => {}
+        }
+
+        self.outputs(now_ms)
+    }
+
+    /// Contactor outputs for the current state.
+    pub fn outputs(&self, now_ms: u32) -> Outputs {
+        let elapsed = now_ms.wrapping_sub(self.entered_ms);
+
+        match self.state {
+            PrechargeState::Idle | PrechargeState::Error => Outputs::default(),
+            PrechargeState::EnablePack | PrechargeState::Measure => Outputs {
+                negative: true,
+                ..Default::default()
+            },
+            PrechargeState::Precharge => Outputs {
+                negative: true,
+                precharge: true,
+                positive: false,
+            },
+            PrechargeState::Run => Outputs {
+                negative: true,
+                precharge: elapsed < self.config.overlap_time_ms,
+                positive: true,
+            },
+        }
+    }
+
+    fn transition(&mut self, state: PrechargeState, now_ms: u32) {
+        if state == PrechargeState::Idle {
+            self.pack_voltage = None;
+        }
+
+        self.state = state;
+        self.entered_ms = now_ms;
+    }
+}
+
+/// Precharge state change reported by the BMU
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transition {
+    /// Previous state
+    pub from: PrechargeState,
+    /// New state
+    pub to: PrechargeState,
+    /// Time spent in the previous state in milliseconds
+    pub duration_ms: u32,
+    /// Transition is part of a normal sequence
+    pub legal: bool,
+}
+
+/// Precharge state monitor
+///
+/// Watches the precharge state broadcast by a BMU, timing how long each
+/// state lasts and flagging transitions outside the normal sequence, see
+/// [`PrechargeState::is_legal_transition`].
+///
+/// An alarm is raised when precharging takes longer than the limit, which
+/// suggests a shorted load or failed precharge resistor. It stays raised
+/// until the next precharge starts or it is cleared.
+#[derive(Debug, Clone, Copy)]
+pub struct Monitor {
+    precharge_limit_ms: u32,
+
+    state: Option<PrechargeState>,
+    entered_ms: u32,
+    durations_ms: [Option<u32>; 6],
+    illegal_count: u32,
+    timer_ms: u32,
+    overrun: bool,
+}
+
+impl Default for Monitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Monitor {
+    /// Create a new monitor that hasn't seen a state.
+    pub fn new() -> Self {
+        Self {
+            precharge_limit_ms: PRECHARGE_LIMIT_MS_DEFAULT,
+            state: None,
+            entered_ms: 0,
+            durations_ms: [None; 6],
+            illegal_count: 0,
+            timer_ms: 0,
+            overrun: false,
+        }
+    }
+
+    /// Set the precharge time above which the alarm is raised.
+    pub fn with_precharge_limit_ms(mut self, limit_ms: u32) -> Self {
+        self.precharge_limit_ms = limit_ms;
+        self
+    }
+
+    /// Latest state.
+    pub fn state(&self) -> Option<PrechargeState> {
+        self.state
+    }
+
+    /// Time spent in the latest state in milliseconds.
+    pub fn time_in_state(&self, now_ms: u32) -> Option<u32> {
+        self.state.map(|_| now_ms.wrapping_sub(self.entered_ms))
+    }
+
+    /// Time spent in `state` when it was last left in milliseconds.
+    pub fn last_duration_ms(&self, state: PrechargeState) -> Option<u32> {
+        self.durations_ms[state as usize]
+    }
+
+    /// Number of transitions outside the normal sequence.
+    pub fn illegal_count(&self) -> u32 {
+        self.illegal_count
+    }
+
+    /// Time spent precharging in milliseconds, `None` unless precharging.
+    ///
+    /// The longer of the time since the state was first seen and the BMU's
+    /// precharge timer, which wraps after 2.55 s.
+    pub fn precharge_elapsed_ms(&self, now_ms: u32) -> Option<u32> {
+        if self.state != Some(PrechargeState::Precharge) {
+            return None;
+        }
+
+        Some(now_ms.wrapping_sub(self.entered_ms).max(self.timer_ms))
+    }
+
+    /// Has precharging taken longer than the limit?
+    pub fn precharge_overrun(&self) -> bool {
+        self.overrun
+    }
+
+    /// Clear the precharge overrun alarm.
+    pub fn clear_alarm(&mut self) {
+        self.overrun = false;
+    }
+
+    /// Update from the latest BMU status, returning the transition if the
+    /// state changed.
+    pub fn update<const CMUS: usize>(
+        &mut self,
+        now_ms: u32,
+        status: &Status<CMUS>,
+    ) -> Option<Transition> {
+        let transition = self.update_state(now_ms, status.precharge_state?);
+
+        if self.state == Some(PrechargeState::Precharge) {
+            if let Some(counter) = status.precharge_timer_counter {
+                self.timer_ms = counter as u32 * 10;
+                self.check_overrun(now_ms);
+            }
+        }
+
+        transition
+    }
+
+    /// Update from a precharge state, returning the transition if the state
+    /// changed.
+    pub fn update_state(&mut self, now_ms: u32, state: PrechargeState) -> Option<Transition> {
+        let transition = match self.state.replace(state) {
+            None => {
+                self.entered_ms = now_ms;
+                None
+            }
+            Some(from) if from != state => Some(self.transition(now_ms, from, state)),
+            Some(_) => None,
+        };
+
+        self.check_overrun(now_ms);
+
+        transition
+    }
+
+    fn transition(&mut self, now_ms: u32, from: PrechargeState, to: PrechargeState) -> Transition {
+        let transition = Transition {
+            from,
+            to,
+            duration_ms: now_ms.wrapping_sub(self.entered_ms),
+            legal: from.is_legal_transition(to),
+        };
+
+        if !transition.legal {
+            log_warn!("illegal precharge transition {} -> {}", from, to);
+            self.illegal_count = self.illegal_count.saturating_add(1);
+        }
+
+        if to == PrechargeState::Precharge {
+            self.timer_ms = 0;
+            self.overrun = false;
+        }
+
+        self.durations_ms[from as usize] = Some(transition.duration_ms);
+        self.entered_ms = now_ms;
+
+        transition
+    }
+
+    fn check_overrun(&mut self, now_ms: u32) {
+        let overrun = self
+            .precharge_elapsed_ms(now_ms)
+            .is_some_and(|elapsed| elapsed > self.precharge_limit_ms);
+
+        if overrun && !self.overrun {
+            log_warn!(
+                "precharge taking longer than {} ms",
+                self.precharge_limit_ms
+            );
+            self.overrun = true;
+        }
+    }
+}
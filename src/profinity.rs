@@ -0,0 +1,208 @@
+//! Profinity configuration import
+//!
+//! Reads the BMU settings of interest to the crate's monitors, such as cell
+//! voltage and temperature thresholds and the pack layout, from a
+//! configuration export so they come from the same source as the vendor
+//! tool.
+//!
+//! Profinity's export schema isn't published, so the parser reads the plain
+//! text `name = value` (or `name: value`) form. Setting names are matched
+//! loosely, ignoring case, spacing and punctuation. `[section]` headers,
+//! blank lines, `#` and `;` comments and unknown settings are skipped.
+//! Values may carry a unit, such as `4.2 V`, `4200 mV` or `60 °C`, and are
+//! otherwise taken in degrees celcius and amps. Voltages without a unit are
+//! taken in volts below 10 and in millivolts otherwise, as cell thresholds
+//! are neither below 10 mV nor above 10 V.
+//!
+//! Enabled with the `std` feature.
+
+use std::string::String;
+
+use crate::pack::PackLimits;
+
+/// BMU configuration
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct BmuConfig {
+    /// Number of CMUs in the pack
+    pub cmu_count: Option<u8>,
+    /// Number of cells monitored by each CMU
+    pub cells_per_cmu: Option<u8>,
+    /// Minimum cell voltage in millivolts
+    pub min_cell_voltage: Option<u16>,
+    /// Maximum cell voltage in millivolts
+    pub max_cell_voltage: Option<u16>,
+    /// Cell voltage above which balancing starts in millivolts
+    pub balance_threshold: Option<u16>,
+    /// Maximum cell temperature in 1/10th degrees celcius
+    pub max_cell_temperature: Option<u16>,
+    /// Maximum charge current in amps
+    pub max_charge_current: Option<f32>,
+    /// Maximum discharge current in amps
+    pub max_discharge_current: Option<f32>,
+}
+
+/// Recognised setting
+enum Setting {
+    CmuCount,
+    CellsPerCmu,
+    MinCellVoltage,
+    MaxCellVoltage,
+    BalanceThreshold,
+    MaxCellTemperature,
+    MaxChargeCurrent,
+    MaxDischargeCurrent,
+}
+
+/// Parse a configuration export.
+pub fn parse(text: &str) -> Result<BmuConfig, &'static str> {
+    let mut config = BmuConfig::default();
+
+    for line in text.lines() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with(['#', ';', '[']) {
+            continue;
+        }
+
+        let Some((name, value)) = line.split_once(['=', ':']) else {
+            continue;
+        };
+
+        let setting = match normalize(name).as_str() {
+            "cmucount" | "numberofcmus" | "cmus" => Setting::CmuCount,
+            "cellspercmu" | "cmucells" => Setting::CellsPerCmu,
+            "mincellvoltage" | "minimumcellvoltage" | "cellundervoltage" | "undervoltage" => {
+                Setting::MinCellVoltage
+            }
+            "maxcellvoltage" | "maximumcellvoltage" | "cellovervoltage" | "overvoltage" => {
+                Setting::MaxCellVoltage
+            }
+            "balancethreshold" | "balancevoltage" | "balancestartvoltage" => {
+                Setting::BalanceThreshold
+            }
+            "maxcelltemperature" | "maximumcelltemperature" | "overtemperature" => {
+                Setting::MaxCellTemperature
+            }
+            "maxchargecurrent" | "maximumchargecurrent" | "chargecurrentlimit" => {
+                Setting::MaxChargeCurrent
+            }
+            "maxdischargecurrent" | "maximumdischargecurrent" | "dischargecurrentlimit" => {
+                Setting::MaxDischargeCurrent
+            }
+            _ => continue,
+        };
+
+        let (number, unit) = split_unit(value)?;
+
+        match setting {
+            Setting::CmuCount => config.cmu_count = Some(integer(number)?),
+            Setting::CellsPerCmu => config.cells_per_cmu = Some(integer(number)?),
+            Setting::MinCellVoltage => config.min_cell_voltage = Some(millivolts(number, &unit)?),
+            Setting::MaxCellVoltage => config.max_cell_voltage = Some(millivolts(number, &unit)?),
+            Setting::BalanceThreshold => {
+                config.balance_threshold = Some(millivolts(number, &unit)?)
+            }
+            Setting::MaxCellTemperature => {
+                config.max_cell_temperature = Some(deci_celcius(number, &unit)?)
+            }
+            Setting::MaxChargeCurrent => config.max_charge_current = Some(amps(number, &unit)?),
+            Setting::MaxDischargeCurrent => {
+                config.max_discharge_current = Some(amps(number, &unit)?)
+            }
+        }
+    }
+
+    Ok(config)
+}
+
+/// Setting name in lower case without spacing or punctuation.
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Split a value into its number and lower case unit.
+fn split_unit(value: &str) -> Result<(f32, String), &'static str> {
+    let value = value.trim();
+    let end = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+'))
+        .unwrap_or(value.len());
+
+    let number = value[..end]
+        .parse()
+        .map_err(|_| "invalid number in BMU configuration")?;
+
+    let unit = value[end..]
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '°')
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+
+    Ok((number, unit))
+}
+
+fn integer(number: f32) -> Result<u8, &'static str> {
+    if number.fract() != 0.0 || !(0.0..=255.0).contains(&number) {
+        return Err("invalid count in BMU configuration");
+    }
+
+    Ok(number as u8)
+}
+
+fn millivolts(number: f32, unit: &str) -> Result<u16, &'static str> {
+    let millivolts = match unit {
+        "" if number.abs() < 10.0 => number * 1000.0,
+        "" | "mv" => number,
+        "v" => number * 1000.0,
+        _ => return Err("invalid voltage unit in BMU configuration"),
+    };
+
+    to_u16(millivolts)
+}
+
+fn deci_celcius(number: f32, unit: &str) -> Result<u16, &'static str> {
+    match unit {
+        "" | "c" | "degc" => to_u16(number * 10.0),
+        _ => Err("invalid temperature unit in BMU configuration"),
+    }
+}
+
+fn amps(number: f32, unit: &str) -> Result<f32, &'static str> {
+    match unit {
+        "" | "a" => Ok(number),
+        "ma" => Ok(number / 1000.0),
+        _ => Err("invalid current unit in BMU configuration"),
+    }
+}
+
+fn to_u16(value: f32) -> Result<u16, &'static str> {
+    let value = value.round();
+
+    if !(0.0..=u16::MAX as f32).contains(&value) {
+        return Err("value out of range in BMU configuration");
+    }
+
+    Ok(value as u16)
+}
+
+impl BmuConfig {
+    /// Pack limits from this configuration, taking unset limits from
+    /// `defaults`.
+    pub fn pack_limits(&self, defaults: PackLimits) -> PackLimits {
+        PackLimits {
+            min_cell_voltage: self.min_cell_voltage.unwrap_or(defaults.min_cell_voltage),
+            max_cell_voltage: self.max_cell_voltage.unwrap_or(defaults.max_cell_voltage),
+            max_cell_temperature: self
+                .max_cell_temperature
+                .unwrap_or(defaults.max_cell_temperature),
+            max_charge_current: self
+                .max_charge_current
+                .unwrap_or(defaults.max_charge_current),
+            max_discharge_current: self
+                .max_discharge_current
+                .unwrap_or(defaults.max_discharge_current),
+        }
+    }
+}
@@ -0,0 +1,88 @@
+//! Prometheus exposition encoder
+//!
+//! Renders the latest decoded signals as gauges in the Prometheus text
+//! exposition format, for telemetry servers scraped by Prometheus. Each
+//! device becomes a `device` label, for example:
+//!
+//! ```text
+//! # TYPE phln_bus_voltage gauge
+//! phln_bus_voltage{device="wavesculptor"} 101.5
+//! ```
+//!
+//! Enabled with the `std` feature.
+
+use core::fmt::Write;
+use std::string::String;
+
+use crate::thresholds::{Measurements, Signal};
+
+/// Render every signal known by `devices` as gauges named
+/// `{namespace}_{signal}`.
+///
+/// `devices` pairs a device label with its status, for example
+/// `[("wavesculptor", &ws_status), ("bmu", &bmu_status)]`. Signals unknown
+/// by every device are omitted.
+pub fn render(namespace: &str, devices: &[(&str, &dyn Measurements)]) -> String {
+    let mut out = String::new();
+
+    for signal in Signal::ALL {
+        let mut typed = false;
+
+        for (device, source) in devices {
+            let Some(value) = source.value(signal) else {
+                continue;
+            };
+
+            if !typed {
+                writeln!(out, "# TYPE {}_{} gauge", namespace, signal.name()).unwrap();
+                typed = true;
+            }
+
+            writeln!(
+                out,
+                "{}_{}{{device=\"{}\"}} {}",
+                namespace,
+                signal.name(),
+                escape(device),
+                format_value(value)
+            )
+            .unwrap();
+        }
+    }
+
+    out
+}
+
+/// Escape a label value.
+fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+/// Format a sample value, using the exposition format's spelling of
+/// non-finite values.
+fn format_value(value: f32) -> String {
+    let mut out = String::new();
+
+    if value.is_nan() {
+        out.push_str("NaN");
+    } else if value == f32::INFINITY {
+        out.push_str("+Inf");
+    } else if value == f32::NEG_INFINITY {
+        out.push_str("-Inf");
+    } else {
+        write!(out, "{}", value).unwrap();
+    }
+
+    out
+}
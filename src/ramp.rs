@@ -0,0 +1,99 @@
+//! Current slew-rate limiter
+//!
+//! Ramps the commanded current of drive commands so steps in pedal input
+//! become smooth torque ramps, reducing drivetrain shock and bus current
+//! spikes.
+
+use crate::driver_controls::DriveCommand;
+
+/// Ramp rates
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Maximum rate of current increase in percent per second
+    pub rise_rate: f32,
+    /// Maximum rate of current decrease in percent per second
+    pub fall_rate: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            rise_rate: 200.0,
+            fall_rate: 400.0,
+        }
+    }
+}
+
+/// Current slew-rate limiter
+#[derive(Debug, Clone, Copy)]
+pub struct CurrentRamp {
+    config: Config,
+    output: DriveCommand,
+    last_update_ms: Option<u32>,
+}
+
+impl CurrentRamp {
+    /// Create a new current ramp starting from zero current.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            output: DriveCommand::default(),
+            last_update_ms: None,
+        }
+    }
+
+    /// Most recent ramped command.
+    pub fn output(&self) -> DriveCommand {
+        self.output
+    }
+
+    /// Return to zero current immediately.
+    pub fn reset(&mut self) {
+        self.output = DriveCommand::default();
+        self.last_update_ms = None;
+    }
+
+    /// Ramp towards the requested command.
+    ///
+    /// When the requested velocity set point changes direction (for example
+    /// from driving to regenerative braking) the current is first ramped down
+    /// to zero using the previous velocity set point.
+    pub fn apply(&mut self, now_ms: u32, command: DriveCommand) -> DriveCommand {
+        let dt = match self.last_update_ms {
+            Some(last) => now_ms.wrapping_sub(last) as f32 / 1000.0,
+            None => 0.0,
+        };
+        self.last_update_ms = Some(now_ms);
+
+        let same_direction = direction(command.velocity_rpm) == direction(self.output.velocity_rpm);
+
+        if same_direction || self.output.current_percent <= 0.0 {
+            self.output.velocity_rpm = command.velocity_rpm;
+            self.output.current_percent = self.step(command.current_percent, dt);
+        } else {
+            self.output.current_percent = self.step(0.0, dt);
+        }
+
+        self.output
+    }
+
+    fn step(&self, target: f32, dt: f32) -> f32 {
+        let current = self.output.current_percent;
+
+        if target > current {
+            (current + self.config.rise_rate * dt).min(target)
+        } else {
+            (current - self.config.fall_rate * dt).max(target)
+        }
+    }
+}
+
+fn direction(velocity_rpm: f32) -> i8 {
+    if velocity_rpm > 0.0 {
+        1
+    } else if velocity_rpm < 0.0 {
+        -1
+    } else {
+        0
+    }
+}
@@ -0,0 +1,70 @@
+//! Blended regenerative braking
+//!
+//! Maps a brake input to a regenerative braking command, fading regen out at
+//! low speed where it is ineffective and the mechanical brakes take over.
+
+use crate::driver_controls::DriveCommand;
+use crate::wavesculptor::Status;
+
+/// Blended regen configuration
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Regen current at full brake input in percent
+    pub max_regen_percent: f32,
+    /// Speed below which no regen is applied in m/s
+    pub cutoff_speed: f32,
+    /// Width of the band above the cutoff where regen fades in, in m/s
+    pub fade_band: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            max_regen_percent: 50.0,
+            cutoff_speed: 1.0,
+            fade_band: 2.0,
+        }
+    }
+}
+
+/// Blended regenerative braking
+#[derive(Debug, Clone, Copy)]
+pub struct BlendedRegen {
+    config: Config,
+}
+
+impl BlendedRegen {
+    /// Create a new blended regen helper.
+    pub fn new(config: Config) -> Self {
+        Self { config }
+    }
+
+    /// Regen current in percent for a brake input between 0 and 1 at the
+    /// given vehicle speed in m/s.
+    pub fn current_percent(&self, brake: f32, speed: f32) -> f32 {
+        let brake = brake.clamp(0.0, 1.0);
+        let speed = speed.abs();
+
+        let fade = if speed <= self.config.cutoff_speed {
+            0.0
+        } else if self.config.fade_band <= 0.0 {
+            1.0
+        } else {
+            ((speed - self.config.cutoff_speed) / self.config.fade_band).min(1.0)
+        };
+
+        brake * fade * self.config.max_regen_percent
+    }
+
+    /// Regen command for a brake input between 0 and 1.
+    ///
+    /// No regen is applied when the vehicle velocity is unknown.
+    pub fn command(&self, brake: f32, status: &Status) -> DriveCommand {
+        let current = status
+            .vehicle_velocity
+            .map(|speed| self.current_percent(brake, speed))
+            .unwrap_or(0.0);
+
+        DriveCommand::regen(current)
+    }
+}
@@ -0,0 +1,136 @@
+//! Pack internal resistance estimation
+//!
+//! Estimates the pack's DC internal resistance from the voltage change
+//! accompanying each sharp current step, such as when accelerating or
+//! lifting off. Each step gives a raw estimate, smoothed so the value is
+//! steady enough to track pack health over a race week.
+//!
+//! Pack current is positive while discharging.
+
+use crate::bms::Status;
+
+/// Resistance estimator configuration
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Smallest current step giving an estimate in amps
+    pub min_current_step: f32,
+    /// Longest time between readings for a step in milliseconds, so the
+    /// voltage change isn't swamped by the state of charge drifting
+    pub max_step_ms: u32,
+    /// Largest plausible resistance in ohms, rejecting steps corrupted by
+    /// noise or stale readings
+    pub max_resistance: f32,
+    /// Weight given to each new estimate, from 0 to 1
+    pub smoothing: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            min_current_step: 20.0,
+            max_step_ms: 500,
+            max_resistance: 1.0,
+            smoothing: 0.05,
+        }
+    }
+}
+
+/// Pack voltage and current reading
+#[derive(Debug, Clone, Copy)]
+struct Reading {
+    time_ms: u32,
+    voltage: f32,
+    current: f32,
+}
+
+/// Pack internal resistance estimator
+#[derive(Debug, Clone, Copy)]
+pub struct ResistanceEstimator {
+    config: Config,
+    previous: Option<Reading>,
+    estimate: Option<f32>,
+    steps: u32,
+}
+
+impl ResistanceEstimator {
+    /// Create a new estimator with no estimate.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            previous: None,
+            estimate: None,
+            steps: 0,
+        }
+    }
+
+    /// Create an estimator continuing from a previously persisted estimate
+    /// in ohms.
+    pub fn with_estimate(config: Config, ohms: f32) -> Self {
+        Self {
+            estimate: Some(ohms),
+            ..Self::new(config)
+        }
+    }
+
+    /// Smoothed resistance in ohms.
+    pub fn estimate(&self) -> Option<f32> {
+        self.estimate
+    }
+
+    /// Number of current steps contributing to the estimate since creation.
+    pub fn steps(&self) -> u32 {
+        self.steps
+    }
+
+    /// Update from the latest BMU status and return the smoothed resistance
+    /// in ohms.
+    pub fn update<const CMUS: usize>(&mut self, now_ms: u32, status: &Status<CMUS>) -> Option<f32> {
+        if let (Some(voltage_mv), Some(current_ma)) =
+            (status.pack_voltage_mv, status.pack_current_ma)
+        {
+            self.update_reading(
+                now_ms,
+                voltage_mv as f32 / 1000.0,
+                current_ma as f32 / 1000.0,
+            );
+        }
+
+        self.estimate
+    }
+
+    /// Update from a pack voltage in volts and current in amps measured
+    /// together, returning the smoothed resistance in ohms.
+    pub fn update_reading(&mut self, now_ms: u32, voltage: f32, current: f32) -> Option<f32> {
+        let reading = Reading {
+            time_ms: now_ms,
+            voltage,
+            current,
+        };
+
+        let Some(previous) = self.previous.replace(reading) else {
+            return self.estimate;
+        };
+
+        let current_step = current - previous.current;
+        if current_step.abs() < self.config.min_current_step
+            || now_ms.wrapping_sub(previous.time_ms) > self.config.max_step_ms
+        {
+            return self.estimate;
+        }
+
+        // voltage sags as discharge current rises
+        let ohms = (previous.voltage - voltage) / current_step;
+        if !(ohms > 0.0 && ohms <= self.config.max_resistance) {
+            log_debug!("implausible pack resistance step {} ohms", ohms);
+            return self.estimate;
+        }
+
+        self.steps = self.steps.saturating_add(1);
+        self.estimate = Some(match self.estimate {
+            Some(estimate) => estimate + self.config.smoothing * (ohms - estimate),
+            None => ohms,
+        });
+
+        self.estimate
+    }
+}
@@ -0,0 +1,236 @@
+//! Vehicle safety supervision
+//!
+//! Combines the individual watchdogs, the BMU heartbeat, motor controller
+//! and driver controls broadcasts and pedal plausibility, into a single
+//! state and a set of actions the vehicle controller must take.
+//!
+//! Each watchdog is only considered once it has been updated, so vehicles
+//! without, say, driver controls simply never update that watchdog.
+//!
+//! [`estop`] produces the emergency stop frame sequence.
+
+use bitflags::bitflags;
+use bxcan::Frame;
+
+use crate::black_box::{self, BlackBox};
+use crate::bms::Bmu;
+use crate::driver_controls::{DriveCommand, DriverControls, SwitchPosition};
+use crate::heartbeat::{self, HeartbeatSupervisor};
+use crate::pedal::PedalBox;
+use crate::thresholds::Measurements;
+use crate::tx::TxSink;
+use crate::wavesculptor::WaveSculptor;
+
+bitflags! {
+    /// Tripped watchdogs
+    pub struct Faults: u8 {
+        const BMU_HEARTBEAT    = 0x01;
+        const MOTOR_CONTROLLER = 0x02;
+        const DRIVER_CONTROLS  = 0x04;
+        const PEDAL            = 0x08;
+    }
+}
+
+bitflags! {
+    /// Actions required of the vehicle controller
+    pub struct Actions: u8 {
+        /// Command zero torque
+        const ZERO_TORQUE     = 0x01;
+        /// Open the contactors
+        const OPEN_CONTACTORS = 0x02;
+        /// Alert the driver
+        const ALERT_DRIVER    = 0x04;
+    }
+}
+
+/// Overall safety state
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SafetyState {
+    /// No watchdog tripped
+    #[default]
+    Normal,
+    /// Drive is lost but the pack is still supervised
+    Degraded,
+    /// The pack is no longer supervised and must be isolated
+    SafeState,
+}
+
+impl Faults {
+    /// Safety state these faults call for.
+    pub fn state(self) -> SafetyState {
+        if self.contains(Faults::BMU_HEARTBEAT) {
+            SafetyState::SafeState
+        } else if self.is_empty() {
+            SafetyState::Normal
+        } else {
+            SafetyState::Degraded
+        }
+    }
+
+    /// Actions these faults require.
+    pub fn actions(self) -> Actions {
+        match self.state() {
+            SafetyState::Normal => Actions::empty(),
+            SafetyState::Degraded => Actions::ZERO_TORQUE | Actions::ALERT_DRIVER,
+            SafetyState::SafeState => Actions::all(),
+        }
+    }
+}
+
+/// Safety supervisor configuration
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// BMU heartbeat supervision
+    pub bmu: heartbeat::Config,
+    /// Motor controller broadcast supervision
+    pub motor_controller: heartbeat::Config,
+    /// Driver controls broadcast supervision
+    pub driver_controls: heartbeat::Config,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            bmu: heartbeat::Config::default(),
+            motor_controller: heartbeat::Config { grace_ms: 500 },
+            driver_controls: heartbeat::Config { grace_ms: 500 },
+        }
+    }
+}
+
+/// Vehicle safety supervisor
+pub struct SafetySupervisor {
+    bmu: HeartbeatSupervisor<fn()>,
+    motor_controller: HeartbeatSupervisor<fn()>,
+    driver_controls: HeartbeatSupervisor<fn()>,
+    faults: Faults,
+    state: SafetyState,
+}
+
+impl SafetySupervisor {
+    /// Create a new supervisor in the normal state.
+    pub fn new(config: Config) -> Self {
+        Self {
+            bmu: HeartbeatSupervisor::new(config.bmu, || {}),
+            motor_controller: HeartbeatSupervisor::new(config.motor_controller, || {}),
+            driver_controls: HeartbeatSupervisor::new(config.driver_controls, || {}),
+            faults: Faults::empty(),
+            state: SafetyState::Normal,
+        }
+    }
+
+    /// Tripped watchdogs.
+    pub fn faults(&self) -> Faults {
+        self.faults
+    }
+
+    /// Overall safety state.
+    pub fn state(&self) -> SafetyState {
+        self.state
+    }
+
+    /// Actions required of the vehicle controller.
+    pub fn actions(&self) -> Actions {
+        self.faults.actions()
+    }
+
+    /// Supervise the BMU heartbeat and return the overall state.
+    pub fn update_bmu<const CMUS: usize>(&mut self, now_ms: u32, bmu: &Bmu<CMUS>) -> SafetyState {
+        let lost = self.bmu.update(now_ms, bmu);
+        self.set(Faults::BMU_HEARTBEAT, lost)
+    }
+
+    /// Supervise the motor controller broadcasts and return the overall
+    /// state.
+    pub fn update_motor_controller(&mut self, now_ms: u32, ws: &WaveSculptor) -> SafetyState {
+        let lost = self
+            .motor_controller
+            .update_last_seen(now_ms, ws.last_receive_ms());
+        self.set(Faults::MOTOR_CONTROLLER, lost)
+    }
+
+    /// Supervise the driver controls broadcasts and return the overall state.
+    pub fn update_driver_controls(
+        &mut self,
+        now_ms: u32,
+        controls: &DriverControls,
+    ) -> SafetyState {
+        let lost = self
+            .driver_controls
+            .update_last_seen(now_ms, controls.last_receive_ms());
+        self.set(Faults::DRIVER_CONTROLS, lost)
+    }
+
+    /// Supervise pedal plausibility and return the overall state.
+    pub fn update_pedal(&mut self, pedal: &PedalBox) -> SafetyState {
+        self.set(Faults::PEDAL, pedal.fault().is_some())
+    }
+
+    fn set(&mut self, fault: Faults, active: bool) -> SafetyState {
+        self.faults.set(fault, active);
+
+        let state = self.faults.state();
+        if state != self.state {
+            log_warn!("safety state {:?}, faults {:?}", state, self.faults);
+            self.state = state;
+        }
+
+        state
+    }
+}
+
+/// Black-box alarm bit marking an emergency stop
+pub const ESTOP_ALARM: u32 = 0x8000_0000;
+
+/// Emergency stop frames, in sending order.
+///
+/// A zero torque drive command, zero bus current and finally every switch
+/// off, so the BMU opens the contactors once the motor is no longer loaded.
+pub fn estop_frames(controls: DriverControls) -> [Frame; 3] {
+    [
+        controls.drive(DriveCommand::torque(0.0)),
+        controls.motor_power(0.0),
+        controls.switches(SwitchPosition::new()),
+    ]
+}
+
+/// Emergency stop, recording a marker with `alarms` and every signal known
+/// by `sources` in the black box and returning the frames to send.
+///
+/// A failed black-box write is logged but never holds back the frames.
+pub fn estop<S: black_box::Storage>(
+    now_ms: u32,
+    controls: DriverControls,
+    black_box: &mut BlackBox<S>,
+    alarms: u32,
+    sources: &[&dyn Measurements],
+) -> [Frame; 3] {
+    log_warn!("emergency stop");
+
+    if let Err(error) = black_box.record(now_ms, alarms | ESTOP_ALARM, sources, None) {
+        log_warn!("emergency stop marker not recorded: {}", error);
+    }
+
+    estop_frames(controls)
+}
+
+/// Emergency stop as [`estop`], sending the frames through `sink`.
+///
+/// Every frame is attempted even if an earlier one fails, returning the
+/// first failure.
+pub fn estop_into<S: black_box::Storage>(
+    now_ms: u32,
+    controls: DriverControls,
+    black_box: &mut BlackBox<S>,
+    alarms: u32,
+    sources: &[&dyn Measurements],
+    sink: &mut impl TxSink,
+) -> Result<(), &'static str> {
+    let mut result = Ok(());
+
+    for frame in estop(now_ms, controls, black_box, alarms, sources) {
+        result = result.and(sink.transmit(&frame));
+    }
+
+    result
+}
@@ -0,0 +1,89 @@
+//! SD card file system
+//!
+//! Implements the CSV logger's [`FileSystem`] on a FAT formatted SD card
+//! through `embedded-sdmmc`, writing log files into an open directory.
+//!
+//! Enabled with the `sdmmc` feature.
+
+use embedded_sdmmc::{BlockDevice, Mode, RawDirectory, RawFile, TimeSource, VolumeManager};
+
+use crate::csv_log::FileSystem;
+
+/// Log file directory on an SD card
+pub struct SdFileSystem<
+    'a,
+    D: BlockDevice,
+    T: TimeSource,
+    const MAX_DIRS: usize,
+    const MAX_FILES: usize,
+    const MAX_VOLUMES: usize,
+> {
+    volume_mgr: &'a mut VolumeManager<D, T, MAX_DIRS, MAX_FILES, MAX_VOLUMES>,
+    directory: RawDirectory,
+    file: Option<RawFile>,
+}
+
+impl<
+        'a,
+        D: BlockDevice,
+        T: TimeSource,
+        const MAX_DIRS: usize,
+        const MAX_FILES: usize,
+        const MAX_VOLUMES: usize,
+    > SdFileSystem<'a, D, T, MAX_DIRS, MAX_FILES, MAX_VOLUMES>
+{
+    /// Create a new file system writing into `directory`, already opened
+    /// through `volume_mgr`.
+    pub fn new(
+        volume_mgr: &'a mut VolumeManager<D, T, MAX_DIRS, MAX_FILES, MAX_VOLUMES>,
+        directory: RawDirectory,
+    ) -> Self {
+        Self {
+            volume_mgr,
+            directory,
+            file: None,
+        }
+    }
+}
+
+impl<
+        D: BlockDevice,
+        T: TimeSource,
+        const MAX_DIRS: usize,
+        const MAX_FILES: usize,
+        const MAX_VOLUMES: usize,
+    > FileSystem for SdFileSystem<'_, D, T, MAX_DIRS, MAX_FILES, MAX_VOLUMES>
+{
+    fn open(&mut self, name: &str) -> Result<u32, &'static str> {
+        self.close()?;
+
+        let file = self
+            .volume_mgr
+            .open_file_in_dir(self.directory, name, Mode::ReadWriteCreateOrAppend)
+            .map_err(|_| "failed to open SD card file")?;
+
+        self.file = Some(file);
+
+        self.volume_mgr
+            .file_length(file)
+            .map_err(|_| "failed to read SD card file length")
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), &'static str> {
+        let file = self.file.ok_or("no SD card file open")?;
+
+        self.volume_mgr
+            .write(file, data)
+            .map_err(|_| "failed to write SD card file")
+    }
+
+    fn close(&mut self) -> Result<(), &'static str> {
+        if let Some(file) = self.file.take() {
+            self.volume_mgr
+                .close_file(file)
+                .map_err(|_| "failed to close SD card file")?;
+        }
+
+        Ok(())
+    }
+}
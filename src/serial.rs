@@ -0,0 +1,158 @@
+//! Serial downlink transport
+//!
+//! Frames packets for byte-oriented links such as UART or XBee. Each packet
+//! has a CRC-16/CCITT-FALSE appended, little-endian, and is COBS encoded so
+//! the frame contains no zero bytes, followed by a single zero delimiter.
+//! A receiver joining mid-stream, or losing bytes, resynchronises at the next
+//! delimiter.
+
+use crate::telemetry::crc16;
+
+/// CRC length in bytes
+pub const CRC_LEN: usize = 2;
+
+/// Longest framed length of a `payload_len` byte packet, including the
+/// delimiter.
+pub const fn max_frame_len(payload_len: usize) -> usize {
+    let len = payload_len + CRC_LEN;
+
+    len + len / 254 + 2
+}
+
+/// Frame `payload` into `buf`, returning the frame length.
+pub fn encode(payload: &[u8], buf: &mut [u8]) -> Result<usize, &'static str> {
+    if buf.len() < max_frame_len(payload.len()) {
+        return Err("serial frame buffer too small");
+    }
+
+    let crc = crc16(payload).to_le_bytes();
+
+    let mut code_index = 0;
+    let mut code = 1u8;
+    let mut out = 1;
+
+    for &byte in payload.iter().chain(crc.iter()) {
+        if byte != 0 {
+            buf[out] = byte;
+            out += 1;
+            code += 1;
+        }
+
+        if byte == 0 || code == 0xFF {
+            buf[code_index] = code;
+            code_index = out;
+            out += 1;
+            code = 1;
+        }
+    }
+
+    buf[code_index] = code;
+    buf[out] = 0;
+
+    Ok(out + 1)
+}
+
+/// Decode a COBS block sequence in place, returning the decoded length.
+fn decode_in_place(buf: &mut [u8]) -> Result<usize, &'static str> {
+    let mut read = 0;
+    let mut write = 0;
+
+    while read < buf.len() {
+        let code = buf[read];
+        read += 1;
+
+        for _ in 1..code {
+            if read >= buf.len() {
+                return Err("serial frame truncated");
+            }
+
+            buf[write] = buf[read];
+            write += 1;
+            read += 1;
+        }
+
+        if code != 0xFF && read < buf.len() {
+            buf[write] = 0;
+            write += 1;
+        }
+    }
+
+    Ok(write)
+}
+
+/// Resynchronising frame decoder, buffering frames up to `N` bytes
+#[derive(Debug, Clone, Copy)]
+pub struct FrameDecoder<const N: usize> {
+    buf: [u8; N],
+    len: usize,
+    overflow: bool,
+}
+
+impl<const N: usize> Default for FrameDecoder<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> FrameDecoder<N> {
+    /// Create a new decoder.
+    pub fn new() -> Self {
+        Self {
+            buf: [0; N],
+            len: 0,
+            overflow: false,
+        }
+    }
+
+    /// Discard any partially received frame.
+    pub fn reset(&mut self) {
+        self.len = 0;
+        self.overflow = false;
+    }
+
+    /// Process a received byte, returning the payload once a complete frame
+    /// has been received.
+    ///
+    /// Corrupted and oversized frames return an error, after which decoding
+    /// continues with the next frame.
+    pub fn push(&mut self, byte: u8) -> Option<Result<&[u8], &'static str>> {
+        if byte != 0 {
+            if self.len < N {
+                self.buf[self.len] = byte;
+                self.len += 1;
+            } else {
+                self.overflow = true;
+            }
+
+            return None;
+        }
+
+        let len = core::mem::take(&mut self.len);
+
+        if core::mem::take(&mut self.overflow) {
+            return Some(Err("serial frame too long"));
+        }
+
+        // back to back delimiters carry no frame
+        if len == 0 {
+            return None;
+        }
+
+        Some(self.decode(len))
+    }
+
+    fn decode(&mut self, len: usize) -> Result<&[u8], &'static str> {
+        let len = decode_in_place(&mut self.buf[..len])?;
+
+        if len < CRC_LEN {
+            return Err("serial frame too short");
+        }
+
+        let (payload, crc) = self.buf[..len].split_at(len - CRC_LEN);
+        if crc16(payload) != u16::from_le_bytes([crc[0], crc[1]]) {
+            return Err("serial frame CRC mismatch");
+        }
+
+        Ok(payload)
+    }
+}
@@ -0,0 +1,147 @@
+//! Induction motor slip health
+//!
+//! For an induction motor the slip speed rises in proportion to the load,
+//! so the slip per amp of quadrature (torque producing) current is roughly
+//! constant for a healthy motor. A baseline ratio is learnt from the first
+//! loaded samples, or restored from a previous session, and a slowly
+//! smoothed trend compared against it. Drift beyond a tolerance points at
+//! rotor or bearing trouble.
+//!
+//! Uses the slip speed broadcast, which permanent magnet motors do not
+//! report.
+
+use crate::wavesculptor::Status;
+
+/// Slip health configuration
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Quadrature current magnitude below which samples are ignored in amps
+    pub min_current: f32,
+    /// Samples averaged into the baseline
+    pub baseline_samples: u32,
+    /// Weight given to each new sample in the trend, from 0 to 1
+    pub smoothing: f32,
+    /// Fractional change of the trend from the baseline that is flagged
+    pub drift_tolerance: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            min_current: 10.0,
+            baseline_samples: 200,
+            smoothing: 0.01,
+            drift_tolerance: 0.2,
+        }
+    }
+}
+
+/// Slip health monitor
+#[derive(Debug, Clone, Copy)]
+pub struct SlipHealth {
+    config: Config,
+    baseline: Option<f32>,
+    baseline_sum: f32,
+    baseline_count: u32,
+    trend: Option<f32>,
+    drifting: bool,
+}
+
+impl SlipHealth {
+    /// Create a new monitor, learning its baseline from the first samples.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            baseline: None,
+            baseline_sum: 0.0,
+            baseline_count: 0,
+            trend: None,
+            drifting: false,
+        }
+    }
+
+    /// Start from a baseline in Hz/A from a previous session.
+    pub fn with_baseline(mut self, baseline: f32) -> Self {
+        self.baseline = Some(baseline);
+        self
+    }
+
+    /// Baseline slip per amp in Hz/A, once learnt.
+    pub fn baseline(&self) -> Option<f32> {
+        self.baseline
+    }
+
+    /// Smoothed slip per amp in Hz/A.
+    pub fn trend(&self) -> Option<f32> {
+        self.trend
+    }
+
+    /// Fractional change of the trend from the baseline.
+    pub fn drift(&self) -> Option<f32> {
+        let baseline = self.baseline.filter(|baseline| *baseline > 0.0)?;
+
+        Some(self.trend? / baseline - 1.0)
+    }
+
+    /// Has the trend drifted beyond the tolerance?
+    pub fn is_drifting(&self) -> bool {
+        self.drifting
+    }
+
+    /// Update from the latest WaveSculptor status and return whether the
+    /// slip has drifted.
+    pub fn update(&mut self, status: &Status) -> bool {
+        if let (Some(slip), Some(current)) = (status.slip_speed, status.motor_current_vector) {
+            self.update_sample(slip, current.im);
+        }
+
+        self.drifting
+    }
+
+    /// Update from a slip speed in Hz and quadrature current in amps and
+    /// return whether the slip has drifted.
+    ///
+    /// Lightly loaded or non-finite samples are ignored.
+    pub fn update_sample(&mut self, slip_hz: f32, current: f32) -> bool {
+        if !(slip_hz.is_finite() && current.is_finite()) || current.abs() < self.config.min_current
+        {
+            return self.drifting;
+        }
+
+        let ratio = slip_hz.abs() / current.abs();
+
+        self.trend = Some(match self.trend {
+            Some(trend) => trend + self.config.smoothing * (ratio - trend),
+            None => ratio,
+        });
+
+        if self.baseline.is_none() {
+            self.baseline_sum += ratio;
+            self.baseline_count += 1;
+
+            if self.baseline_count >= self.config.baseline_samples {
+                let baseline = self.baseline_sum / self.baseline_count as f32;
+                log_info!("slip baseline {} Hz/A", baseline);
+                self.baseline = Some(baseline);
+            }
+
+            return self.drifting;
+        }
+
+        let drifting = self
+            .drift()
+            .is_some_and(|drift| drift.abs() > self.config.drift_tolerance);
+
+        if drifting != self.drifting {
+            if drifting {
+                log_warn!("slip drifted from baseline by {:?}", self.drift());
+            } else {
+                log_info!("slip back within tolerance of baseline");
+            }
+
+            self.drifting = drifting;
+        }
+
+        self.drifting
+    }
+}
@@ -0,0 +1,92 @@
+//! Speed limiter
+//!
+//! Limits outgoing drive commands as the vehicle approaches a maximum speed,
+//! regardless of the driver's pedal input.
+
+use crate::driver_controls::DriveCommand;
+use crate::wavesculptor::Status;
+
+/// How the drive current is limited near the maximum speed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Drive current is removed once the limit is reached.
+    Clamp,
+    /// Drive current is scaled down linearly across the approach band,
+    /// reaching zero at the limit.
+    Scale,
+}
+
+/// Speed limiter configuration
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Maximum vehicle speed in m/s
+    pub limit: f32,
+    /// Width of the band below the limit where scaling is applied in m/s
+    pub band: f32,
+    /// Limiting behaviour
+    pub mode: Mode,
+}
+
+/// Speed limiter
+#[derive(Debug, Clone, Copy)]
+pub struct SpeedLimiter {
+    config: Config,
+}
+
+impl SpeedLimiter {
+    /// Create a new speed limiter.
+    pub fn new(config: Config) -> Self {
+        Self { config }
+    }
+
+    /// Change the maximum vehicle speed in m/s.
+    pub fn set_limit(&mut self, limit: f32) {
+        self.config.limit = limit;
+    }
+
+    /// Fraction of drive current allowed at the given vehicle speed.
+    pub fn factor(&self, speed: f32) -> f32 {
+        let speed = speed.abs();
+
+        match self.config.mode {
+            Mode::Clamp => {
+                if speed >= self.config.limit {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+            Mode::Scale => {
+                let start = self.config.limit - self.config.band;
+
+                if speed >= self.config.limit {
+                    0.0
+                } else if speed <= start || self.config.band <= 0.0 {
+                    1.0
+                } else {
+                    (self.config.limit - speed) / self.config.band
+                }
+            }
+        }
+    }
+
+    /// Limit a drive command.
+    ///
+    /// Only commands driving in the current direction of travel are limited,
+    /// braking commands pass through untouched. Commands are passed through
+    /// when the vehicle velocity is unknown.
+    pub fn apply(&self, command: DriveCommand, status: &Status) -> DriveCommand {
+        let Some(velocity) = status.vehicle_velocity else {
+            return command;
+        };
+
+        if command.velocity_rpm * velocity <= 0.0 {
+            return command;
+        }
+
+        DriveCommand::new(
+            command.velocity_rpm,
+            command.current_percent * self.factor(velocity),
+        )
+    }
+}
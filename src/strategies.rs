@@ -0,0 +1,79 @@
+//! Proptest strategies
+//!
+//! Strategies generating arbitrary CAN frames, both within and outside a
+//! device's identifier window, for fuzz-style testing that decoders never
+//! panic and maintain their invariants.
+//!
+//! Enabled with the `proptest` feature.
+
+use bxcan::{Data, ExtendedId, Frame, StandardId};
+use proptest::prelude::*;
+
+/// Highest standard identifier
+pub const STANDARD_ID_MAX: u16 = 0x7FF;
+
+/// Any standard identifier.
+pub fn standard_id() -> impl Strategy<Value = u16> {
+    0..=STANDARD_ID_MAX
+}
+
+/// Standard identifiers within `base..base + width`.
+///
+/// The window must contain at least one valid identifier.
+pub fn id_in_window(base: u16, width: u16) -> impl Strategy<Value = u16> {
+    let end = base.saturating_add(width).min(STANDARD_ID_MAX + 1);
+    assert!(base < end, "empty identifier window");
+
+    base..end
+}
+
+/// Standard identifiers outside `base..base + width`.
+pub fn id_outside_window(base: u16, width: u16) -> impl Strategy<Value = u16> {
+    let end = base.saturating_add(width);
+
+    standard_id().prop_filter("identifier inside window", move |id| {
+        *id < base || *id >= end
+    })
+}
+
+/// Any data length code from 0 to 8.
+pub fn dlc() -> impl Strategy<Value = u8> {
+    0..=8u8
+}
+
+/// Payloads of any length from 0 to 8 bytes.
+pub fn payload() -> impl Strategy<Value = Data> {
+    (any::<[u8; 8]>(), dlc()).prop_map(|(bytes, len)| Data::new(&bytes[..len as usize]).unwrap())
+}
+
+/// Data frames with the given identifiers and arbitrary payloads.
+pub fn data_frame(id: impl Strategy<Value = u16>) -> impl Strategy<Value = Frame> {
+    (id, payload()).prop_map(|(id, data)| Frame::new_data(StandardId::new(id).unwrap(), data))
+}
+
+/// Any standard data frame.
+pub fn frame() -> impl Strategy<Value = Frame> {
+    data_frame(standard_id())
+}
+
+/// Standard data frames within a device's identifier window.
+pub fn frame_in_window(base: u16, width: u16) -> impl Strategy<Value = Frame> {
+    data_frame(id_in_window(base, width))
+}
+
+/// Standard data frames outside a device's identifier window.
+pub fn frame_outside_window(base: u16, width: u16) -> impl Strategy<Value = Frame> {
+    data_frame(id_outside_window(base, width))
+}
+
+/// Extended data frames, which no device in this crate uses.
+pub fn extended_frame() -> impl Strategy<Value = Frame> {
+    (0..=ExtendedId::MAX.as_raw(), payload())
+        .prop_map(|(id, data)| Frame::new_data(ExtendedId::new(id).unwrap(), data))
+}
+
+/// Remote frames with standard identifiers.
+pub fn remote_frame() -> impl Strategy<Value = Frame> {
+    (standard_id(), dlc())
+        .prop_map(|(id, dlc)| Frame::new_remote(StandardId::new(id).unwrap(), dlc))
+}
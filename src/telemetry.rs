@@ -0,0 +1,443 @@
+//! Radio telemetry packets
+//!
+//! A compact, versioned binary packet carrying a selectable set of signals
+//! from the car to the chase car, sized for 50 to 200 byte radio MTUs.
+//!
+//! All fields are little-endian:
+//!
+//! ```text
+//! offset  size  field
+//!      0     1  version
+//!      1     1  packet kind
+//!      2     2  sequence number
+//!      4     4  signal mask, bit n set for the nth signal in `Signal::ALL`
+//!      8   4*n  f32 value of each signal in the mask, in `Signal::ALL` order
+//!    end     2  CRC-16/CCITT-FALSE over all preceding bytes
+//! ```
+//!
+//! For low bandwidth links the [`DeltaEncoder`] sends full packets only
+//! periodically, and in between delta packets carrying just the signals that
+//! changed. The [`DeltaDecoder`] reassembles the complete state on the
+//! receiving side.
+
+use crate::thresholds::{Measurements, Signal};
+
+/// Packet format version
+pub const VERSION: u8 = 1;
+
+/// Header length in bytes
+pub const HEADER_LEN: usize = 8;
+
+/// CRC length in bytes
+pub const CRC_LEN: usize = 2;
+
+/// Number of signals a packet can carry
+pub const SIGNAL_COUNT: usize = Signal::ALL.len();
+
+/// Longest possible packet in bytes
+pub const MAX_PACKET_LEN: usize = HEADER_LEN + 4 * SIGNAL_COUNT + CRC_LEN;
+
+/// Telemetry packet kind
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacketKind {
+    /// Carries every selected signal
+    Full = 0,
+    /// Carries only the signals changed since the previous packet
+    Delta = 1,
+}
+
+/// CRC-16/CCITT-FALSE checksum.
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+
+    for byte in data {
+        crc ^= (*byte as u16) << 8;
+
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+
+    crc
+}
+
+/// Set of signals
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SignalSet(u32);
+
+impl SignalSet {
+    /// No signals.
+    pub fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Every signal.
+    pub fn all() -> Self {
+        Self((1 << SIGNAL_COUNT) - 1)
+    }
+
+    /// Set from its bit mask.
+    pub fn from_bits(bits: u32) -> Self {
+        Self(bits & Self::all().0)
+    }
+
+    /// Bit mask, bit n set for the nth signal in [`Signal::ALL`].
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// This set with `signal` added.
+    pub fn with(self, signal: Signal) -> Self {
+        Self(self.0 | 1 << signal as u32)
+    }
+
+    /// Does the set contain `signal`?
+    pub fn contains(&self, signal: Signal) -> bool {
+        self.0 & 1 << signal as u32 != 0
+    }
+
+    /// Number of signals in the set.
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    /// Is the set empty?
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Signals in the set, in [`Signal::ALL`] order.
+    pub fn iter(self) -> impl Iterator<Item = Signal> {
+        Signal::ALL
+            .into_iter()
+            .filter(move |signal| self.contains(*signal))
+    }
+}
+
+/// Decoded telemetry packet
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Packet {
+    /// Packet kind
+    pub kind: PacketKind,
+    /// Sequence number
+    pub sequence: u16,
+    values: [Option<f32>; SIGNAL_COUNT],
+}
+
+impl Packet {
+    /// Value of a signal, if carried by the packet.
+    pub fn value(&self, signal: Signal) -> Option<f32> {
+        self.values[signal as usize]
+    }
+
+    /// Signals carried by the packet.
+    pub fn signals(&self) -> SignalSet {
+        Signal::ALL
+            .into_iter()
+            .filter(|signal| self.value(*signal).is_some())
+            .fold(SignalSet::empty(), SignalSet::with)
+    }
+}
+
+impl Measurements for Packet {
+    fn value(&self, signal: Signal) -> Option<f32> {
+        Packet::value(self, signal)
+    }
+}
+
+/// Telemetry packet encoder, run on the car
+#[derive(Debug, Clone, Copy)]
+pub struct Encoder {
+    signals: SignalSet,
+    sequence: u16,
+}
+
+impl Encoder {
+    /// Create a new encoder sending the selected signals.
+    pub fn new(signals: SignalSet) -> Self {
+        Self {
+            signals,
+            sequence: 0,
+        }
+    }
+
+    /// Encode the selected signals known by `sources` into `buf`, returning
+    /// the packet length.
+    ///
+    /// Where several sources know a signal the first one wins. Signals no
+    /// source knows are left out of the packet.
+    pub fn encode(
+        &mut self,
+        sources: &[&dyn Measurements],
+        buf: &mut [u8],
+    ) -> Result<usize, &'static str> {
+        let mut values = [None; SIGNAL_COUNT];
+
+        for signal in self.signals.iter() {
+            values[signal as usize] = sources.iter().find_map(|source| source.value(signal));
+        }
+
+        let len = encode_packet(PacketKind::Full, self.sequence, &values, buf)?;
+        self.sequence = self.sequence.wrapping_add(1);
+
+        Ok(len)
+    }
+}
+
+fn encode_packet(
+    kind: PacketKind,
+    sequence: u16,
+    values: &[Option<f32>; SIGNAL_COUNT],
+    buf: &mut [u8],
+) -> Result<usize, &'static str> {
+    let present = values.iter().flatten().count();
+    let len = HEADER_LEN + 4 * present + CRC_LEN;
+
+    if buf.len() < len {
+        return Err("telemetry buffer too small");
+    }
+
+    let mask = Signal::ALL
+        .into_iter()
+        .filter(|signal| values[*signal as usize].is_some())
+        .fold(SignalSet::empty(), SignalSet::with);
+
+    buf[0] = VERSION;
+    buf[1] = kind as u8;
+    buf[2..4].copy_from_slice(&sequence.to_le_bytes());
+    buf[4..8].copy_from_slice(&mask.bits().to_le_bytes());
+
+    for (chunk, value) in buf[HEADER_LEN..len - CRC_LEN]
+        .chunks_exact_mut(4)
+        .zip(values.iter().flatten())
+    {
+        chunk.copy_from_slice(&value.to_le_bytes());
+    }
+
+    let crc = crc16(&buf[..len - CRC_LEN]);
+    buf[len - CRC_LEN..len].copy_from_slice(&crc.to_le_bytes());
+
+    Ok(len)
+}
+
+/// Decode a telemetry packet, checking its version and CRC.
+pub fn decode(packet: &[u8]) -> Result<Packet, &'static str> {
+    if packet.len() < HEADER_LEN + CRC_LEN {
+        return Err("telemetry packet too short");
+    }
+
+    let (body, crc) = packet.split_at(packet.len() - CRC_LEN);
+    if crc16(body) != u16::from_le_bytes([crc[0], crc[1]]) {
+        return Err("telemetry packet CRC mismatch");
+    }
+
+    if body[0] != VERSION {
+        return Err("unsupported telemetry packet version");
+    }
+
+    let kind = match body[1] {
+        0 => PacketKind::Full,
+        1 => PacketKind::Delta,
+        _ => return Err("unknown telemetry packet kind"),
+    };
+
+    let sequence = u16::from_le_bytes([body[2], body[3]]);
+    let mask = SignalSet::from_bits(u32::from_le_bytes(body[4..8].try_into().unwrap()));
+
+    if body.len() != HEADER_LEN + 4 * mask.len() {
+        return Err("telemetry packet length does not match signal mask");
+    }
+
+    let mut values = [None; SIGNAL_COUNT];
+
+    for (signal, chunk) in mask.iter().zip(body[HEADER_LEN..].chunks_exact(4)) {
+        values[signal as usize] = Some(f32::from_le_bytes(chunk.try_into().unwrap()));
+    }
+
+    Ok(Packet {
+        kind,
+        sequence,
+        values,
+    })
+}
+
+/// Telemetry packet decoder, run on the chase car
+///
+/// Counts packets lost in transit from gaps in the sequence numbers.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Decoder {
+    last_sequence: Option<u16>,
+    lost: u32,
+}
+
+impl Decoder {
+    /// Create a new decoder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Packets lost in transit so far.
+    pub fn lost(&self) -> u32 {
+        self.lost
+    }
+
+    /// Decode a received packet.
+    pub fn receive(&mut self, packet: &[u8]) -> Result<Packet, &'static str> {
+        let packet = decode(packet)?;
+
+        if let Some(last) = self.last_sequence {
+            let gap = packet.sequence.wrapping_sub(last).wrapping_sub(1);
+
+            // large gaps are duplicates or reordering rather than losses
+            if gap < u16::MAX / 2 {
+                self.lost = self.lost.saturating_add(gap as u32);
+            }
+        }
+
+        self.last_sequence = Some(packet.sequence);
+
+        Ok(packet)
+    }
+}
+
+/// Delta telemetry encoder, run on the car
+///
+/// Sends a full packet every `full_interval` packets and delta packets in
+/// between, so a receiver that missed a packet resynchronises at the next
+/// full one.
+#[derive(Debug, Clone, Copy)]
+pub struct DeltaEncoder {
+    signals: SignalSet,
+    full_interval: u16,
+    deadband: f32,
+    sequence: u16,
+    since_full: u16,
+    sent: [Option<f32>; SIGNAL_COUNT],
+}
+
+impl DeltaEncoder {
+    /// Create a new encoder sending the selected signals.
+    ///
+    /// A signal is included in a delta packet when it has changed by more
+    /// than `deadband` since it was last sent.
+    pub fn new(signals: SignalSet, full_interval: u16, deadband: f32) -> Self {
+        Self {
+            signals,
+            full_interval: full_interval.max(1),
+            deadband,
+            sequence: 0,
+            since_full: 0,
+            sent: [None; SIGNAL_COUNT],
+        }
+    }
+
+    /// Send a full packet next.
+    pub fn force_full(&mut self) {
+        self.since_full = 0;
+    }
+
+    /// Encode the selected signals known by `sources` into `buf`, returning
+    /// the packet length.
+    ///
+    /// See [`Encoder::encode`].
+    pub fn encode(
+        &mut self,
+        sources: &[&dyn Measurements],
+        buf: &mut [u8],
+    ) -> Result<usize, &'static str> {
+        let kind = if self.since_full == 0 {
+            PacketKind::Full
+        } else {
+            PacketKind::Delta
+        };
+
+        let mut values = [None; SIGNAL_COUNT];
+
+        for signal in self.signals.iter() {
+            let value = sources.iter().find_map(|source| source.value(signal));
+            let sent = self.sent[signal as usize];
+
+            let changed = match (value, sent) {
+                (Some(value), Some(sent)) => (value - sent).abs() > self.deadband,
+                (Some(_), None) => true,
+                (None, _) => false,
+            };
+
+            if kind == PacketKind::Full || changed {
+                values[signal as usize] = value;
+            }
+        }
+
+        let len = encode_packet(kind, self.sequence, &values, buf)?;
+
+        for (sent, value) in self.sent.iter_mut().zip(values) {
+            if value.is_some() {
+                *sent = value;
+            }
+        }
+
+        self.sequence = self.sequence.wrapping_add(1);
+        self.since_full = (self.since_full + 1) % self.full_interval;
+
+        Ok(len)
+    }
+}
+
+/// Delta telemetry decoder, run on the chase car
+///
+/// Applies delta packets on top of the latest full packet. After a lost
+/// packet the state is out of date until the next full packet arrives.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DeltaDecoder {
+    decoder: Decoder,
+    state: Option<Packet>,
+}
+
+impl DeltaDecoder {
+    /// Create a new decoder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Packets lost in transit so far.
+    pub fn lost(&self) -> u32 {
+        self.decoder.lost()
+    }
+
+    /// Reassembled state, if synchronised.
+    pub fn state(&self) -> Option<&Packet> {
+        self.state.as_ref()
+    }
+
+    /// Decode a received packet, returning the reassembled state if
+    /// synchronised.
+    pub fn receive(&mut self, packet: &[u8]) -> Result<Option<&Packet>, &'static str> {
+        let lost = self.decoder.lost();
+        let packet = self.decoder.receive(packet)?;
+
+        if self.decoder.lost() != lost {
+            self.state = None;
+        }
+
+        match packet.kind {
+            PacketKind::Full => self.state = Some(packet),
+            PacketKind::Delta => {
+                if let Some(state) = self.state.as_mut() {
+                    for (value, delta) in state.values.iter_mut().zip(packet.values) {
+                        if delta.is_some() {
+                            *value = delta;
+                        }
+                    }
+
+                    state.sequence = packet.sequence;
+                }
+            }
+        }
+
+        Ok(self.state.as_ref())
+    }
+}
@@ -0,0 +1,197 @@
+//! Thermal model temperature prediction
+//!
+//! Predicts the motor and heat-sink temperatures some minutes ahead with a
+//! first order thermal model of each, so derating can start before a limit
+//! is reached on a long climb. Each body settles at the ambient temperature
+//! plus its thermal resistance times the heat it dissipates, a fixed
+//! fraction of the bus power, approaching it with its thermal time constant.
+//!
+//! The model starts from the measured temperatures and assumes the smoothed
+//! bus power continues over the prediction horizon.
+
+use crate::wavesculptor::Status;
+
+/// First order thermal model of one body
+#[derive(Debug, Clone, Copy)]
+pub struct BodyConfig {
+    /// Thermal resistance to ambient in kelvin per watt
+    pub thermal_resistance: f32,
+    /// Thermal time constant in seconds
+    pub time_constant_s: f32,
+    /// Fraction of the bus power dissipated in the body
+    pub loss_fraction: f32,
+}
+
+impl BodyConfig {
+    /// Temperature after `horizon_s` seconds starting from `temperature`
+    /// while the bus draws `power` watts.
+    pub fn predict(&self, ambient: f32, temperature: f32, power: f32, horizon_s: f32) -> f32 {
+        let settled = ambient + self.thermal_resistance * self.loss_fraction * power.abs();
+        let decay = exp_neg(horizon_s / self.time_constant_s.max(f32::EPSILON));
+
+        settled + (temperature - settled) * decay
+    }
+}
+
+/// Thermal model configuration
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Motor model
+    pub motor: BodyConfig,
+    /// Motor controller heat-sink model
+    pub heatsink: BodyConfig,
+    /// Ambient temperature in degrees celcius
+    pub ambient_temperature: f32,
+    /// Prediction horizon in seconds
+    pub horizon_s: f32,
+    /// Bus power filter time constant in milliseconds
+    pub power_time_constant_ms: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            motor: BodyConfig {
+                thermal_resistance: 0.5,
+                time_constant_s: 900.0,
+                loss_fraction: 0.04,
+            },
+            heatsink: BodyConfig {
+                thermal_resistance: 0.8,
+                time_constant_s: 300.0,
+                loss_fraction: 0.02,
+            },
+            ambient_temperature: 30.0,
+            horizon_s: 180.0,
+            power_time_constant_ms: 10_000,
+        }
+    }
+}
+
+/// Predicted temperatures in degrees celcius
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Prediction {
+    /// Motor temperature
+    pub motor_temperature: Option<f32>,
+    /// Heat-sink temperature
+    pub heatsink_temperature: Option<f32>,
+}
+
+/// Thermal model
+#[derive(Debug, Clone, Copy)]
+pub struct ThermalModel {
+    config: Config,
+    power: Option<f32>,
+    last_update_ms: Option<u32>,
+    motor_temperature: Option<f32>,
+    heatsink_temperature: Option<f32>,
+}
+
+impl ThermalModel {
+    /// Create a new model with no measurements.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            power: None,
+            last_update_ms: None,
+            motor_temperature: None,
+            heatsink_temperature: None,
+        }
+    }
+
+    /// Change the ambient temperature in degrees celcius, for example from
+    /// a weather station.
+    pub fn set_ambient_temperature(&mut self, temperature: f32) {
+        self.config.ambient_temperature = temperature;
+    }
+
+    /// Smoothed bus power in watts.
+    pub fn power(&self) -> Option<f32> {
+        self.power
+    }
+
+    /// Temperatures predicted at the configured horizon.
+    pub fn prediction(&self) -> Prediction {
+        self.predict(self.config.horizon_s)
+    }
+
+    /// Temperatures predicted `horizon_s` seconds ahead.
+    ///
+    /// Temperatures not yet measured are not predicted.
+    pub fn predict(&self, horizon_s: f32) -> Prediction {
+        let ambient = self.config.ambient_temperature;
+        let power = self.power.unwrap_or_default();
+
+        Prediction {
+            motor_temperature: self
+                .motor_temperature
+                .map(|t| self.config.motor.predict(ambient, t, power, horizon_s)),
+            heatsink_temperature: self
+                .heatsink_temperature
+                .map(|t| self.config.heatsink.predict(ambient, t, power, horizon_s)),
+        }
+    }
+
+    /// Update from the latest WaveSculptor status and return the prediction.
+    pub fn update(&mut self, now_ms: u32, status: &Status) -> Prediction {
+        let power = status
+            .bus_voltage
+            .zip(status.bus_current)
+            .map(|(voltage, current)| voltage * current);
+
+        self.update_sample(
+            now_ms,
+            power,
+            status.motor_temperature,
+            status.heatsink_temperature,
+        )
+    }
+
+    /// Update from a bus power in watts and motor and heat-sink temperatures
+    /// in degrees celcius and return the prediction.
+    ///
+    /// Unknown or non-finite values are ignored.
+    pub fn update_sample(
+        &mut self,
+        now_ms: u32,
+        power: Option<f32>,
+        motor_temperature: Option<f32>,
+        heatsink_temperature: Option<f32>,
+    ) -> Prediction {
+        if let Some(power) = power.filter(|power| power.is_finite()) {
+            let dt = self
+                .last_update_ms
+                .map_or(0, |last| now_ms.wrapping_sub(last));
+            self.last_update_ms = Some(now_ms);
+
+            let alpha =
+                dt as f32 / (self.config.power_time_constant_ms as f32 + dt as f32).max(1.0);
+
+            self.power = Some(match self.power {
+                Some(smoothed) => smoothed + alpha * (power - smoothed),
+                None => power,
+            });
+        }
+
+        if let Some(temperature) = motor_temperature.filter(|t| t.is_finite()) {
+            self.motor_temperature = Some(temperature);
+        }
+
+        if let Some(temperature) = heatsink_temperature.filter(|t| t.is_finite()) {
+            self.heatsink_temperature = Some(temperature);
+        }
+
+        self.prediction()
+    }
+}
+
+/// `e^-x` for non-negative `x`, as `(1 - x/2^16)^(2^16)`.
+fn exp_neg(x: f32) -> f32 {
+    let mut value = (1.0 - x / 65536.0).max(0.0);
+
+    for _ in 0..16 {
+        value *= value;
+    }
+
+    value
+}
@@ -0,0 +1,296 @@
+//! Measurement thresholds
+//!
+//! A single table of warning and fault limits per signal, evaluated against
+//! the device status on every update, so scrutineering limits are defined in
+//! one place.
+
+use crate::{bms, wavesculptor};
+
+/// Measured signals
+///
+/// Variants are numbered in declaration order, matching [`Signal::ALL`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    /// WaveSculptor bus voltage in volts
+    BusVoltage,
+    /// WaveSculptor bus current in amps
+    BusCurrent,
+    /// Motor velocity in RPM
+    MotorVelocity,
+    /// Vehicle velocity in meters/second
+    VehicleVelocity,
+    /// Phase B current in amps RMS
+    PhaseBCurrent,
+    /// Phase C current in amps RMS
+    PhaseCCurrent,
+    /// 15V rail in volts
+    Rail15v,
+    /// 3.3V rail in volts
+    Rail3v3,
+    /// 1.9V rail in volts
+    Rail1v9,
+    /// Motor temperature in degrees celcius
+    MotorTemperature,
+    /// Heat-sink temperature in degrees celcius
+    HeatsinkTemperature,
+    /// DSP board temperature in degrees celcius
+    DspBoardTemperature,
+    /// Pack state of charge as a fraction
+    StateOfCharge,
+    /// Lowest cell voltage in millivolts
+    MinimumCellVoltage,
+    /// Highest cell voltage in millivolts
+    MaximumCellVoltage,
+    /// Lowest cell temperature in 1/10th degrees celcius
+    MinimumCellTemperature,
+    /// Highest cell temperature in 1/10th degrees celcius
+    MaximumCellTemperature,
+    /// Pack voltage in millivolts
+    PackVoltage,
+}
+
+impl Signal {
+    /// Every signal.
+    pub const ALL: [Signal; 18] = [
+        Signal::BusVoltage,
+        Signal::BusCurrent,
+        Signal::MotorVelocity,
+        Signal::VehicleVelocity,
+        Signal::PhaseBCurrent,
+        Signal::PhaseCCurrent,
+        Signal::Rail15v,
+        Signal::Rail3v3,
+        Signal::Rail1v9,
+        Signal::MotorTemperature,
+        Signal::HeatsinkTemperature,
+        Signal::DspBoardTemperature,
+        Signal::StateOfCharge,
+        Signal::MinimumCellVoltage,
+        Signal::MaximumCellVoltage,
+        Signal::MinimumCellTemperature,
+        Signal::MaximumCellTemperature,
+        Signal::PackVoltage,
+    ];
+
+    /// Signal name in snake case, for use in telemetry keys.
+    pub fn name(self) -> &'static str {
+        match self {
+            Signal::BusVoltage => "bus_voltage",
+            Signal::BusCurrent => "bus_current",
+            Signal::MotorVelocity => "motor_velocity",
+            Signal::VehicleVelocity => "vehicle_velocity",
+            Signal::PhaseBCurrent => "phase_b_current",
+            Signal::PhaseCCurrent => "phase_c_current",
+            Signal::Rail15v => "rail_15v",
+            Signal::Rail3v3 => "rail_3v3",
+            Signal::Rail1v9 => "rail_1v9",
+            Signal::MotorTemperature => "motor_temperature",
+            Signal::HeatsinkTemperature => "heatsink_temperature",
+            Signal::DspBoardTemperature => "dsp_board_temperature",
+            Signal::StateOfCharge => "state_of_charge",
+            Signal::MinimumCellVoltage => "minimum_cell_voltage",
+            Signal::MaximumCellVoltage => "maximum_cell_voltage",
+            Signal::MinimumCellTemperature => "minimum_cell_temperature",
+            Signal::MaximumCellTemperature => "maximum_cell_temperature",
+            Signal::PackVoltage => "pack_voltage",
+        }
+    }
+}
+
+/// Source of signal measurements
+pub trait Measurements {
+    /// Latest value of a signal, if known by this source.
+    fn value(&self, signal: Signal) -> Option<f32>;
+}
+
+impl Measurements for wavesculptor::Status {
+    fn value(&self, signal: Signal) -> Option<f32> {
+        match signal {
+            Signal::BusVoltage => self.bus_voltage,
+            Signal::BusCurrent => self.bus_current,
+            Signal::MotorVelocity => self.motor_velocity,
+            Signal::VehicleVelocity => self.vehicle_velocity,
+            Signal::PhaseBCurrent => self.phase_b_current,
+            Signal::PhaseCCurrent => self.phase_c_current,
+            Signal::Rail15v => self.rail_15v,
+            Signal::Rail3v3 => self.rail_3v3,
+            Signal::Rail1v9 => self.rail_1v9,
+            Signal::MotorTemperature => self.motor_temperature,
+            Signal::HeatsinkTemperature => self.heatsink_temperature,
+            Signal::DspBoardTemperature => self.dsp_board_temperature,
+            _ => None,
+        }
+    }
+}
+
+impl<const CMUS: usize> Measurements for bms::Status<CMUS> {
+    fn value(&self, signal: Signal) -> Option<f32> {
+        match signal {
+            Signal::StateOfCharge => self.soc_percent,
+            Signal::MinimumCellVoltage => self.minimum_voltage_cell.map(|c| c.voltage as f32),
+            Signal::MaximumCellVoltage => self.maximum_voltage_cell.map(|c| c.voltage as f32),
+            Signal::MinimumCellTemperature => {
+                self.minimum_temperature_cell.map(|c| c.temperature as f32)
+            }
+            Signal::MaximumCellTemperature => {
+                self.maximum_temperature_cell.map(|c| c.temperature as f32)
+            }
+            Signal::PackVoltage => self.pack_voltage_mv.map(|v| v as f32),
+            _ => None,
+        }
+    }
+}
+
+/// Threshold level
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// Within limits
+    #[default]
+    Normal,
+    /// Outside the warning limits
+    Warning,
+    /// Outside the fault limits
+    Fault,
+}
+
+/// Warning and fault limits of a signal
+///
+/// Unset limits are not checked.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Limits {
+    /// Fault below this value
+    pub fault_low: Option<f32>,
+    /// Warning below this value
+    pub warn_low: Option<f32>,
+    /// Warning above this value
+    pub warn_high: Option<f32>,
+    /// Fault above this value
+    pub fault_high: Option<f32>,
+}
+
+impl Limits {
+    /// Upper limits only.
+    pub fn above(warn_high: f32, fault_high: f32) -> Self {
+        Self {
+            warn_high: Some(warn_high),
+            fault_high: Some(fault_high),
+            ..Default::default()
+        }
+    }
+
+    /// Lower limits only.
+    pub fn below(warn_low: f32, fault_low: f32) -> Self {
+        Self {
+            warn_low: Some(warn_low),
+            fault_low: Some(fault_low),
+            ..Default::default()
+        }
+    }
+
+    /// Both lower and upper limits.
+    pub fn between(warn_low: f32, fault_low: f32, warn_high: f32, fault_high: f32) -> Self {
+        Self {
+            fault_low: Some(fault_low),
+            warn_low: Some(warn_low),
+            warn_high: Some(warn_high),
+            fault_high: Some(fault_high),
+        }
+    }
+
+    /// Level of a value against these limits.
+    ///
+    /// Non-finite values are faults.
+    pub fn level(&self, value: f32) -> Level {
+        let below = |limit: Option<f32>| limit.is_some_and(|limit| value < limit);
+        let above = |limit: Option<f32>| limit.is_some_and(|limit| value > limit);
+
+        if !value.is_finite() || below(self.fault_low) || above(self.fault_high) {
+            Level::Fault
+        } else if below(self.warn_low) || above(self.warn_high) {
+            Level::Warning
+        } else {
+            Level::Normal
+        }
+    }
+}
+
+/// Limits of one signal and its latest level
+#[derive(Debug, Clone, Copy)]
+pub struct Threshold {
+    /// Checked signal
+    pub signal: Signal,
+    /// Limits
+    pub limits: Limits,
+    level: Level,
+}
+
+impl Threshold {
+    /// Create a new threshold at the normal level.
+    pub fn new(signal: Signal, limits: Limits) -> Self {
+        Self {
+            signal,
+            limits,
+            level: Level::Normal,
+        }
+    }
+
+    /// Latest level.
+    pub fn level(&self) -> Level {
+        self.level
+    }
+}
+
+/// Table of signal thresholds
+#[derive(Debug, Clone, Copy)]
+pub struct Thresholds<const N: usize> {
+    thresholds: [Threshold; N],
+}
+
+impl<const N: usize> Thresholds<N> {
+    /// Create a new table.
+    pub fn new(thresholds: [Threshold; N]) -> Self {
+        Self { thresholds }
+    }
+
+    /// Evaluate the signals known by `source` and return the highest level
+    /// across the whole table.
+    ///
+    /// Signals unknown by `source` keep their previous level, so a table can
+    /// be evaluated against several devices in turn.
+    pub fn evaluate(&mut self, source: &impl Measurements) -> Level {
+        for threshold in self.thresholds.iter_mut() {
+            if let Some(value) = source.value(threshold.signal) {
+                let level = threshold.limits.level(value);
+
+                if level != threshold.level {
+                    log_warn!("{:?} {:?} at {}", threshold.signal, level, value);
+                }
+
+                threshold.level = level;
+            }
+        }
+
+        self.level()
+    }
+
+    /// Highest level across the whole table.
+    pub fn level(&self) -> Level {
+        self.thresholds
+            .iter()
+            .map(|threshold| threshold.level)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Thresholds at or above `level`.
+    pub fn at_least(&self, level: Level) -> impl Iterator<Item = &Threshold> {
+        self.thresholds
+            .iter()
+            .filter(move |threshold| threshold.level >= level)
+    }
+
+    /// All thresholds.
+    pub fn iter(&self) -> impl Iterator<Item = &Threshold> {
+        self.thresholds.iter()
+    }
+}
@@ -0,0 +1,131 @@
+//! Traction control
+//!
+//! Detects driven wheel slip and backs off the commanded current until grip
+//! is regained. Slip is detected by comparing the WaveSculptor vehicle
+//! velocity (derived from the driven wheel) against a reference speed from
+//! an undriven wheel or GPS when one is available, and otherwise by the
+//! driven wheel accelerating faster than the vehicle plausibly can.
+//!
+//! Note that the WaveSculptor slip speed broadcast is the electrical slip of
+//! an induction motor, not wheel slip, and is not used here.
+
+use crate::driver_controls::DriveCommand;
+use crate::wavesculptor::Status;
+
+/// Traction control configuration
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Slip ratio above which the wheel is considered slipping
+    pub slip_threshold: f32,
+    /// Driven wheel acceleration above which the wheel is considered
+    /// slipping when no reference speed is available, in m/s²
+    pub max_acceleration: f32,
+    /// Speed below which the slip ratio is not evaluated in m/s
+    pub min_speed: f32,
+    /// Rate the current factor is reduced while slipping per second
+    pub backoff_rate: f32,
+    /// Rate the current factor recovers once grip is regained per second
+    pub recovery_rate: f32,
+    /// Lowest current factor traction control will command
+    pub min_factor: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            slip_threshold: 0.15,
+            max_acceleration: 4.0,
+            min_speed: 2.0,
+            backoff_rate: 4.0,
+            recovery_rate: 1.0,
+            min_factor: 0.2,
+        }
+    }
+}
+
+/// Traction control
+#[derive(Debug, Clone, Copy)]
+pub struct TractionControl {
+    config: Config,
+    factor: f32,
+    slipping: bool,
+    last_velocity: Option<f32>,
+    last_update_ms: Option<u32>,
+}
+
+impl TractionControl {
+    /// Create a new traction control instance.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            factor: 1.0,
+            slipping: false,
+            last_velocity: None,
+            last_update_ms: None,
+        }
+    }
+
+    /// Is wheel slip currently detected?
+    pub fn is_slipping(&self) -> bool {
+        self.slipping
+    }
+
+    /// Fraction of the drive current currently allowed.
+    pub fn factor(&self) -> f32 {
+        self.factor
+    }
+
+    /// Update slip detection and return the allowed current fraction.
+    ///
+    /// `reference_speed` is the true vehicle speed in m/s if known.
+    pub fn update(&mut self, now_ms: u32, status: &Status, reference_speed: Option<f32>) -> f32 {
+        let dt = match self.last_update_ms {
+            Some(last) => now_ms.wrapping_sub(last) as f32 / 1000.0,
+            None => 0.0,
+        };
+        self.last_update_ms = Some(now_ms);
+
+        let velocity = status.vehicle_velocity.map(f32::abs);
+
+        self.slipping = match (velocity, reference_speed.map(f32::abs)) {
+            (Some(velocity), Some(reference)) => {
+                reference.max(velocity) >= self.config.min_speed
+                    && (velocity - reference) / velocity.max(reference) > self.config.slip_threshold
+            }
+            (Some(velocity), None) => match self.last_velocity {
+                Some(last) if dt > 0.0 => (velocity - last) / dt > self.config.max_acceleration,
+                _ => false,
+            },
+            _ => false,
+        };
+        self.last_velocity = velocity;
+
+        self.factor = if self.slipping {
+            (self.factor - self.config.backoff_rate * dt).max(self.config.min_factor)
+        } else {
+            (self.factor + self.config.recovery_rate * dt).min(1.0)
+        };
+
+        self.factor
+    }
+
+    /// Update slip detection and limit a drive command.
+    ///
+    /// Only the current of driving commands is reduced, regenerative braking
+    /// commands pass through.
+    pub fn apply(
+        &mut self,
+        now_ms: u32,
+        command: DriveCommand,
+        status: &Status,
+        reference_speed: Option<f32>,
+    ) -> DriveCommand {
+        let factor = self.update(now_ms, status, reference_speed);
+
+        if command.velocity_rpm == 0.0 {
+            command
+        } else {
+            DriveCommand::new(command.velocity_rpm, command.current_percent * factor)
+        }
+    }
+}
@@ -0,0 +1,46 @@
+//! Frame transmission
+//!
+//! Command-producing modules send their frames through a [`TxSink`] rather
+//! than a particular CAN peripheral, so any of them can be unit tested by
+//! inspecting the frames emitted. The `test_support` feature provides a
+//! recording sink and implements the trait for its mock bus.
+//!
+//! HALs without bxcan can instead use the `_raw` variants of the frame
+//! builders, returning [`RawParts`]. The charger's extended identifier
+//! frames have no raw form.
+
+use bxcan::{Frame, Id};
+
+/// Standard identifier, data zero padded to 8 bytes and data length
+pub type RawParts = (u16, [u8; 8], usize);
+
+/// Split a standard data frame into its raw parts.
+///
+/// Returns `None` for extended identifier and remote frames.
+pub fn raw_parts(frame: &Frame) -> Option<RawParts> {
+    let (Id::Standard(id), Some(bytes)) = (frame.id(), frame.data()) else {
+        return None;
+    };
+
+    let mut data = [0; 8];
+    data[..bytes.len()].copy_from_slice(bytes);
+
+    Some((id.as_raw(), data, bytes.len()))
+}
+
+/// Raw parts of a frame built by this crate, always a standard data frame.
+pub(crate) fn standard_parts(frame: &Frame) -> RawParts {
+    raw_parts(frame).expect("builders produce standard data frames")
+}
+
+/// Destination of transmitted frames
+pub trait TxSink {
+    /// Queue `frame` for transmission.
+    fn transmit(&mut self, frame: &Frame) -> Result<(), &'static str>;
+}
+
+impl<T: TxSink + ?Sized> TxSink for &mut T {
+    fn transmit(&mut self, frame: &Frame) -> Result<(), &'static str> {
+        (**self).transmit(frame)
+    }
+}
@@ -0,0 +1,84 @@
+//! Torque vectoring
+//!
+//! Splits a total drive current request between the left and right motors of
+//! a twin WaveSculptor vehicle, biasing current towards the outside wheel to
+//! help the vehicle turn.
+
+use crate::driver_controls::DriveCommand;
+
+/// Torque vectoring configuration
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Fraction of the total current moved between sides at full yaw demand
+    pub gain: f32,
+    /// Steering angle corresponding to full yaw demand in radians
+    pub max_steering_angle: f32,
+    /// Maximum current for either motor in percent
+    pub max_current_percent: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            gain: 0.3,
+            max_steering_angle: 0.5,
+            max_current_percent: 100.0,
+        }
+    }
+}
+
+/// Left and right motor commands
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Split {
+    /// Left motor command
+    pub left: DriveCommand,
+    /// Right motor command
+    pub right: DriveCommand,
+}
+
+/// Torque vectoring helper
+#[derive(Debug, Clone, Copy)]
+pub struct TorqueVectoring {
+    config: Config,
+}
+
+impl TorqueVectoring {
+    /// Create a new torque vectoring helper.
+    pub fn new(config: Config) -> Self {
+        Self { config }
+    }
+
+    /// Split a total current request using a yaw demand between -1 and 1.
+    ///
+    /// A positive yaw demand turns the vehicle left by biasing current
+    /// towards the right motor.
+    pub fn split_yaw(&self, total_current_percent: f32, yaw: f32) -> Split {
+        let yaw = if yaw.is_nan() {
+            0.0
+        } else {
+            yaw.clamp(-1.0, 1.0)
+        };
+        let bias = self.config.gain * yaw;
+        let max = self.config.max_current_percent;
+
+        let left = (total_current_percent * (1.0 - bias)).clamp(0.0, max);
+        let right = (total_current_percent * (1.0 + bias)).clamp(0.0, max);
+
+        Split {
+            left: DriveCommand::torque(left),
+            right: DriveCommand::torque(right),
+        }
+    }
+
+    /// Split a total current request using the steering angle in radians,
+    /// positive to the left.
+    pub fn split_steering(&self, total_current_percent: f32, steering_angle: f32) -> Split {
+        let yaw = if self.config.max_steering_angle > 0.0 {
+            steering_angle / self.config.max_steering_angle
+        } else {
+            0.0
+        };
+
+        self.split_yaw(total_current_percent, yaw)
+    }
+}
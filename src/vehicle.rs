@@ -0,0 +1,199 @@
+//! Whole vehicle
+//!
+//! Composes the devices on a vehicle's bus behind a single
+//! [`receive`](Vehicle::receive), and combines their status into a
+//! [`Snapshot`] of the energy flow for telemetry and strategy.
+//!
+//! Each device ignores frames outside its identifier window, so every frame
+//! can be passed to the vehicle.
+
+use bxcan::Frame;
+
+use crate::bms::{Bmu, CMU_CAPACITY_DEFAULT};
+use crate::driver_controls::DriverControls;
+use crate::mppt::Mppt;
+use crate::wavesculptor::WaveSculptor;
+
+/// Combined vehicle status
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Snapshot {
+    /// Solar array power into the MPPTs in watts
+    pub array_power: Option<f32>,
+    /// Solar power delivered by the MPPTs in watts
+    pub solar_power: Option<f32>,
+    /// Motor controller bus power in watts, negative while regenerating
+    pub motor_power: Option<f32>,
+    /// Pack state of charge as a fraction, averaged across BMUs
+    pub soc: Option<f32>,
+    /// Pack voltage in volts, averaged across BMUs
+    pub pack_voltage: Option<f32>,
+    /// Pack current in amps, summed across BMUs, positive while discharging
+    pub pack_current: Option<f32>,
+    /// Vehicle speed in km/h, averaged across motor controllers
+    pub speed_kmh: Option<f32>,
+}
+
+/// Vehicle bus devices
+///
+/// Holds `MOTORS` motor controllers, `BMUS` battery management units
+/// tracking up to `CMUS` CMUs each, `MPPTS` solar trackers and optional
+/// driver controls.
+pub struct Vehicle<
+    const MOTORS: usize = 1,
+    const BMUS: usize = 1,
+    const MPPTS: usize = 0,
+    const CMUS: usize = CMU_CAPACITY_DEFAULT,
+> {
+    motors: [WaveSculptor; MOTORS],
+    bmus: [Bmu<CMUS>; BMUS],
+    mppts: [Mppt; MPPTS],
+    driver_controls: Option<DriverControls>,
+}
+
+impl<const MOTORS: usize, const BMUS: usize, const MPPTS: usize, const CMUS: usize>
+    Vehicle<MOTORS, BMUS, MPPTS, CMUS>
+{
+    /// Create a vehicle from its devices.
+    pub fn new(
+        motors: [WaveSculptor; MOTORS],
+        bmus: [Bmu<CMUS>; BMUS],
+        mppts: [Mppt; MPPTS],
+    ) -> Self {
+        Self {
+            motors,
+            bmus,
+            mppts,
+            driver_controls: None,
+        }
+    }
+
+    /// Decode the frames broadcast by a driver controls unit.
+    pub fn with_driver_controls(mut self, driver_controls: DriverControls) -> Self {
+        self.driver_controls = Some(driver_controls);
+        self
+    }
+
+    /// Motor controllers.
+    pub fn motors(&self) -> &[WaveSculptor; MOTORS] {
+        &self.motors
+    }
+
+    /// Battery management units.
+    pub fn bmus(&self) -> &[Bmu<CMUS>; BMUS] {
+        &self.bmus
+    }
+
+    /// Solar trackers.
+    pub fn mppts(&self) -> &[Mppt; MPPTS] {
+        &self.mppts
+    }
+
+    /// Driver controls.
+    pub fn driver_controls(&self) -> Option<&DriverControls> {
+        self.driver_controls.as_ref()
+    }
+
+    /// Decode a frame and apply it to every device it belongs to.
+    ///
+    /// Every device sees the frame even if one fails to decode it, and the
+    /// last error is returned.
+    pub fn receive(&mut self, frame: Frame) -> Result<(), &'static str> {
+        self.dispatch(
+            frame,
+            |motor, frame| motor.receive(frame),
+            |bmu, frame| bmu.receive(frame),
+        )
+    }
+
+    /// Decode a frame received at `now_ms` and apply it to every device it
+    /// belongs to, tracking controller resets and silent CMUs.
+    ///
+    /// See [`receive`](Self::receive).
+    pub fn receive_at(&mut self, now_ms: u32, frame: Frame) -> Result<(), &'static str> {
+        self.dispatch(
+            frame,
+            |motor, frame| motor.receive_at(now_ms, frame),
+            |bmu, frame| bmu.receive_at(now_ms, frame),
+        )
+    }
+
+    fn dispatch(
+        &mut self,
+        frame: Frame,
+        motor: impl Fn(&mut WaveSculptor, Frame) -> Result<(), &'static str>,
+        bmu: impl Fn(&mut Bmu<CMUS>, Frame) -> Result<(), &'static str>,
+    ) -> Result<(), &'static str> {
+        let mut result = Ok(());
+
+        for device in &mut self.motors {
+            result = motor(device, frame.clone()).and(result);
+        }
+
+        for device in &mut self.bmus {
+            result = bmu(device, frame.clone()).and(result);
+        }
+
+        for device in &mut self.mppts {
+            result = device.receive(frame.clone()).and(result);
+        }
+
+        if let Some(device) = &mut self.driver_controls {
+            result = device.receive(frame).and(result);
+        }
+
+        result
+    }
+
+    /// Combined status of every device.
+    ///
+    /// Each value is `None` until every device contributing to it has
+    /// reported it.
+    pub fn snapshot(&self) -> Snapshot {
+        let motors = self.motors.each_ref().map(|motor| motor.status());
+        let bmus = self.bmus.each_ref().map(|bmu| bmu.status());
+        let mppts = self.mppts.each_ref().map(|mppt| mppt.status());
+
+        Snapshot {
+            array_power: sum(mppts.iter().map(|mppt| mppt.input_power())),
+            solar_power: sum(mppts.iter().map(|mppt| mppt.output_power())),
+            motor_power: sum(motors
+                .iter()
+                .map(|motor| Some(motor.bus_voltage? * motor.bus_current?))),
+            soc: mean(bmus.iter().map(|bmu| bmu.soc_percent)),
+            pack_voltage: mean(
+                bmus.iter()
+                    .map(|bmu| bmu.pack_voltage_mv.map(|voltage| voltage as f32 / 1000.0)),
+            ),
+            pack_current: sum(bmus
+                .iter()
+                .map(|bmu| bmu.pack_current_ma.map(|current| current as f32 / 1000.0))),
+            speed_kmh: mean(motors.iter().map(|motor| motor.vehicle_speed_kmh())),
+        }
+    }
+}
+
+/// Sum of the values, `None` if any is unknown or there are none.
+fn sum(values: impl Iterator<Item = Option<f32>>) -> Option<f32> {
+    let mut count = 0;
+    let mut total = 0.0;
+
+    for value in values {
+        total += value?;
+        count += 1;
+    }
+
+    (count > 0).then_some(total)
+}
+
+/// Mean of the values, `None` if any is unknown or there are none.
+fn mean(values: impl Iterator<Item = Option<f32>>) -> Option<f32> {
+    let mut count = 0;
+    let mut total = 0.0;
+
+    for value in values {
+        total += value?;
+        count += 1;
+    }
+
+    (count > 0).then(|| total / count as f32)
+}
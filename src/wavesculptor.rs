@@ -1,11 +1,19 @@
 //! WaveScultpor 22 and 200 motor driver.
 //!
-//! This driver is backwards compaible with Tritium WaveSculptors.
+//! This driver is backwards compaible with Tritium WaveSculptors, see
+//! [`Model`] to decode WaveSculptor 20 specific broadcasts.
+//!
+//! The only command documented in the user's manual is the active motor
+//! change (base + 0x12), see [`WaveSculptor::active_motor_change`].
+//! Reading and writing the controller configuration uses an undocumented
+//! protocol and is left to the vendor configuration tool.
 
 use bitflags::bitflags;
 use bxcan::{Frame, Id, StandardId};
 use num_complex::Complex32;
 
+use crate::tx::{standard_parts, RawParts};
+
 // broadcase message identifiers normalized for base id.
 const ID_BROAD_ID: u16 = 0x00;
 const ID_BROAD_STATUS: u16 = 0x01;
@@ -17,6 +25,7 @@ const ID_BROAD_MOTOR_CURRENT: u16 = 0x06;
 const ID_BROAD_BACK_EMF: u16 = 0x07;
 const ID_BROAD_RAIL_15V: u16 = 0x08;
 const ID_BROAD_RAIL_3V3_1V9: u16 = 0x09;
+const ID_BROAD_FAN_SPEED: u16 = 0x0A;
 const ID_BROAD_TEMP_HSINK_MOTOR: u16 = 0x0B;
 const ID_BROAD_TEMP_DSP: u16 = 0x0C;
 const ID_BROAD_ODOMETER: u16 = 0x0E;
@@ -28,12 +37,18 @@ const ID_CMD_MOTOR_CHANGE: u16 = 0x12;
 /// Default base identifier
 pub static ID_BASE: u16 = 0x400;
 
+/// Number of identifiers used by a device, starting at its base identifier
+pub const ID_WINDOW: u16 = 0x20;
+
+/// Default silence before an identification broadcast indicates a reset
+const RESET_SILENCE_MS_DEFAULT: u32 = 2000;
+
 bitflags! {
     /// Error flags
     pub struct ErrorFlags: u16 {
         const HARDWARE_OVER_CURRENT       = 1 << 0;
         const SOFTWARE_OVER_CURRENT       = 1 << 1;
-        const DC_BUS_OVER_CURRENT         = 1 << 2;
+        const DC_BUS_OVER_VOLTAGE         = 1 << 2;
         const BAD_MOTOR_POSITION_SEQUENCE = 1 << 3;
         const WATCHDOG_CAUSED_LAST_RESET  = 1 << 4;
         const CONFIG_READ_ERROR           = 1 << 5;
@@ -43,6 +58,12 @@ bitflags! {
     }
 }
 
+impl ErrorFlags {
+    /// Former name of [`DC_BUS_OVER_VOLTAGE`](Self::DC_BUS_OVER_VOLTAGE)
+    #[deprecated(note = "renamed to DC_BUS_OVER_VOLTAGE")]
+    pub const DC_BUS_OVER_CURRENT: Self = Self::DC_BUS_OVER_VOLTAGE;
+}
+
 bitflags! {
     /// Limit flags
     pub struct LimitFlags: u16 {
@@ -56,8 +77,124 @@ bitflags! {
     }
 }
 
+const ERROR_FLAG_DESCRIPTIONS: [(ErrorFlags, &str); 9] = [
+    (ErrorFlags::HARDWARE_OVER_CURRENT, "Hardware over current"),
+    (ErrorFlags::SOFTWARE_OVER_CURRENT, "Software over current"),
+    (ErrorFlags::DC_BUS_OVER_VOLTAGE, "DC bus over voltage"),
+    (
+        ErrorFlags::BAD_MOTOR_POSITION_SEQUENCE,
+        "Bad motor position hall sequence",
+    ),
+    (
+        ErrorFlags::WATCHDOG_CAUSED_LAST_RESET,
+        "Watchdog caused last reset",
+    ),
+    (ErrorFlags::CONFIG_READ_ERROR, "Config read error"),
+    (ErrorFlags::RAIL_15V_UVLO, "15V rail under voltage lock out"),
+    (ErrorFlags::DESATURATION_FAULT, "Desaturation fault"),
+    (ErrorFlags::MOTOR_OVER_SPEED, "Motor over speed"),
+];
+
+const LIMIT_FLAG_DESCRIPTIONS: [(LimitFlags, &str); 7] = [
+    (LimitFlags::OUTPUT_VOLTAGE_PWM, "Output voltage PWM"),
+    (LimitFlags::MOTOR_CURRENT, "Motor current"),
+    (LimitFlags::VELOCITY, "Velocity"),
+    (LimitFlags::BUS_CURRENT, "Bus current"),
+    (
+        LimitFlags::BUS_VOLTAGE_UPPER_LIMIT,
+        "Bus voltage upper limit",
+    ),
+    (
+        LimitFlags::BUS_VOLTAGE_LOWER_LIMIT,
+        "Bus voltage lower limit",
+    ),
+    (LimitFlags::TEMPERATURE, "IPM or motor temperature"),
+];
+
+impl ErrorFlags {
+    /// Human-readable descriptions of each set flag.
+    pub fn describe(&self) -> impl Iterator<Item = &'static str> {
+        let flags = *self;
+
+        ERROR_FLAG_DESCRIPTIONS
+            .into_iter()
+            .filter(move |(flag, _)| flags.contains(*flag))
+            .map(|(_, description)| description)
+    }
+}
+
+impl LimitFlags {
+    /// Human-readable descriptions of each set flag.
+    pub fn describe(&self) -> impl Iterator<Item = &'static str> {
+        let flags = *self;
+
+        LIMIT_FLAG_DESCRIPTIONS
+            .into_iter()
+            .filter(move |(flag, _)| flags.contains(*flag))
+            .map(|(_, description)| description)
+    }
+}
+
+/// Status with every broadcast received
+///
+/// Model-specific measurements such as the WaveSculptor 20 fan speed are not
+/// included.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Snapshot {
+    /// Device serial number, allocated at manufacture
+    pub serial_number: u32,
+    /// Device identifier (Tritium ID or Prohelion ID)
+    pub identifier: u32,
+    /// CAN receive error count
+    pub can_rx_error_count: u8,
+    /// CAN transmit error count
+    pub can_tx_error_count: u8,
+    /// Active motor identifier
+    pub active_motor: u16,
+    /// Error flags
+    pub error_flags: ErrorFlags,
+    /// Limit flags
+    pub limit_flags: LimitFlags,
+    /// Bus current in amps
+    pub bus_current: f32,
+    /// Bus voltage in volts
+    pub bus_voltage: f32,
+    /// Vehicle velocity in meters/second
+    pub vehicle_velocity: f32,
+    /// Motor velocity in RPM
+    pub motor_velocity: f32,
+    /// Phase C current in amps RMS
+    pub phase_c_current: f32,
+    /// Phase B current in amps RMS
+    pub phase_b_current: f32,
+    /// Motor voltage vector in volts
+    pub motor_voltage_vector: Complex32,
+    /// Motor current vector in amps
+    pub motor_current_vector: Complex32,
+    /// Motor back-EMF vector in volts
+    pub motor_back_emf_vector: Complex32,
+    /// 15V rail measurement in volts
+    pub rail_15v: f32,
+    /// 3.3V rail measurement in volts
+    pub rail_3v3: f32,
+    /// 1.9V rail measurement in volts
+    pub rail_1v9: f32,
+    /// Heat-sink temperature in degrees celcius
+    pub heatsink_temperature: f32,
+    /// Motor temperature in degrees celcius
+    pub motor_temperature: f32,
+    /// DSP board temperature in degrees celcius
+    pub dsp_board_temperature: f32,
+    /// DC bus amp-hours measurement
+    pub bus_amp_hours: f32,
+    /// Odometer (distance traveled since last reset) in meters.
+    pub odometer: f32,
+    /// Slip speed measurement in Hz
+    pub slip_speed: f32,
+}
+
 /// Status
-#[derive(Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub struct Status {
     /// Device serial number, allocated at manufacture
     pub serial_number: Option<u32>,
@@ -109,169 +246,791 @@ pub struct Status {
     pub odometer: Option<f32>,
     /// Slip speed measurement in Hz
     pub slip_speed: Option<f32>,
+    /// Fan speed in RPM (WaveSculptor 20 only)
+    pub fan_speed: Option<f32>,
+    /// Fan drive in percent (WaveSculptor 20 only)
+    pub fan_drive: Option<f32>,
+}
+
+impl Status {
+    /// Vehicle velocity in kilometres/hour.
+    pub fn vehicle_speed_kmh(&self) -> Option<f32> {
+        self.vehicle_velocity.map(|velocity| velocity * 3.6)
+    }
+
+    /// Vehicle velocity in miles/hour.
+    pub fn vehicle_speed_mph(&self) -> Option<f32> {
+        self.vehicle_velocity
+            .map(|velocity| velocity * 3.6 / 1.609_344)
+    }
+
+    /// Motor angular velocity in radians/second.
+    pub fn motor_angular_velocity(&self) -> Option<f32> {
+        self.motor_velocity
+            .map(|velocity| velocity * core::f32::consts::TAU / 60.0)
+    }
+
+    /// Forget every received value.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Merge another status into this one, taking every value present in
+    /// `other` as newer.
+    pub fn merge(&mut self, other: &Status) {
+        self.serial_number = other.serial_number.or(self.serial_number);
+        self.identifier = other.identifier.or(self.identifier);
+        self.can_rx_error_count = other.can_rx_error_count.or(self.can_rx_error_count);
+        self.can_tx_error_count = other.can_tx_error_count.or(self.can_tx_error_count);
+        self.active_motor = other.active_motor.or(self.active_motor);
+        self.error_flags = other.error_flags.or(self.error_flags);
+        self.limit_flags = other.limit_flags.or(self.limit_flags);
+        self.bus_current = other.bus_current.or(self.bus_current);
+        self.bus_voltage = other.bus_voltage.or(self.bus_voltage);
+        self.vehicle_velocity = other.vehicle_velocity.or(self.vehicle_velocity);
+        self.motor_velocity = other.motor_velocity.or(self.motor_velocity);
+        self.phase_c_current = other.phase_c_current.or(self.phase_c_current);
+        self.phase_b_current = other.phase_b_current.or(self.phase_b_current);
+        self.motor_voltage_vector = other.motor_voltage_vector.or(self.motor_voltage_vector);
+        self.motor_current_vector = other.motor_current_vector.or(self.motor_current_vector);
+        self.motor_back_emf_vector = other.motor_back_emf_vector.or(self.motor_back_emf_vector);
+        self.rail_15v = other.rail_15v.or(self.rail_15v);
+        self.rail_3v3 = other.rail_3v3.or(self.rail_3v3);
+        self.rail_1v9 = other.rail_1v9.or(self.rail_1v9);
+        self.heatsink_temperature = other.heatsink_temperature.or(self.heatsink_temperature);
+        self.motor_temperature = other.motor_temperature.or(self.motor_temperature);
+        self.dsp_board_temperature = other.dsp_board_temperature.or(self.dsp_board_temperature);
+        self.bus_amp_hours = other.bus_amp_hours.or(self.bus_amp_hours);
+        self.odometer = other.odometer.or(self.odometer);
+        self.slip_speed = other.slip_speed.or(self.slip_speed);
+        self.fan_speed = other.fan_speed.or(self.fan_speed);
+        self.fan_drive = other.fan_drive.or(self.fan_drive);
+    }
+
+    /// Snapshot of the status once every broadcast has been received at
+    /// least once.
+    pub fn try_complete(&self) -> Option<Snapshot> {
+        Some(Snapshot {
+            serial_number: self.serial_number?,
+            identifier: self.identifier?,
+            can_rx_error_count: self.can_rx_error_count?,
+            can_tx_error_count: self.can_tx_error_count?,
+            active_motor: self.active_motor?,
+            error_flags: self.error_flags?,
+            limit_flags: self.limit_flags?,
+            bus_current: self.bus_current?,
+            bus_voltage: self.bus_voltage?,
+            vehicle_velocity: self.vehicle_velocity?,
+            motor_velocity: self.motor_velocity?,
+            phase_c_current: self.phase_c_current?,
+            phase_b_current: self.phase_b_current?,
+            motor_voltage_vector: self.motor_voltage_vector?,
+            motor_current_vector: self.motor_current_vector?,
+            motor_back_emf_vector: self.motor_back_emf_vector?,
+            rail_15v: self.rail_15v?,
+            rail_3v3: self.rail_3v3?,
+            rail_1v9: self.rail_1v9?,
+            heatsink_temperature: self.heatsink_temperature?,
+            motor_temperature: self.motor_temperature?,
+            dsp_board_temperature: self.dsp_board_temperature?,
+            bus_amp_hours: self.bus_amp_hours?,
+            odometer: self.odometer?,
+            slip_speed: self.slip_speed?,
+        })
+    }
+
+    /// Estimated shaft torque in Nm from the quadrature (Q) axis motor
+    /// current and the motor torque constant `kt` in Nm/A.
+    ///
+    /// See also [`MotorProfile::kt`](crate::motor_profile::MotorProfile::kt).
+    pub fn estimated_torque(&self, kt: f32) -> Option<f32> {
+        self.motor_current_vector.map(|current| current.im * kt)
+    }
+
+    /// Motor voltage, current and back-EMF vectors, once all three have been
+    /// received.
+    pub fn motor_vectors(&self) -> Option<MotorVectors> {
+        Some(MotorVectors {
+            voltage: self.motor_voltage_vector?,
+            current: self.motor_current_vector?,
+            back_emf: self.motor_back_emf_vector?,
+        })
+    }
+
+    /// Update the status with a decoded broadcast message.
+    pub fn apply(&mut self, message: WaveSculptorMessage) {
+        match message {
+            WaveSculptorMessage::Identification {
+                identifier,
+                serial_number,
+            } => {
+                self.identifier = Some(identifier);
+                self.serial_number = Some(serial_number);
+            }
+            WaveSculptorMessage::Status {
+                can_rx_error_count,
+                can_tx_error_count,
+                active_motor,
+                error_flags,
+                limit_flags,
+            } => {
+                self.can_rx_error_count = Some(can_rx_error_count);
+                self.can_tx_error_count = Some(can_tx_error_count);
+                self.active_motor = Some(active_motor);
+                self.error_flags = Some(error_flags);
+                self.limit_flags = Some(limit_flags);
+            }
+            WaveSculptorMessage::BusMeasurement { voltage, current } => {
+                self.bus_voltage = Some(voltage);
+                self.bus_current = Some(current);
+            }
+            WaveSculptorMessage::Velocity { motor, vehicle } => {
+                self.motor_velocity = Some(motor);
+                self.vehicle_velocity = Some(vehicle);
+            }
+            WaveSculptorMessage::PhaseCurrent { phase_b, phase_c } => {
+                self.phase_b_current = Some(phase_b);
+                self.phase_c_current = Some(phase_c);
+            }
+            WaveSculptorMessage::MotorVoltageVector(vector) => {
+                self.motor_voltage_vector = Some(vector)
+            }
+            WaveSculptorMessage::MotorCurrentVector(vector) => {
+                self.motor_current_vector = Some(vector)
+            }
+            WaveSculptorMessage::BackEmfVector(vector) => self.motor_back_emf_vector = Some(vector),
+            WaveSculptorMessage::Rail15v(voltage) => self.rail_15v = Some(voltage),
+            WaveSculptorMessage::Rails { rail_1v9, rail_3v3 } => {
+                self.rail_1v9 = Some(rail_1v9);
+                self.rail_3v3 = Some(rail_3v3);
+            }
+            WaveSculptorMessage::Temperature { motor, heatsink } => {
+                self.motor_temperature = Some(motor);
+                self.heatsink_temperature = Some(heatsink);
+            }
+            WaveSculptorMessage::DspTemperature(temperature) => {
+                self.dsp_board_temperature = Some(temperature)
+            }
+            WaveSculptorMessage::Odometer {
+                odometer,
+                bus_amp_hours,
+            } => {
+                self.odometer = Some(odometer);
+                self.bus_amp_hours = Some(bus_amp_hours);
+            }
+            WaveSculptorMessage::SlipSpeed(slip_speed) => self.slip_speed = Some(slip_speed),
+            WaveSculptorMessage::FanSpeed { speed, drive } => {
+                self.fan_speed = Some(speed);
+                self.fan_drive = Some(drive);
+            }
+        }
+    }
+}
+
+/// Motor voltage, current and back-EMF vectors
+///
+/// The vectors are in the rotor reference frame, the real part being the
+/// direct (D) axis and the imaginary part the quadrature (Q) axis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MotorVectors {
+    /// Motor voltage vector in volts
+    pub voltage: Complex32,
+    /// Motor current vector in amps
+    pub current: Complex32,
+    /// Motor back-EMF vector in volts
+    pub back_emf: Complex32,
+}
+
+impl MotorVectors {
+    /// Motor voltage magnitude in volts.
+    pub fn voltage_magnitude(&self) -> f32 {
+        self.voltage.norm()
+    }
+
+    /// Motor current magnitude in amps.
+    pub fn current_magnitude(&self) -> f32 {
+        self.current.norm()
+    }
+
+    /// Back-EMF magnitude in volts.
+    pub fn back_emf_magnitude(&self) -> f32 {
+        self.back_emf.norm()
+    }
+
+    /// Motor voltage phase angle from the D axis in radians.
+    pub fn voltage_phase(&self) -> f32 {
+        self.voltage.arg()
+    }
+
+    /// Motor current phase angle from the D axis in radians.
+    pub fn current_phase(&self) -> f32 {
+        self.current.arg()
+    }
+
+    /// Power factor, the cosine of the angle between the voltage and current
+    /// vectors.
+    ///
+    /// Returns `None` when either vector is zero.
+    pub fn power_factor(&self) -> Option<f32> {
+        let magnitudes = self.voltage_magnitude() * self.current_magnitude();
+
+        (magnitudes > 0.0).then(|| (self.voltage * self.current.conj()).re / magnitudes)
+    }
+}
+
+/// Broadcast messages sent by a WaveSculptor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WaveSculptorMessage {
+    /// Device identifier and serial number
+    Identification { identifier: u32, serial_number: u32 },
+    /// CAN error counts, active motor and flags
+    Status {
+        can_rx_error_count: u8,
+        can_tx_error_count: u8,
+        active_motor: u16,
+        error_flags: ErrorFlags,
+        limit_flags: LimitFlags,
+    },
+    /// Bus voltage in volts and current in amps
+    BusMeasurement { voltage: f32, current: f32 },
+    /// Motor velocity in RPM and vehicle velocity in meters/second
+    Velocity { motor: f32, vehicle: f32 },
+    /// Phase currents in amps RMS
+    PhaseCurrent { phase_b: f32, phase_c: f32 },
+    /// Motor voltage vector in volts
+    MotorVoltageVector(Complex32),
+    /// Motor current vector in amps
+    MotorCurrentVector(Complex32),
+    /// Motor back-EMF vector in volts
+    BackEmfVector(Complex32),
+    /// 15V rail measurement in volts
+    Rail15v(f32),
+    /// 1.9V and 3.3V rail measurements in volts
+    Rails { rail_1v9: f32, rail_3v3: f32 },
+    /// Motor and heat-sink temperatures in degrees celcius
+    Temperature { motor: f32, heatsink: f32 },
+    /// DSP board temperature in degrees celcius
+    DspTemperature(f32),
+    /// Distance traveled in meters and DC bus amp-hours
+    Odometer { odometer: f32, bus_amp_hours: f32 },
+    /// Slip speed in Hz
+    SlipSpeed(f32),
+    /// Fan speed in RPM and fan drive in percent (WaveSculptor 20 only)
+    FanSpeed { speed: f32, drive: f32 },
+}
+
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn f32_at(data: &[u8], offset: usize) -> f32 {
+    f32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+/// Offset and payload of a data frame within the window starting at
+/// `base_id`, `None` for frames belonging to other devices.
+fn window(frame: &Frame, base_id: u16) -> Option<(u16, &[u8])> {
+    let (Id::Standard(id), Some(data)) = (frame.id(), frame.data()) else {
+        return None;
+    };
+
+    let offset = id.as_raw().wrapping_sub(base_id);
+
+    (offset < ID_WINDOW).then_some((offset, &data[..]))
+}
+
+/// WaveSculptor model
+///
+/// Selects the model-specific broadcasts to decode. Only the WaveSculptor 20
+/// fan speed broadcast differs, every model shares the same scaling.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Model {
+    /// Tritium WaveSculptor 20, which also broadcasts fan speed
+    Ws20,
+    /// WaveSculptor 22
+    #[default]
+    Ws22,
+    /// WaveSculptor 200, decoded the same as the WaveSculptor 22
+    Ws200,
+}
+
+/// Broadcast decoding mode
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeMode {
+    /// Require the full 8 byte payload documented in the manual
+    #[default]
+    Strict,
+    /// Also accept 4 byte frames for single-value broadcasts (15V rail and
+    /// slip speed), as sent by some firmware revisions, reading the value
+    /// from the first 4 bytes
+    ///
+    /// Note the 15V rail identifier is shared with the MPPTs, so short MPPT
+    /// frames will be decoded as rail measurements in this mode.
+    Lenient,
+}
+
+/// Decode the payload of a broadcast message.
+///
+/// `offset` is the frame identifier relative to the device base identifier.
+/// Returns `None` for identifiers that are not WaveSculptor broadcasts.
+pub fn decode_ws(offset: u16, data: &[u8]) -> Result<Option<WaveSculptorMessage>, &'static str> {
+    decode_ws_with(offset, data, Model::default(), DecodeMode::Strict)
+}
+
+/// Decode the payload of a broadcast message from the given model using the
+/// given mode.
+///
+/// See [`decode_ws`].
+pub fn decode_ws_with(
+    offset: u16,
+    data: &[u8],
+    model: Model,
+    mode: DecodeMode,
+) -> Result<Option<WaveSculptorMessage>, &'static str> {
+    if model == Model::Ws20 && offset == ID_BROAD_FAN_SPEED {
+        if data.len() < 8 {
+            return Err("broadcast frame too short");
+        }
+
+        return Ok(Some(WaveSculptorMessage::FanSpeed {
+            speed: f32_at(data, 0),
+            drive: f32_at(data, 4),
+        }));
+    }
+
+    if mode == DecodeMode::Lenient && (4..8).contains(&data.len()) {
+        match offset {
+            ID_BROAD_RAIL_15V => return Ok(Some(WaveSculptorMessage::Rail15v(f32_at(data, 0)))),
+            ID_BROAD_SLIP_SPEED => {
+                return Ok(Some(WaveSculptorMessage::SlipSpeed(f32_at(data, 0))))
+            }
+            _ => {}
+        }
+    }
+
+    // Extra care when handling these as the identifiers are shared with the
+    // MPPTs: data is only for the WaveSculptor if the frame has 8 bytes.
+    if matches!(
+        offset,
+        ID_BROAD_RAIL_15V | ID_BROAD_RAIL_3V3_1V9 | ID_BROAD_TEMP_HSINK_MOTOR
+    ) && data.len() < 8
+    {
+        return Ok(None);
+    }
+
+    let required = match offset {
+        ID_BROAD_TEMP_DSP => 4,
+        ID_BROAD_ID..=ID_BROAD_RAIL_3V3_1V9
+        | ID_BROAD_TEMP_HSINK_MOTOR
+        | ID_BROAD_ODOMETER
+        | ID_BROAD_SLIP_SPEED => 8,
+        _ => return Ok(None),
+    };
+
+    if data.len() < required {
+        return Err("broadcast frame too short");
+    }
+
+    let message = match offset {
+        ID_BROAD_ID => WaveSculptorMessage::Identification {
+            identifier: u32_at(data, 0),
+            serial_number: u32_at(data, 4),
+        },
+
+        ID_BROAD_STATUS => WaveSculptorMessage::Status {
+            can_rx_error_count: data[0],
+            can_tx_error_count: data[1],
+            active_motor: u16::from_le_bytes(data[2..4].try_into().unwrap()),
+            error_flags: ErrorFlags::from_bits_truncate(u16::from_le_bytes(
+                data[4..6].try_into().unwrap(),
+            )),
+            limit_flags: LimitFlags::from_bits_truncate(u16::from_le_bytes(
+                data[6..8].try_into().unwrap(),
+            )),
+        },
+
+        ID_BROAD_BUS_MEAS => WaveSculptorMessage::BusMeasurement {
+            voltage: f32_at(data, 0),
+            current: f32_at(data, 4),
+        },
+
+        ID_BROAD_VELOCITY => WaveSculptorMessage::Velocity {
+            motor: f32_at(data, 0),
+            vehicle: f32_at(data, 4),
+        },
+
+        ID_BROAD_PHASE_CURRENT => WaveSculptorMessage::PhaseCurrent {
+            phase_b: f32_at(data, 0),
+            phase_c: f32_at(data, 4),
+        },
+
+        ID_BROAD_MOTOR_VOLTAGE => WaveSculptorMessage::MotorVoltageVector(Complex32::new(
+            f32_at(data, 4),
+            f32_at(data, 0),
+        )),
+
+        ID_BROAD_MOTOR_CURRENT => WaveSculptorMessage::MotorCurrentVector(Complex32::new(
+            f32_at(data, 4),
+            f32_at(data, 0),
+        )),
+
+        ID_BROAD_BACK_EMF => {
+            WaveSculptorMessage::BackEmfVector(Complex32::new(f32_at(data, 4), f32_at(data, 0)))
+        }
+
+        ID_BROAD_RAIL_15V => WaveSculptorMessage::Rail15v(f32_at(data, 4)),
+
+        ID_BROAD_RAIL_3V3_1V9 => WaveSculptorMessage::Rails {
+            rail_1v9: f32_at(data, 0),
+            rail_3v3: f32_at(data, 4),
+        },
+
+        ID_BROAD_TEMP_HSINK_MOTOR => WaveSculptorMessage::Temperature {
+            motor: f32_at(data, 0),
+            heatsink: f32_at(data, 4),
+        },
+
+        ID_BROAD_TEMP_DSP => WaveSculptorMessage::DspTemperature(f32_at(data, 0)),
+
+        ID_BROAD_ODOMETER => WaveSculptorMessage::Odometer {
+            odometer: f32_at(data, 0),
+            bus_amp_hours: f32_at(data, 4),
+        },
+
+        ID_BROAD_SLIP_SPEED => WaveSculptorMessage::SlipSpeed(f32_at(data, 4)),
+
+        _ => return Ok(None),
+    };
+
+    Ok(Some(message))
 }
 
 pub struct WaveSculptor {
     base_id: u16,
+    model: Model,
+    decode_mode: DecodeMode,
+    reset_silence_ms: u32,
 
     status: Status,
+    last_receive_ms: Option<u32>,
+    silence_seen: bool,
+    reset_count: u32,
 }
 
 impl WaveSculptor {
     /// Create a new WaveSculptor instance.
-    pub fn new(base_id: u16) -> Self {
+    ///
+    /// Fails if the device's identifier window starting at `base_id` does
+    /// not fit within the standard identifier range.
+    pub fn new(base_id: u16) -> Result<Self, &'static str> {
+        if StandardId::new(base_id.saturating_add(ID_WINDOW - 1)).is_none() {
+            return Err("identifier window exceeds standard identifier range");
+        }
+
+        Ok(Self::unchecked(base_id))
+    }
+
+    fn unchecked(base_id: u16) -> Self {
         Self {
             base_id,
+            model: Model::default(),
+            decode_mode: DecodeMode::default(),
+            reset_silence_ms: RESET_SILENCE_MS_DEFAULT,
             status: Status {
                 ..Default::default()
             },
+            last_receive_ms: None,
+            silence_seen: false,
+            reset_count: 0,
         }
     }
 
+    /// Set the device model, selecting model-specific broadcasts.
+    pub fn with_model(mut self, model: Model) -> Self {
+        self.model = model;
+        self
+    }
+
+    /// Set how broadcast frames are decoded.
+    pub fn with_decode_mode(mut self, mode: DecodeMode) -> Self {
+        self.decode_mode = mode;
+        self
+    }
+
+    /// Set the silence after which an identification broadcast is treated
+    /// as a controller reset by [`receive_at`](Self::receive_at).
+    pub fn with_reset_silence_ms(mut self, silence_ms: u32) -> Self {
+        self.reset_silence_ms = silence_ms;
+        self
+    }
+
     /// Get the current status state of the device
-    pub fn status(&mut self) -> Status {
+    pub fn status(&self) -> Status {
         self.status
     }
 
+    /// Number of controller resets detected by [`receive_at`](Self::receive_at).
+    pub fn reset_count(&self) -> u32 {
+        self.reset_count
+    }
+
+    /// Time of the latest broadcast noted by [`receive_at`](Self::receive_at).
+    pub fn last_receive_ms(&self) -> Option<u32> {
+        self.last_receive_ms
+    }
+
+    /// Decode a broadcast frame and apply it to the device status.
     pub fn receive(&mut self, frame: Frame) -> Result<(), &'static str> {
-        match frame.id() {
-            Id::Standard(id) => {
-                // is within range
-                if id.as_raw() >= self.base_id {
-                    // is there some data in this frame?
-                    if let Some(data) = frame.data() {
-                        // normalized identifier
-                        match id.as_raw() - self.base_id {
-                            ID_BROAD_ID => {
-                                self.status.identifier =
-                                    Some(u32::from_le_bytes(data[0..4].try_into().unwrap()));
-                                self.status.serial_number =
-                                    Some(u32::from_le_bytes(data[4..8].try_into().unwrap()));
-                            }
-
-                            ID_BROAD_STATUS => {
-                                self.status.can_rx_error_count = Some(data[0]);
-                                self.status.can_tx_error_count = Some(data[1]);
-                                self.status.active_motor =
-                                    Some(u16::from_le_bytes(data[2..4].try_into().unwrap()));
-                                self.status.error_flags = ErrorFlags::from_bits(
-                                    u16::from_le_bytes(data[4..6].try_into().unwrap()),
-                                );
-                                self.status.limit_flags = LimitFlags::from_bits(
-                                    u16::from_le_bytes(data[6..8].try_into().unwrap()),
-                                );
-                            }
-
-                            ID_BROAD_BUS_MEAS => {
-                                self.status.bus_voltage =
-                                    Some(f32::from_le_bytes(data[0..4].try_into().unwrap()));
-                                self.status.bus_current =
-                                    Some(f32::from_le_bytes(data[4..8].try_into().unwrap()));
-                            }
-
-                            ID_BROAD_VELOCITY => {
-                                self.status.motor_velocity =
-                                    Some(f32::from_le_bytes(data[0..4].try_into().unwrap()));
-                                self.status.vehicle_velocity =
-                                    Some(f32::from_le_bytes(data[4..8].try_into().unwrap()));
-                            }
-
-                            ID_BROAD_PHASE_CURRENT => {
-                                self.status.phase_b_current =
-                                    Some(f32::from_le_bytes(data[0..4].try_into().unwrap()));
-                                self.status.phase_c_current =
-                                    Some(f32::from_le_bytes(data[4..8].try_into().unwrap()));
-                            }
-
-                            ID_BROAD_MOTOR_VOLTAGE => {
-                                let i = f32::from_le_bytes(data[0..4].try_into().unwrap());
-                                let r = f32::from_le_bytes(data[4..8].try_into().unwrap());
-                                self.status.motor_voltage_vector = Some(Complex32::new(r, i));
-                            }
-
-                            ID_BROAD_MOTOR_CURRENT => {
-                                let i = f32::from_le_bytes(data[0..4].try_into().unwrap());
-                                let r = f32::from_le_bytes(data[4..8].try_into().unwrap());
-                                self.status.motor_current_vector = Some(Complex32::new(r, i));
-                            }
-
-                            ID_BROAD_BACK_EMF => {
-                                let i = f32::from_le_bytes(data[0..4].try_into().unwrap());
-                                let r = f32::from_le_bytes(data[4..8].try_into().unwrap());
-                                self.status.motor_back_emf_vector = Some(Complex32::new(r, i));
-                            }
-
-                            ID_BROAD_RAIL_15V => {
-                                // Extra care when handling these as this ID is shared with the MPPTs
-                                // Data is for wave sculptor if it has at least 9 bytes
-                                if data.len() >= 8 {
-                                    if let Ok(rail_15v) = data[4..8].try_into() {
-                                        self.status.rail_15v = Some(f32::from_le_bytes(rail_15v));
-                                    }
-                                }
-                            }
-
-                            ID_BROAD_RAIL_3V3_1V9 => {
-                                if data.len() >= 8 {
-                                    if let Ok(rail_1v9) = data[0..4].try_into() {
-                                        self.status.rail_1v9 = Some(f32::from_le_bytes(rail_1v9));
-                                    }
-                                    if let Ok(rail_3v3) = data[4..8].try_into() {
-                                        self.status.rail_3v3 = Some(f32::from_le_bytes(rail_3v3));
-                                    }
-                                }
-                            }
-
-                            ID_BROAD_TEMP_HSINK_MOTOR => {
-                                if data.len() >= 8 {
-                                    if let Ok(motor_temperature) = data[0..4].try_into() {
-                                        self.status.motor_temperature = Some(f32::from_le_bytes(motor_temperature));
-                                    }
-                                    if let Ok(heatsink_temperature) = data[4..8].try_into() {
-                                        self.status.heatsink_temperature = Some(f32::from_le_bytes(heatsink_temperature));
-                                    }
-                                }
-                            }
-
-                            ID_BROAD_TEMP_DSP => {
-                                if data.len() >= 4 {
-                                    if let Ok(dsp_board_temperature) = data[0..4].try_into() {
-                                        self.status.dsp_board_temperature = Some(f32::from_le_bytes(dsp_board_temperature));
-                                    }
-                                }
-                            }
-
-                            ID_BROAD_ODOMETER => {
-                                self.status.odometer =
-                                    Some(f32::from_le_bytes(data[0..4].try_into().unwrap()));
-                                self.status.bus_amp_hours =
-                                    Some(f32::from_le_bytes(data[4..8].try_into().unwrap()));
-                            }
-
-                            ID_BROAD_SLIP_SPEED => {
-                                self.status.slip_speed =
-                                    Some(f32::from_le_bytes(data[4..8].try_into().unwrap()));
-                            }
-
-                            _ => {}
-                        }
-                    }
-                }
-            }
-            Id::Extended(_) => {}
+        if let Some(message) = self.decode(&frame)? {
+            self.status.apply(message);
+        }
+
+        Ok(())
+    }
+
+    /// Decode a broadcast frame received at `now_ms` and apply it to the
+    /// device status.
+    ///
+    /// A silence of at least the reset silence followed by an identification
+    /// broadcast means the controller restarted, so the status is cleared
+    /// before applying it to avoid trusting values from before the reset.
+    /// Other broadcasts may arrive between the silence and the
+    /// identification broadcast.
+    pub fn receive_at(&mut self, now_ms: u32, frame: Frame) -> Result<(), &'static str> {
+        if let Some(message) = self.decode(&frame)? {
+            self.apply_at(now_ms, message);
         }
 
         Ok(())
     }
 
+    fn apply_at(&mut self, now_ms: u32, message: WaveSculptorMessage) {
+        if self
+            .last_receive_ms
+            .is_some_and(|last| now_ms.wrapping_sub(last) >= self.reset_silence_ms)
+        {
+            self.silence_seen = true;
+        }
+
+        if self.silence_seen && matches!(message, WaveSculptorMessage::Identification { .. }) {
+            self.silence_seen = false;
+            self.status.clear();
+            self.reset_count = self.reset_count.wrapping_add(1);
+        }
+
+        self.last_receive_ms = Some(now_ms);
+        self.status.apply(message);
+    }
+
+    fn decode(&self, frame: &Frame) -> Result<Option<WaveSculptorMessage>, &'static str> {
+        match window(frame, self.base_id) {
+            Some((offset, data)) => self.decode_offset(offset, data),
+            None => Ok(None),
+        }
+    }
+
+    fn decode_offset(
+        &self,
+        offset: u16,
+        data: &[u8],
+    ) -> Result<Option<WaveSculptorMessage>, &'static str> {
+        let message = decode_ws_with(offset, data, self.model, self.decode_mode);
+
+        match &message {
+            Err(error) => log_warn!(
+                "WaveSculptor frame {:#05x}: {}",
+                self.base_id + offset,
+                error
+            ),
+            Ok(None) => log_debug!(
+                "WaveSculptor frame {:#05x} not decoded",
+                self.base_id + offset
+            ),
+            Ok(Some(_)) => {}
+        }
+
+        message
+    }
+
     /// Change the active motor profile.
     ///
     /// `motor` must be between 0 and 9 (inclusive).
-    pub fn active_motor_change(self, motor: u8) -> Frame {
+    pub fn active_motor_change(&self, motor: u8) -> Frame {
         assert!(motor <= 9);
 
         let id = StandardId::new(self.base_id + ID_CMD_MOTOR_CHANGE).unwrap();
 
         Frame::new_data(id, [0, motor, b'A', b'C', b'T', b'M', b'O', b'T'])
     }
+
+    /// Raw parts of [`active_motor_change`](Self::active_motor_change), for
+    /// HALs without bxcan.
+    pub fn active_motor_change_raw(&self, motor: u8) -> RawParts {
+        standard_parts(&self.active_motor_change(motor))
+    }
+
+    /// Decode an active motor change frame, returning the requested motor.
+    ///
+    /// Returns `None` for frames that are not active motor change commands.
+    pub fn decode_active_motor_change(&self, frame: &Frame) -> Result<Option<u8>, &'static str> {
+        let (Id::Standard(id), Some(data)) = (frame.id(), frame.data()) else {
+            return Ok(None);
+        };
+
+        if id.as_raw() != self.base_id + ID_CMD_MOTOR_CHANGE {
+            return Ok(None);
+        }
+
+        if data.len() < 8 || &data[2..8] != b"ACTMOT" {
+            return Err("malformed active motor change frame");
+        }
+
+        if data[1] > 9 {
+            return Err("active motor out of range");
+        }
+
+        Ok(Some(data[1]))
+    }
+}
+
+/// Progress of an active motor change
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MotorChangeState {
+    /// Waiting for a status broadcast reporting the requested motor
+    Pending,
+    /// The requested motor is active
+    Confirmed,
+    /// The requested motor was not reported active in time
+    TimedOut,
+}
+
+/// Tracks an active motor change request until the WaveSculptor confirms it
+///
+/// Create one when sending [`WaveSculptor::active_motor_change`], then update
+/// it with the device status as broadcasts arrive.
+#[derive(Debug, Clone, Copy)]
+pub struct MotorChange {
+    motor: u8,
+    requested_ms: u32,
+    timeout_ms: u32,
+    state: MotorChangeState,
+}
+
+impl MotorChange {
+    /// Start tracking a request for `motor` sent at `now_ms`.
+    pub fn new(motor: u8, now_ms: u32, timeout_ms: u32) -> Self {
+        Self {
+            motor,
+            requested_ms: now_ms,
+            timeout_ms,
+            state: MotorChangeState::Pending,
+        }
+    }
+
+    /// Requested motor.
+    pub fn motor(&self) -> u8 {
+        self.motor
+    }
+
+    /// Current progress.
+    pub fn state(&self) -> MotorChangeState {
+        self.state
+    }
+
+    /// Check the latest status for the requested motor.
+    ///
+    /// Once confirmed or timed out the result no longer changes.
+    pub fn update(&mut self, status: &Status, now_ms: u32) -> MotorChangeState {
+        if self.state == MotorChangeState::Pending {
+            if status.active_motor == Some(self.motor as u16) {
+                self.state = MotorChangeState::Confirmed;
+            } else if now_ms.wrapping_sub(self.requested_ms) >= self.timeout_ms {
+                self.state = MotorChangeState::TimedOut;
+            }
+        }
+
+        self.state
+    }
+}
+
+/// WaveSculptor with a base identifier fixed at compile time
+///
+/// The identifier window is validated when the program is compiled, so
+/// construction cannot fail. Dereferences to [`WaveSculptor`] for everything else.
+pub struct FixedWaveSculptor<const BASE: u16>(WaveSculptor);
+
+impl<const BASE: u16> FixedWaveSculptor<BASE> {
+    const WINDOW_FITS: () = assert!(
+        BASE as u32 + ID_WINDOW as u32 <= 0x800,
+        "identifier window exceeds standard identifier range"
+    );
+
+    /// Create a new instance.
+    pub fn new() -> Self {
+        let () = Self::WINDOW_FITS;
+
+        Self(WaveSculptor::unchecked(BASE))
+    }
+
+    /// See [`WaveSculptor::with_model`].
+    pub fn with_model(self, model: Model) -> Self {
+        Self(self.0.with_model(model))
+    }
+
+    /// See [`WaveSculptor::with_decode_mode`].
+    pub fn with_decode_mode(self, mode: DecodeMode) -> Self {
+        Self(self.0.with_decode_mode(mode))
+    }
+
+    /// See [`WaveSculptor::with_reset_silence_ms`].
+    pub fn with_reset_silence_ms(self, silence_ms: u32) -> Self {
+        Self(self.0.with_reset_silence_ms(silence_ms))
+    }
+
+    /// Decode a broadcast frame and apply it to the device status.
+    ///
+    /// The identifier window is resolved from `BASE` at compile time.
+    pub fn receive(&mut self, frame: Frame) -> Result<(), &'static str> {
+        if let Some(message) = self.decode(&frame)? {
+            self.0.status.apply(message);
+        }
+
+        Ok(())
+    }
+
+    /// See [`WaveSculptor::receive_at`].
+    pub fn receive_at(&mut self, now_ms: u32, frame: Frame) -> Result<(), &'static str> {
+        if let Some(message) = self.decode(&frame)? {
+            self.0.apply_at(now_ms, message);
+        }
+
+        Ok(())
+    }
+
+    fn decode(&self, frame: &Frame) -> Result<Option<WaveSculptorMessage>, &'static str> {
+        match window(frame, BASE) {
+            Some((offset, data)) => self.0.decode_offset(offset, data),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<const BASE: u16> Default for FixedWaveSculptor<BASE> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const BASE: u16> core::ops::Deref for FixedWaveSculptor<BASE> {
+    type Target = WaveSculptor;
+
+    fn deref(&self) -> &WaveSculptor {
+        &self.0
+    }
+}
+
+impl<const BASE: u16> core::ops::DerefMut for FixedWaveSculptor<BASE> {
+    fn deref_mut(&mut self) -> &mut WaveSculptor {
+        &mut self.0
+    }
 }
@@ -0,0 +1,164 @@
+use bxcan::{Frame, StandardId};
+use phln::black_box::{BlackBox, RawFrame, Record, Storage, RECORD_LEN};
+use phln::thresholds::Signal;
+use phln::wavesculptor::Status;
+
+/// Storage held in memory, optionally losing power part way through a write
+struct Memory {
+    bytes: Vec<u8>,
+    torn_after: Option<usize>,
+}
+
+impl Memory {
+    fn erased(slots: usize) -> Self {
+        Self {
+            bytes: vec![0xFF; slots * RECORD_LEN],
+            torn_after: None,
+        }
+    }
+}
+
+impl Storage for Memory {
+    fn capacity(&self) -> u32 {
+        self.bytes.len() as u32
+    }
+
+    fn read(&mut self, offset: u32, buf: &mut [u8]) -> Result<(), &'static str> {
+        let offset = offset as usize;
+        buf.copy_from_slice(&self.bytes[offset..offset + buf.len()]);
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), &'static str> {
+        let len = self
+            .torn_after
+            .map_or(data.len(), |len| len.min(data.len()));
+        let offset = offset as usize;
+        self.bytes[offset..offset + len].copy_from_slice(&data[..len]);
+        Ok(())
+    }
+}
+
+fn status(bus_voltage: f32) -> Status {
+    Status {
+        bus_voltage: Some(bus_voltage),
+        motor_temperature: Some(40.0),
+        ..Default::default()
+    }
+}
+
+fn timeline(black_box: &mut BlackBox<Memory>) -> Vec<Record> {
+    let mut out = [Record::default(); 16];
+    let len = black_box.timeline(&mut out).unwrap();
+
+    out[..len].to_vec()
+}
+
+fn sequences(black_box: &mut BlackBox<Memory>) -> Vec<u32> {
+    timeline(black_box)
+        .iter()
+        .map(|record| record.sequence)
+        .collect()
+}
+
+#[test]
+fn record_round_trip() {
+    let mut black_box = BlackBox::new(Memory::erased(4)).unwrap();
+    assert!(timeline(&mut black_box).is_empty());
+
+    let fault = Frame::new_data(StandardId::new(0x401).unwrap(), [1, 2, 3]);
+    let fault = RawFrame::from_frame(&fault).unwrap();
+
+    black_box.record(100, 0, &[&status(96.0)], None).unwrap();
+    black_box
+        .record(200, 0x5, &[&status(97.5)], Some(fault))
+        .unwrap();
+
+    let records = timeline(&mut black_box);
+    assert_eq!(records.len(), 2);
+
+    assert_eq!(records[0].sequence, 0);
+    assert_eq!(records[0].time_ms, 100);
+    assert_eq!(records[0].frame, None);
+    assert_eq!(records[0].value(Signal::BusVoltage), Some(96.0));
+
+    assert_eq!(records[1].sequence, 1);
+    assert_eq!(records[1].alarms, 0x5);
+    assert_eq!(records[1].value(Signal::BusVoltage), Some(97.5));
+    assert_eq!(records[1].value(Signal::MotorTemperature), Some(40.0));
+    assert_eq!(records[1].value(Signal::StateOfCharge), None);
+    assert_eq!(records[1].frame.unwrap().data(), [1, 2, 3]);
+}
+
+#[test]
+fn ring_keeps_the_newest_records() {
+    let mut black_box = BlackBox::new(Memory::erased(4)).unwrap();
+    assert_eq!(black_box.slots(), 4);
+
+    for i in 0..10 {
+        black_box.record(i, 0, &[&status(96.0)], None).unwrap();
+    }
+
+    assert_eq!(sequences(&mut black_box), [6, 7, 8, 9]);
+
+    let mut out = [Record::default(); 2];
+    assert_eq!(black_box.timeline(&mut out), Ok(2));
+    assert_eq!(out.map(|record| record.sequence), [8, 9]);
+
+    // reopening resumes after the newest record
+    let mut black_box = BlackBox::new(black_box.into_inner()).unwrap();
+    black_box.record(10, 0, &[&status(96.0)], None).unwrap();
+    assert_eq!(sequences(&mut black_box), [7, 8, 9, 10]);
+}
+
+#[test]
+fn torn_write_is_skipped() {
+    let mut black_box = BlackBox::new(Memory::erased(4)).unwrap();
+
+    for i in 0..4 {
+        black_box.record(i, 0, &[&status(96.0)], None).unwrap();
+    }
+
+    // power lost half way through overwriting the oldest record
+    let mut storage = black_box.into_inner();
+    storage.torn_after = Some(RECORD_LEN / 2);
+    let mut black_box = BlackBox::new(storage).unwrap();
+    black_box.record(4, 0, &[&status(96.0)], None).unwrap();
+
+    let mut storage = black_box.into_inner();
+    storage.torn_after = None;
+    let mut black_box = BlackBox::new(storage).unwrap();
+    assert_eq!(sequences(&mut black_box), [1, 2, 3]);
+
+    // the torn slot is written again with the same sequence number
+    black_box.record(5, 0, &[&status(96.0)], None).unwrap();
+    let records = timeline(&mut black_box);
+    assert_eq!(records.len(), 4);
+    assert_eq!(records[3].sequence, 4);
+    assert_eq!(records[3].time_ms, 5);
+}
+
+#[test]
+fn corrupted_record_is_skipped() {
+    let mut black_box = BlackBox::new(Memory::erased(4)).unwrap();
+
+    for i in 0..3 {
+        black_box.record(i, 0, &[&status(96.0)], None).unwrap();
+    }
+
+    let mut storage = black_box.into_inner();
+    storage.bytes[RECORD_LEN + 20] ^= 0x01;
+
+    let mut black_box = BlackBox::new(storage).unwrap();
+    assert_eq!(sequences(&mut black_box), [0, 2]);
+}
+
+#[test]
+fn storage_too_small() {
+    let storage = Memory {
+        bytes: vec![0xFF; RECORD_LEN - 1],
+        torn_after: None,
+    };
+
+    assert!(BlackBox::new(storage).is_err());
+}
@@ -0,0 +1,43 @@
+use bxcan::{Frame, StandardId};
+use phln::bms::{Bmu, Status};
+use phln::pack::{CellVoltageConfig, CellVoltageMonitor, PackAlarms};
+
+/// Status with the first four cells of CMU 0 reporting `voltages`.
+fn status(voltages: [i16; 4]) -> Status {
+    let mut bmu = Bmu::new(0x600).unwrap();
+
+    let mut data = [0u8; 8];
+    for (chunk, voltage) in data.chunks_exact_mut(2).zip(voltages) {
+        chunk.copy_from_slice(&voltage.to_le_bytes());
+    }
+
+    bmu.receive(Frame::new_data(StandardId::new(0x602).unwrap(), data))
+        .unwrap();
+
+    bmu.status()
+}
+
+#[test]
+fn cell_extremes_skip_negative_readings() {
+    let extremes = status([3600, -5, -200, 3700]).cell_extremes().unwrap();
+
+    assert_eq!(extremes.minimum_voltage.cell.number, 0);
+    assert_eq!(extremes.minimum_voltage.voltage, 3600);
+    assert_eq!(extremes.maximum_voltage.cell.number, 3);
+    assert_eq!(extremes.maximum_voltage.voltage, 3700);
+
+    // no valid reading to report
+    assert_eq!(status([-1, -5, -200, -3]).cell_extremes(), None);
+}
+
+#[test]
+fn negative_cell_reading_is_a_sensor_fault() {
+    let mut monitor: CellVoltageMonitor = CellVoltageMonitor::new(CellVoltageConfig {
+        cells_per_cmu: 4,
+        ..Default::default()
+    });
+
+    let alarms = monitor.update(&status([3600, -5, 3600, 3600]));
+
+    assert_eq!(alarms, PackAlarms::CELL_SENSOR_FAULT);
+}
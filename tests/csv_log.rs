@@ -0,0 +1,107 @@
+use std::collections::BTreeMap;
+
+use phln::csv_log::{Config, CsvLogger, FileSystem};
+use phln::wavesculptor::Status;
+
+/// Files held in memory
+#[derive(Default)]
+struct Memory {
+    files: BTreeMap<String, Vec<u8>>,
+    open: Option<String>,
+}
+
+impl FileSystem for Memory {
+    fn open(&mut self, name: &str) -> Result<u32, &'static str> {
+        self.open = Some(name.into());
+
+        Ok(self.files.entry(name.into()).or_default().len() as u32)
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), &'static str> {
+        let name = self.open.as_ref().ok_or("no file open")?;
+        self.files.get_mut(name).unwrap().extend_from_slice(data);
+
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), &'static str> {
+        self.open = None;
+
+        Ok(())
+    }
+}
+
+impl Memory {
+    fn headers(&self, name: &str) -> usize {
+        String::from_utf8_lossy(&self.files[name])
+            .lines()
+            .filter(|line| line.starts_with("time_ms"))
+            .count()
+    }
+}
+
+fn status() -> Status {
+    Status {
+        bus_voltage: Some(96.0),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn power_cycle_starts_an_empty_file() {
+    let mut logger = CsvLogger::<_, 64>::new(Config::default(), Memory::default());
+
+    for now_ms in 0..3 {
+        logger.log(now_ms, &[&status()]).unwrap();
+    }
+
+    // power lost without closing the logger
+    logger.flush().unwrap();
+    let fs = logger.into_inner();
+
+    let mut logger = CsvLogger::<_, 64>::new(Config::default(), fs);
+    logger.log(10, &[&status()]).unwrap();
+    logger.close().unwrap();
+
+    assert_eq!(logger.file_index(), 2);
+
+    let fs = logger.into_inner();
+    assert_eq!(fs.headers("LOG00000.CSV"), 1);
+    assert_eq!(fs.headers("LOG00001.CSV"), 1);
+    assert_eq!(
+        String::from_utf8_lossy(&fs.files["LOG00001.CSV"])
+            .lines()
+            .nth(1)
+            .unwrap()
+            .split(',')
+            .next(),
+        Some("10")
+    );
+}
+
+#[test]
+fn rotation_counts_existing_bytes() {
+    let config = Config {
+        max_file_bytes: 1024,
+        ..Default::default()
+    };
+
+    let mut logger = CsvLogger::<_, 64>::new(config, Memory::default());
+
+    for now_ms in 0..100 {
+        logger.log(now_ms, &[&status()]).unwrap();
+
+        // committing the file reads its length back
+        logger.sync().unwrap();
+    }
+
+    logger.close().unwrap();
+    let fs = logger.into_inner();
+
+    assert!(fs.files.len() > 1);
+
+    for (name, file) in &fs.files {
+        assert!(file.len() <= 1024, "{} is {} bytes", name, file.len());
+        assert_eq!(fs.headers(name), 1);
+    }
+}
@@ -0,0 +1,151 @@
+use phln::driver_controls::{
+    Command, DriveCommand, DriverControls, IgnitionPosition, SwitchFlags, SwitchPosition,
+    ID_BASE_DEFAULT, TORQUE_MODE_VELOCITY_RPM,
+};
+use phln::gear::Gear;
+use phln::wavesculptor::WaveSculptor;
+
+fn controls() -> DriverControls {
+    DriverControls::new(ID_BASE_DEFAULT).unwrap()
+}
+
+#[test]
+fn drive_round_trip() {
+    let dc = controls();
+
+    for command in [
+        DriveCommand::new(1234.5, 37.5),
+        DriveCommand::new(-800.0, 100.0),
+        DriveCommand::velocity(3000.0),
+        DriveCommand::regen(25.0),
+        DriveCommand::new(0.0, 0.0),
+    ] {
+        let frame = dc.drive(command);
+
+        assert_eq!(dc.decode(&frame), Ok(Some(Command::Drive(command))));
+    }
+}
+
+#[test]
+fn torque_mode_round_trip() {
+    let dc = controls();
+
+    let forward = dc.decode(&dc.drive_torque(75.0));
+    assert_eq!(
+        forward,
+        Ok(Some(Command::Drive(DriveCommand::new(
+            TORQUE_MODE_VELOCITY_RPM,
+            75.0
+        ))))
+    );
+
+    let reverse = dc.decode(&dc.drive_torque(-75.0));
+    assert_eq!(
+        reverse,
+        Ok(Some(Command::Drive(DriveCommand::new(
+            -TORQUE_MODE_VELOCITY_RPM,
+            75.0
+        ))))
+    );
+}
+
+#[test]
+fn power_round_trip() {
+    let dc = controls();
+
+    for percent in [0.0, 12.5, 50.0, 100.0] {
+        let frame = dc.motor_power(percent);
+
+        assert_eq!(dc.decode(&frame), Ok(Some(Command::Power(percent))));
+    }
+}
+
+#[test]
+fn builders_send_set_points_unchanged() {
+    let dc = controls();
+
+    let frame = dc.motor_drive(500.0, 0.25);
+    assert_eq!(frame.data().unwrap()[4..8], 0.25f32.to_le_bytes());
+
+    let frame = dc.motor_power(f32::NAN);
+    assert!(f32::from_le_bytes(frame.data().unwrap()[4..8].try_into().unwrap()).is_nan());
+}
+
+#[test]
+fn validated_builders() {
+    let dc = controls();
+
+    assert_eq!(
+        dc.try_motor_drive(500.0, 300.0),
+        Ok(dc.motor_drive(500.0, 100.0))
+    );
+    assert_eq!(dc.try_motor_power(-5.0), Ok(dc.motor_power(0.0)));
+
+    assert!(dc.try_motor_drive(f32::INFINITY, 50.0).is_err());
+    assert!(dc.try_drive(DriveCommand::new(0.0, f32::NAN)).is_err());
+    assert!(dc.try_motor_power(f32::NAN).is_err());
+}
+
+#[test]
+fn reset_round_trip() {
+    let dc = controls();
+
+    assert_eq!(
+        dc.decode(&dc.reset_wavesculptor()),
+        Ok(Some(Command::Reset))
+    );
+}
+
+#[test]
+fn switch_position_round_trip() {
+    let dc = controls();
+
+    for (ignition, flags) in [
+        (IgnitionPosition::Off, SwitchFlags::empty()),
+        (IgnitionPosition::Accessories, SwitchFlags::ACCESSORIES),
+        (IgnitionPosition::Run, SwitchFlags::RUN),
+        (IgnitionPosition::Start, SwitchFlags::START),
+    ] {
+        let frame = dc.switch_position(ignition);
+
+        assert_eq!(dc.decode(&frame), Ok(Some(Command::Switches(flags))));
+    }
+}
+
+#[test]
+fn switches_round_trip() {
+    let dc = controls();
+
+    let switches = SwitchPosition::new()
+        .ignition(IgnitionPosition::Run)
+        .gear(Gear::Drive)
+        .regen(true)
+        .brake(true)
+        .charge_port(true)
+        .with(SwitchFlags::SPARE_2 | SwitchFlags::CAN_FAULT, true);
+
+    let frame = dc.switches(switches);
+
+    assert_eq!(
+        dc.decode(&frame),
+        Ok(Some(Command::Switches(switches.flags())))
+    );
+
+    // every flag survives the trip
+    let all = SwitchPosition::new().with(SwitchFlags::all(), true);
+    assert_eq!(
+        dc.decode(&dc.switches(all)),
+        Ok(Some(Command::Switches(SwitchFlags::all())))
+    );
+}
+
+#[test]
+fn active_motor_change_round_trip() {
+    let ws = WaveSculptor::new(0x400).unwrap();
+
+    for motor in 0..=9 {
+        let frame = ws.active_motor_change(motor);
+
+        assert_eq!(ws.decode_active_motor_change(&frame), Ok(Some(motor)));
+    }
+}
@@ -0,0 +1,82 @@
+use bxcan::{Frame, StandardId};
+use phln::bms::FixedBmu;
+use phln::driver_controls::{Command, DriveCommand, DriverControls, FixedDriverControls};
+use phln::wavesculptor::{DecodeMode, FixedWaveSculptor};
+
+fn frame(id: u16, data: &[u8]) -> Frame {
+    Frame::new_data(
+        StandardId::new(id).unwrap(),
+        bxcan::Data::new(data).unwrap(),
+    )
+}
+
+#[test]
+fn fixed_wavesculptor_forwards_builders() {
+    let rail = 15.5f32.to_le_bytes();
+
+    let mut strict = FixedWaveSculptor::<0x400>::new();
+    let _ = strict.receive(frame(0x408, &rail));
+    assert_eq!(strict.status().rail_15v, None);
+
+    let mut lenient = FixedWaveSculptor::<0x400>::new()
+        .with_decode_mode(DecodeMode::Lenient)
+        .with_reset_silence_ms(100);
+
+    lenient.receive_at(0, frame(0x408, &rail)).unwrap();
+    assert_eq!(lenient.status().rail_15v, Some(15.5));
+    assert_eq!(lenient.last_receive_ms(), Some(0));
+
+    // identification after the configured silence is a reset
+    lenient.receive_at(100, frame(0x400, &[0; 8])).unwrap();
+    assert_eq!(lenient.reset_count(), 1);
+    assert_eq!(lenient.status().rail_15v, None);
+}
+
+#[test]
+fn fixed_wavesculptor_ignores_other_devices() {
+    let mut ws = FixedWaveSculptor::<0x400>::new();
+
+    ws.receive(frame(0x3FF, &[0xFF; 8])).unwrap();
+    ws.receive(frame(0x420, &[0xFF; 8])).unwrap();
+
+    assert_eq!(ws.status(), FixedWaveSculptor::<0x400>::new().status());
+}
+
+#[test]
+fn fixed_bmu_forwards_builders() {
+    let mut bmu = FixedBmu::<0x600>::new().with_cmu_timeout_ms(50);
+
+    // CMU 0 status
+    bmu.receive_at(0, frame(0x601, &[0; 8])).unwrap();
+    bmu.receive_at(10, frame(0x600, &[0; 8])).unwrap();
+    assert_eq!(bmu.last_heartbeat_ms(), Some(10));
+
+    assert_eq!(bmu.check_cmus(49), 0);
+    assert_eq!(bmu.check_cmus(50), 1);
+
+    // outside the window
+    bmu.receive_at(60, frame(0x700, &[0; 8])).unwrap();
+    assert_eq!(bmu.last_heartbeat_ms(), Some(10));
+}
+
+#[test]
+fn fixed_driver_controls_receive() {
+    let builder = DriverControls::new(0x500).unwrap();
+    let mut dc = FixedDriverControls::<0x500>::new();
+
+    let drive = builder.drive(DriveCommand::velocity(1500.0));
+    assert_eq!(
+        dc.decode(&drive),
+        Ok(Some(Command::Drive(DriveCommand::velocity(1500.0))))
+    );
+
+    dc.receive_at(20, drive).unwrap();
+    assert_eq!(dc.status().drive, Some(DriveCommand::velocity(1500.0)));
+    assert_eq!(dc.last_receive_ms(), Some(20));
+
+    // another unit's commands
+    let other = DriverControls::new(0x520).unwrap();
+    dc.receive_at(30, other.motor_power(50.0)).unwrap();
+    assert_eq!(dc.status().bus_current_percent, None);
+    assert_eq!(dc.last_receive_ms(), Some(20));
+}
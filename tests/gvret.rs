@@ -0,0 +1,152 @@
+use bxcan::{Data, ExtendedId, Frame, Id, StandardId};
+use phln::gvret::{encode_frame, BusConfig, Gvret, Output, MAX_FRAME_LEN};
+
+fn frames() -> Vec<Frame> {
+    let mut frames = Vec::new();
+
+    for len in 0..=8 {
+        let data: Vec<u8> = (0..len).map(|i| 0xA0 + i).collect();
+        let data = Data::new(&data).unwrap();
+
+        frames.push(Frame::new_data(
+            StandardId::new(0x400 + len as u16).unwrap(),
+            data,
+        ));
+        frames.push(Frame::new_data(
+            ExtendedId::new(0x1ABC_DE00 + len as u32).unwrap(),
+            data,
+        ));
+    }
+
+    frames.push(Frame::new_data(StandardId::MAX, [0xFF; 8]));
+    frames.push(Frame::new_data(ExtendedId::MAX, [0xFF; 8]));
+
+    frames
+}
+
+/// Frame as the host sends it to be transmitted.
+fn host_frame(frame: &Frame) -> Vec<u8> {
+    let id = match frame.id() {
+        Id::Standard(id) => id.as_raw() as u32,
+        Id::Extended(id) => id.as_raw() | 1 << 31,
+    };
+    let data = frame.data().unwrap();
+
+    let mut bytes = vec![0xF1, 0x00];
+    bytes.extend_from_slice(&id.to_le_bytes());
+    bytes.push(0);
+    bytes.push(data.len() as u8);
+    bytes.extend_from_slice(data);
+    bytes.push(0);
+
+    bytes
+}
+
+/// Frame decoded as the host reads a captured frame.
+fn captured(bytes: &[u8]) -> (u32, Frame) {
+    assert_eq!(bytes[..2], [0xF1, 0x00]);
+
+    let timestamp_us = u32::from_le_bytes(bytes[2..6].try_into().unwrap());
+    let raw = u32::from_le_bytes(bytes[6..10].try_into().unwrap());
+    let len = (bytes[10] & 0x0F) as usize;
+    assert_eq!(bytes[10] >> 4, 0, "first bus");
+    assert_eq!(bytes.len(), 12 + len);
+
+    let id: Id = if raw & 1 << 31 != 0 {
+        ExtendedId::new(raw & !(1 << 31)).unwrap().into()
+    } else {
+        StandardId::new(raw as u16).unwrap().into()
+    };
+
+    let data = Data::new(&bytes[11..11 + len]).unwrap();
+
+    (timestamp_us, Frame::new_data(id, data))
+}
+
+fn receive(gvret: &mut Gvret, bytes: &[u8]) -> Vec<Output> {
+    bytes
+        .iter()
+        .filter_map(|byte| gvret.receive(*byte, 1234))
+        .collect()
+}
+
+#[test]
+fn captured_frame_round_trip() {
+    let mut buf = [0; MAX_FRAME_LEN];
+
+    for frame in frames() {
+        let len = encode_frame(0xDEAD_BEEF, &frame, &mut buf).unwrap();
+
+        assert_eq!(captured(&buf[..len]), (0xDEAD_BEEF, frame));
+    }
+
+    let remote = Frame::new_remote(StandardId::new(0x400).unwrap(), 0);
+    assert!(encode_frame(0, &remote, &mut buf).is_err());
+
+    let frame = Frame::new_data(StandardId::new(0x400).unwrap(), [0; 8]);
+    assert!(encode_frame(0, &frame, &mut buf[..19]).is_err());
+}
+
+#[test]
+fn host_frame_round_trip() {
+    let mut gvret = Gvret::new(BusConfig::default());
+
+    for frame in frames() {
+        assert_eq!(
+            receive(&mut gvret, &host_frame(&frame)),
+            [Output::Transmit(frame)]
+        );
+    }
+
+    // frames for the second bus are dropped, and parsing carries on
+    let frame = Frame::new_data(StandardId::new(0x123).unwrap(), [1, 2]);
+    let mut second_bus = host_frame(&frame);
+    second_bus[6] = 1;
+    assert!(receive(&mut gvret, &second_bus).is_empty());
+    assert_eq!(
+        receive(&mut gvret, &host_frame(&frame)),
+        [Output::Transmit(frame)]
+    );
+}
+
+#[test]
+fn host_commands() {
+    let mut gvret = Gvret::new(BusConfig::default());
+    assert!(!gvret.is_binary());
+
+    assert!(receive(&mut gvret, &[0xE7, 0xE7]).is_empty());
+    assert!(gvret.is_binary());
+
+    let reply = |outputs: Vec<Output>| match outputs.as_slice() {
+        [Output::Reply(reply)] => reply.as_bytes().to_vec(),
+        other => panic!("expected a reply, got {:?}", other),
+    };
+
+    assert_eq!(
+        reply(receive(&mut gvret, &[0xF1, 0x01])),
+        [0xF1, 0x01, 0xD2, 0x04, 0, 0]
+    );
+    assert_eq!(reply(receive(&mut gvret, &[0xF1, 0x0C])), [0xF1, 0x0C, 1]);
+    assert_eq!(
+        reply(receive(&mut gvret, &[0xF1, 0x09])),
+        [0xF1, 0x09, 0xDE, 0xAD]
+    );
+
+    // 250 kbit/s, listen only
+    let value: u32 = 250_000 | 1 << 31 | 1 << 30 | 1 << 29;
+    let mut setup = vec![0xF1, 0x05];
+    setup.extend_from_slice(&value.to_le_bytes());
+    setup.extend_from_slice(&[0; 4]);
+
+    let config = BusConfig {
+        bitrate: 250_000,
+        enabled: true,
+        listen_only: true,
+    };
+    assert_eq!(receive(&mut gvret, &setup), [Output::Configure(config)]);
+    assert_eq!(gvret.bus_config(), config);
+
+    let params = reply(receive(&mut gvret, &[0xF1, 0x06]));
+    assert_eq!(params[2], 0x11);
+    assert_eq!(params[3..7], 250_000u32.to_le_bytes());
+}
@@ -0,0 +1,47 @@
+use phln::bms::{Cell, CellWithVoltage, Status};
+use phln::health::{Config, StateOfHealth};
+
+fn status(minimum_mv: u16, maximum_mv: u16, current_ma: i32) -> Status {
+    let cell = |voltage| {
+        Some(CellWithVoltage {
+            cell: Cell { cmu: 0, number: 0 },
+            voltage,
+        })
+    };
+
+    Status {
+        minimum_voltage_cell: cell(minimum_mv),
+        maximum_voltage_cell: cell(maximum_mv),
+        pack_current_ma: Some(current_ma),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn discharging_at_full_voltage_is_not_a_full_charge() {
+    let mut soh = StateOfHealth::new(Config::default());
+
+    // pulling 10 A straight after charging, cells still at the full voltage
+    soh.update(0, &status(4100, 4150, 10_000));
+
+    // run the pack empty, then charge it until the current tapers off
+    soh.update(3_600_000, &status(2900, 3300, 10_000));
+    soh.update(3_600_001, &status(4100, 4150, -1_000));
+
+    // the cycle never started at a full charge, so isn't measured
+    assert_eq!(soh.cycles(), 0);
+    assert_eq!(soh.state_of_health(), None);
+}
+
+#[test]
+fn full_charge_at_taper_current() {
+    let mut soh = StateOfHealth::new(Config::default());
+
+    soh.update(0, &status(4100, 4150, -1_000));
+    soh.update(1, &status(4000, 4100, 10_000));
+    soh.update(3_600_001, &status(2900, 3300, 10_000));
+    soh.update(3_600_002, &status(4100, 4150, -1_000));
+
+    assert_eq!(soh.cycles(), 1);
+    assert!(soh.capacity().is_some());
+}
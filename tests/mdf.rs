@@ -0,0 +1,217 @@
+#![cfg(feature = "std")]
+
+use phln::black_box::RawFrame;
+use phln::mdf::{export_frames, export_snapshots};
+use phln::thresholds::{Measurements, Signal};
+use phln::wavesculptor::Status;
+
+/// Block read back from an exported file
+struct Block<'a> {
+    id: &'a [u8],
+    links: Vec<u64>,
+    data: &'a [u8],
+}
+
+fn block(file: &[u8], address: u64) -> Block<'_> {
+    let address = address as usize;
+    assert_eq!(address % 8, 0, "block at {} is not aligned", address);
+    assert_eq!(&file[address..address + 2], b"##");
+
+    let u64_at = |offset: usize| u64::from_le_bytes(file[offset..offset + 8].try_into().unwrap());
+
+    let len = u64_at(address + 8) as usize;
+    let link_count = u64_at(address + 16) as usize;
+    let links = (0..link_count)
+        .map(|i| u64_at(address + 24 + 8 * i))
+        .collect();
+
+    Block {
+        id: &file[address + 2..address + 4],
+        links,
+        data: &file[address + 24 + 8 * link_count..address + len],
+    }
+}
+
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn u64_at(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+fn text(file: &[u8], address: u64) -> &str {
+    let block = block(file, address);
+    assert_eq!(block.id, b"TX");
+
+    let end = block.data.iter().position(|byte| *byte == 0).unwrap();
+    core::str::from_utf8(&block.data[..end]).unwrap()
+}
+
+/// Channel read back from an exported file
+#[derive(Debug)]
+struct Channel {
+    name: String,
+    data_type: u8,
+    byte_offset: usize,
+    bit_count: u32,
+    invalidation_bit: Option<u32>,
+}
+
+/// Channels, record length and records of the file's single channel group.
+fn channel_group(file: &[u8]) -> (Vec<Channel>, usize, Vec<&[u8]>) {
+    assert_eq!(&file[0..12], b"MDF     4.10");
+    assert_eq!(u16::from_le_bytes([file[28], file[29]]), 410);
+
+    let hd = block(file, 64);
+    assert_eq!(hd.id, b"HD");
+
+    let dg = block(file, hd.links[0]);
+    assert_eq!(dg.id, b"DG");
+    assert_eq!(dg.links[0], 0, "single data group");
+
+    let cg = block(file, dg.links[1]);
+    assert_eq!(cg.id, b"CG");
+    assert_eq!(cg.links[0], 0, "single channel group");
+
+    let cycles = u64_at(cg.data, 8) as usize;
+    let record_len = (u32_at(cg.data, 24) + u32_at(cg.data, 28)) as usize;
+
+    let mut channels = Vec::new();
+    let mut next = cg.links[1];
+
+    while next != 0 {
+        let cn = block(file, next);
+        assert_eq!(cn.id, b"CN");
+
+        let flags = u32_at(cn.data, 12);
+        channels.push(Channel {
+            name: text(file, cn.links[2]).into(),
+            data_type: cn.data[2],
+            byte_offset: u32_at(cn.data, 4) as usize,
+            bit_count: u32_at(cn.data, 8),
+            invalidation_bit: (flags & 1 << 1 != 0).then(|| u32_at(cn.data, 16)),
+        });
+
+        next = cn.links[0];
+    }
+
+    let dt = block(file, dg.links[2]);
+    assert_eq!(dt.id, b"DT");
+    assert_eq!(dt.data.len(), cycles * record_len);
+
+    (
+        channels,
+        record_len,
+        dt.data.chunks_exact(record_len).collect(),
+    )
+}
+
+#[test]
+fn snapshot_layout() {
+    let first = Status {
+        bus_voltage: Some(96.0),
+        ..Default::default()
+    };
+    let second = Status {
+        bus_voltage: Some(95.5),
+        motor_temperature: Some(41.0),
+        ..Default::default()
+    };
+
+    let file = export_snapshots(
+        1_700_000_000_000_000_000,
+        &[(5000, &first as &dyn Measurements), (5250, &second)],
+    );
+
+    let hd = block(&file, 64);
+    assert_eq!(u64_at(hd.data, 0), 1_700_000_000_000_000_000);
+    assert_eq!(block(&file, hd.links[1]).id, b"FH");
+
+    let (channels, record_len, records) = channel_group(&file);
+    let value_bytes = 8 + 4 * Signal::ALL.len();
+
+    assert_eq!(channels.len(), Signal::ALL.len() + 1);
+    assert_eq!(record_len, value_bytes + Signal::ALL.len().div_ceil(8));
+    assert_eq!(records.len(), 2);
+
+    assert_eq!(channels[0].name, "time");
+    assert_eq!((channels[0].byte_offset, channels[0].bit_count), (0, 64));
+
+    let times: Vec<f64> = records
+        .iter()
+        .map(|record| f64::from_le_bytes(record[0..8].try_into().unwrap()))
+        .collect();
+    assert_eq!(times, [0.0, 0.25]);
+
+    for (channel, signal) in channels[1..].iter().zip(Signal::ALL) {
+        assert_eq!(channel.name, signal.name());
+        assert_eq!(channel.bit_count, 32);
+
+        let bit = channel.invalidation_bit.unwrap() as usize;
+
+        for (record, source) in records.iter().zip([&first, &second]) {
+            let offset = channel.byte_offset;
+            let value = f32::from_le_bytes(record[offset..offset + 4].try_into().unwrap());
+            let invalid = record[value_bytes + bit / 8] & 1 << (bit % 8) != 0;
+
+            match source.value(signal) {
+                Some(expected) => {
+                    assert!(!invalid);
+                    assert_eq!(value, expected);
+                }
+                None => assert!(invalid),
+            }
+        }
+    }
+}
+
+#[test]
+fn frame_layout() {
+    let frames = [
+        (
+            100,
+            RawFrame {
+                id: 0x401,
+                len: 8,
+                data: [1, 2, 3, 4, 5, 6, 7, 8],
+            },
+        ),
+        (
+            101,
+            RawFrame {
+                id: 0x7FF,
+                len: 2,
+                data: [9, 10, 0, 0, 0, 0, 0, 0],
+            },
+        ),
+    ];
+
+    let file = export_frames(0, &frames);
+    let (channels, record_len, records) = channel_group(&file);
+
+    let names: Vec<&str> = channels
+        .iter()
+        .map(|channel| channel.name.as_str())
+        .collect();
+    assert_eq!(names, ["time", "can_id", "dlc", "data"]);
+    assert!(channels
+        .iter()
+        .all(|channel| channel.invalidation_bit.is_none()));
+    assert_eq!(record_len, 19);
+
+    let field = |record: &[u8], channel: &Channel| {
+        let offset = channel.byte_offset;
+        record[offset..offset + channel.bit_count as usize / 8].to_vec()
+    };
+
+    for (record, (time_ms, frame)) in records.iter().zip(frames) {
+        let time = f64::from_le_bytes(field(record, &channels[0]).try_into().unwrap());
+        assert_eq!(time, (time_ms - 100) as f64 / 1000.0);
+
+        assert_eq!(field(record, &channels[1]), frame.id.to_le_bytes());
+        assert_eq!(field(record, &channels[2]), [frame.len]);
+        assert_eq!(field(record, &channels[3]), frame.data);
+        assert_eq!(channels[3].data_type, 10);
+    }
+}
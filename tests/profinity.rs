@@ -0,0 +1,74 @@
+#![cfg(feature = "std")]
+
+use phln::pack::PackLimits;
+use phln::profinity::{parse, BmuConfig};
+
+// hand written in the form the parser reads, not an export from Profinity
+const SETTINGS: &str = "
+[BMU]
+; pack layout
+Number of CMUs = 4
+Cells per CMU: 8
+
+# thresholds
+Min Cell Voltage = 2.5
+Max Cell Voltage = 4.2 V
+Balance Threshold = 4150 mV
+Max Cell Temperature = 60 °C
+Max Charge Current = 45.5 A
+Max Discharge Current = 120
+Unknown Setting = 12
+";
+
+#[test]
+fn parse_settings() {
+    assert_eq!(
+        parse(SETTINGS),
+        Ok(BmuConfig {
+            cmu_count: Some(4),
+            cells_per_cmu: Some(8),
+            min_cell_voltage: Some(2500),
+            max_cell_voltage: Some(4200),
+            balance_threshold: Some(4150),
+            max_cell_temperature: Some(600),
+            max_charge_current: Some(45.5),
+            max_discharge_current: Some(120.0),
+        })
+    );
+}
+
+#[test]
+fn unitless_voltages() {
+    let voltage = |value: &str| {
+        parse(&format!("max cell voltage = {}", value))
+            .unwrap()
+            .max_cell_voltage
+    };
+
+    // volts below 10, millivolts otherwise
+    assert_eq!(voltage("4.2"), Some(4200));
+    assert_eq!(voltage("4"), Some(4000));
+    assert_eq!(voltage("4200"), Some(4200));
+    assert_eq!(voltage("4199.6"), Some(4200));
+}
+
+#[test]
+fn invalid_values() {
+    assert!(parse("max cell voltage = 4.2 A").is_err());
+    assert!(parse("max cell voltage = 70 V").is_err());
+    assert!(parse("max cell voltage = high").is_err());
+    assert!(parse("cells per cmu = 8.5").is_err());
+    assert!(parse("max charge current = 10 V").is_err());
+}
+
+#[test]
+fn unset_limits_keep_defaults() {
+    let config = parse("max cell voltage = 4.1").unwrap();
+    let limits = config.pack_limits(PackLimits::default());
+
+    assert_eq!(limits.max_cell_voltage, 4100);
+    assert_eq!(
+        limits.min_cell_voltage,
+        PackLimits::default().min_cell_voltage
+    );
+}
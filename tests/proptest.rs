@@ -0,0 +1,130 @@
+#![cfg(feature = "proptest")]
+
+use bxcan::{Frame, Id};
+use phln::bms::{self, decode_bmu, Bmu};
+use phln::driver_controls::{self, DriverControls};
+use phln::strategies::{frame_in_window, frame_outside_window};
+use phln::wavesculptor::{self, decode_ws, WaveSculptor};
+use proptest::prelude::*;
+
+const WS_BASE: u16 = 0x400;
+const BMU_BASE: u16 = 0x600;
+const DC_BASE: u16 = driver_controls::ID_BASE_DEFAULT;
+
+fn offset(frame: &Frame, base: u16) -> u16 {
+    match frame.id() {
+        Id::Standard(id) => id.as_raw() - base,
+        Id::Extended(_) => unreachable!("strategies only generate standard frames"),
+    }
+}
+
+fn data(frame: &Frame) -> &[u8] {
+    frame.data().map_or(&[], |data| &data[..])
+}
+
+proptest! {
+    #[test]
+    fn decode_ws_never_panics(frame in frame_in_window(WS_BASE, wavesculptor::ID_WINDOW)) {
+        let _ = decode_ws(offset(&frame, WS_BASE), data(&frame));
+    }
+
+    #[test]
+    fn decode_bmu_never_panics(frame in frame_in_window(BMU_BASE, bms::ID_WINDOW)) {
+        let _ = decode_bmu(offset(&frame, BMU_BASE), data(&frame));
+    }
+
+    #[test]
+    fn driver_controls_decode_never_panics(
+        frame in frame_in_window(DC_BASE, driver_controls::ID_WINDOW),
+    ) {
+        let _ = DriverControls::new(DC_BASE).unwrap().decode(&frame);
+    }
+
+    #[test]
+    fn driver_controls_decode_ignores_other_devices(
+        frame in frame_outside_window(DC_BASE, driver_controls::ID_WINDOW),
+    ) {
+        let dc = DriverControls::new(DC_BASE).unwrap();
+
+        prop_assert_eq!(dc.decode(&frame), Ok(None));
+    }
+
+    #[test]
+    fn wavesculptor_receive_never_panics(
+        frames in prop::collection::vec(frame_in_window(WS_BASE, wavesculptor::ID_WINDOW), 1..32),
+    ) {
+        let mut ws = WaveSculptor::new(WS_BASE).unwrap();
+
+        for (i, frame) in frames.into_iter().enumerate() {
+            let _ = ws.receive_at(i as u32 * 100, frame);
+        }
+    }
+
+    #[test]
+    fn wavesculptor_receive_ignores_other_devices(
+        frames in prop::collection::vec(frame_outside_window(WS_BASE, wavesculptor::ID_WINDOW), 1..32),
+    ) {
+        let mut ws = WaveSculptor::new(WS_BASE).unwrap();
+        let before = ws.status();
+
+        for frame in frames {
+            prop_assert_eq!(ws.receive(frame), Ok(()));
+        }
+
+        prop_assert_eq!(ws.status(), before);
+    }
+
+    #[test]
+    fn bmu_receive_never_panics(
+        frames in prop::collection::vec(frame_in_window(BMU_BASE, bms::ID_WINDOW), 1..32),
+    ) {
+        let mut bmu: Bmu = Bmu::new(BMU_BASE).unwrap();
+
+        for (i, frame) in frames.into_iter().enumerate() {
+            let _ = bmu.receive_at(i as u32 * 100, frame);
+        }
+    }
+
+    #[test]
+    fn bmu_receive_ignores_other_devices(
+        frames in prop::collection::vec(frame_outside_window(BMU_BASE, bms::ID_WINDOW), 1..32),
+    ) {
+        let mut bmu: Bmu = Bmu::new(BMU_BASE).unwrap();
+        let before = bmu.status();
+
+        for frame in frames {
+            prop_assert_eq!(bmu.receive(frame), Ok(()));
+        }
+
+        prop_assert_eq!(bmu.status(), before);
+    }
+
+    #[test]
+    fn driver_controls_receive_never_panics(
+        frames in prop::collection::vec(frame_in_window(DC_BASE, driver_controls::ID_WINDOW), 1..32),
+    ) {
+        let mut dc = DriverControls::new(DC_BASE).unwrap();
+
+        for (i, frame) in frames.into_iter().enumerate() {
+            let _ = dc.receive_at(i as u32 * 100, frame);
+        }
+    }
+
+    #[test]
+    fn driver_controls_receive_ignores_other_devices(
+        frames in prop::collection::vec(
+            frame_outside_window(DC_BASE, driver_controls::ID_WINDOW),
+            1..32,
+        ),
+    ) {
+        let mut dc = DriverControls::new(DC_BASE).unwrap();
+        let before = dc.status();
+
+        for frame in frames {
+            prop_assert_eq!(dc.receive(frame), Ok(()));
+        }
+
+        prop_assert_eq!(dc.status(), before);
+        prop_assert_eq!(dc.last_receive_ms(), None);
+    }
+}
@@ -0,0 +1,85 @@
+use phln::serial::{encode, max_frame_len, FrameDecoder};
+
+const MAX_PAYLOAD: usize = 600;
+
+fn frame(payload: &[u8]) -> Vec<u8> {
+    let mut buf = [0; max_frame_len(MAX_PAYLOAD)];
+    let len = encode(payload, &mut buf).unwrap();
+
+    buf[..len].to_vec()
+}
+
+fn receive<const N: usize>(
+    decoder: &mut FrameDecoder<N>,
+    bytes: &[u8],
+) -> Vec<Result<Vec<u8>, &'static str>> {
+    let mut frames = Vec::new();
+
+    for byte in bytes {
+        if let Some(result) = decoder.push(*byte) {
+            frames.push(result.map(<[u8]>::to_vec));
+        }
+    }
+
+    frames
+}
+
+#[test]
+fn round_trip_across_block_boundaries() {
+    let mut decoder = FrameDecoder::<1024>::new();
+
+    // zero-free payloads fill whole 254 byte blocks, zeros end them early
+    for fill in [|i: usize| (i % 255 + 1) as u8, |i: usize| (i % 7) as u8] {
+        for len in (0..8).chain(248..264).chain(500..516) {
+            let payload: Vec<u8> = (0..len).map(fill).collect();
+            let frame = frame(&payload);
+
+            assert!(frame.len() <= max_frame_len(len));
+            assert_eq!(
+                frame.iter().position(|byte| *byte == 0),
+                Some(frame.len() - 1)
+            );
+
+            assert_eq!(receive(&mut decoder, &frame), [Ok(payload)]);
+        }
+    }
+}
+
+#[test]
+fn resynchronises_after_corruption() {
+    let mut decoder = FrameDecoder::<1024>::new();
+
+    let first = frame(&[1, 2, 3, 0, 5]);
+    let second = frame(&[6, 0, 0, 7]);
+
+    // joining mid-frame
+    assert_eq!(
+        receive(&mut decoder, &first[2..]),
+        [Err("serial frame CRC mismatch")]
+    );
+
+    let mut corrupted = first.clone();
+    corrupted[3] ^= 0x40;
+    assert!(receive(&mut decoder, &corrupted)[0].is_err());
+
+    assert_eq!(receive(&mut decoder, &second), [Ok(vec![6, 0, 0, 7])]);
+}
+
+#[test]
+fn oversized_frames_are_dropped() {
+    let mut decoder = FrameDecoder::<16>::new();
+
+    let long = frame(&[0xAA; 32]);
+    let short = frame(&[0xBB; 4]);
+
+    assert_eq!(receive(&mut decoder, &long), [Err("serial frame too long")]);
+    assert_eq!(receive(&mut decoder, &short), [Ok(vec![0xBB; 4])]);
+}
+
+#[test]
+fn buffer_too_small() {
+    let mut buf = [0; 16];
+
+    assert!(encode(&[1; 16], &mut buf).is_err());
+    assert!(encode(&[1; 8], &mut buf).is_ok());
+}
@@ -0,0 +1,148 @@
+use phln::telemetry::{
+    self, Decoder, DeltaDecoder, DeltaEncoder, Encoder, PacketKind, SignalSet, MAX_PACKET_LEN,
+};
+use phln::thresholds::Signal;
+use phln::wavesculptor::Status;
+
+fn status(bus_voltage: f32, motor_temperature: f32) -> Status {
+    Status {
+        bus_voltage: Some(bus_voltage),
+        bus_current: Some(-12.5),
+        motor_temperature: Some(motor_temperature),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn packet_round_trip() {
+    let signals = SignalSet::empty()
+        .with(Signal::BusVoltage)
+        .with(Signal::BusCurrent)
+        .with(Signal::MotorTemperature)
+        .with(Signal::StateOfCharge);
+
+    let mut encoder = Encoder::new(signals);
+    let mut decoder = Decoder::new();
+    let mut buf = [0; MAX_PACKET_LEN];
+
+    for sequence in 0..3 {
+        let len = encoder.encode(&[&status(96.0, 45.0)], &mut buf).unwrap();
+        let packet = decoder.receive(&buf[..len]).unwrap();
+
+        assert_eq!(packet.kind, PacketKind::Full);
+        assert_eq!(packet.sequence, sequence);
+        assert_eq!(packet.value(Signal::BusVoltage), Some(96.0));
+        assert_eq!(packet.value(Signal::BusCurrent), Some(-12.5));
+        assert_eq!(packet.value(Signal::MotorTemperature), Some(45.0));
+
+        // selected but unknown to the source
+        assert_eq!(packet.value(Signal::StateOfCharge), None);
+        assert_eq!(
+            packet.signals(),
+            SignalSet::empty()
+                .with(Signal::BusVoltage)
+                .with(Signal::BusCurrent)
+                .with(Signal::MotorTemperature)
+        );
+    }
+
+    assert_eq!(decoder.lost(), 0);
+}
+
+#[test]
+fn corrupted_packets_are_rejected() {
+    let mut encoder = Encoder::new(SignalSet::all());
+    let mut buf = [0; MAX_PACKET_LEN];
+
+    let len = encoder.encode(&[&status(96.0, 45.0)], &mut buf).unwrap();
+    assert!(telemetry::decode(&buf[..len]).is_ok());
+
+    for i in 0..len {
+        let mut corrupted = buf;
+        corrupted[i] ^= 0x10;
+
+        assert!(telemetry::decode(&corrupted[..len]).is_err());
+    }
+
+    assert!(telemetry::decode(&buf[..len - 1]).is_err());
+}
+
+#[test]
+fn lost_packets_are_counted() {
+    let mut encoder = Encoder::new(SignalSet::all());
+    let mut decoder = Decoder::new();
+    let mut buf = [0; MAX_PACKET_LEN];
+
+    for sequence in 0..10 {
+        let len = encoder.encode(&[&status(96.0, 45.0)], &mut buf).unwrap();
+
+        // every third packet lost in transit
+        if sequence % 3 != 1 {
+            decoder.receive(&buf[..len]).unwrap();
+        }
+    }
+
+    assert_eq!(decoder.lost(), 3);
+}
+
+#[test]
+fn delta_round_trip() {
+    let mut encoder = DeltaEncoder::new(SignalSet::all(), 4, 0.5);
+    let mut decoder = DeltaDecoder::new();
+    let mut buf = [0; MAX_PACKET_LEN];
+
+    let full = encoder.encode(&[&status(96.0, 45.0)], &mut buf).unwrap();
+    let state = *decoder.receive(&buf[..full]).unwrap().unwrap();
+    assert_eq!(state.kind, PacketKind::Full);
+
+    // only the signal beyond the deadband is sent
+    let delta = encoder.encode(&[&status(96.2, 47.0)], &mut buf).unwrap();
+    assert_eq!(telemetry::decode(&buf[..delta]).unwrap().signals().len(), 1);
+    assert!(delta < full);
+
+    let state = decoder.receive(&buf[..delta]).unwrap().unwrap();
+    assert_eq!(state.sequence, 1);
+    assert_eq!(state.value(Signal::BusVoltage), Some(96.0));
+    assert_eq!(state.value(Signal::BusCurrent), Some(-12.5));
+    assert_eq!(state.value(Signal::MotorTemperature), Some(47.0));
+}
+
+#[test]
+fn delta_decoder_resynchronises_after_loss() {
+    let mut encoder = DeltaEncoder::new(SignalSet::all(), 3, 0.0);
+    let mut decoder = DeltaDecoder::new();
+    let mut buf = [0; MAX_PACKET_LEN];
+
+    let mut send = |decoder: &mut DeltaDecoder, temperature: f32, received: bool| {
+        let len = encoder
+            .encode(&[&status(96.0, temperature)], &mut buf)
+            .unwrap();
+
+        if received {
+            decoder.receive(&buf[..len]).unwrap().copied()
+        } else {
+            decoder.state().copied()
+        }
+    };
+
+    // deltas before the first full packet have nothing to apply to
+    assert_eq!(send(&mut decoder, 40.0, false), None);
+    assert_eq!(send(&mut decoder, 41.0, true), None);
+    assert_eq!(send(&mut decoder, 42.0, true), None);
+
+    let state = send(&mut decoder, 43.0, true).unwrap();
+    assert_eq!(state.kind, PacketKind::Full);
+
+    // losing a delta leaves the state out of date until the next full packet
+    send(&mut decoder, 44.0, false);
+    assert_eq!(send(&mut decoder, 45.0, true), None);
+    assert_eq!(decoder.lost(), 1);
+
+    let state = send(&mut decoder, 46.0, true).unwrap();
+    assert_eq!(state.kind, PacketKind::Full);
+    assert_eq!(state.value(Signal::MotorTemperature), Some(46.0));
+
+    let state = send(&mut decoder, 47.0, true).unwrap();
+    assert_eq!(state.value(Signal::MotorTemperature), Some(47.0));
+    assert_eq!(state.value(Signal::BusVoltage), Some(96.0));
+}
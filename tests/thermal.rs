@@ -0,0 +1,15 @@
+use phln::thermal::{Config, ThermalModel};
+
+#[test]
+fn out_of_order_samples_do_not_overflow() {
+    let mut model = ThermalModel::new(Config::default());
+
+    // the last sample arrives a millisecond early, wrapping the interval
+    for now_ms in [1000, 2000, 1999] {
+        let prediction = model.update_sample(now_ms, Some(5000.0), Some(60.0), Some(40.0));
+
+        assert!(prediction
+            .motor_temperature
+            .is_some_and(|temperature| temperature.is_finite()));
+    }
+}
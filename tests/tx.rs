@@ -0,0 +1,198 @@
+#![cfg(feature = "test_support")]
+
+use bxcan::Frame;
+use phln::black_box::{BlackBox, Storage};
+use phln::bms::{self, PrechargeState};
+use phln::charger::Charger;
+use phln::charging::{self, ChargeManager, ChargeState};
+use phln::cruise::{self, CruiseControl, PowerCruise};
+use phln::driver_controls::{DriveCommand, DriverControls, ID_BASE_DEFAULT};
+use phln::launch::{self, LaunchControl};
+use phln::safety::{estop_frames, estop_into};
+use phln::test_support::RecordingSink;
+use phln::wavesculptor::Status;
+
+/// Storage held in memory
+struct Memory([u8; 1024]);
+
+impl Storage for Memory {
+    fn capacity(&self) -> u32 {
+        self.0.len() as u32
+    }
+
+    fn read(&mut self, offset: u32, buf: &mut [u8]) -> Result<(), &'static str> {
+        let offset = offset as usize;
+        buf.copy_from_slice(&self.0[offset..offset + buf.len()]);
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), &'static str> {
+        let offset = offset as usize;
+        self.0[offset..offset + data.len()].copy_from_slice(data);
+        Ok(())
+    }
+}
+
+fn controls() -> DriverControls {
+    DriverControls::new(ID_BASE_DEFAULT).unwrap()
+}
+
+fn recorded<const N: usize>(sink: &RecordingSink<N>) -> Vec<Frame> {
+    sink.frames().cloned().collect()
+}
+
+#[test]
+fn cruise_control_update_into() {
+    let mut cruise = CruiseControl::new(cruise::Config::default());
+    let mut sink = RecordingSink::<4>::new();
+
+    let status = Status {
+        vehicle_velocity: Some(20.0),
+        ..Default::default()
+    };
+
+    // disabled, so the driver's input is left alone
+    assert_eq!(
+        cruise.update_into(0, &status, controls(), &mut sink),
+        Ok(None)
+    );
+    assert!(sink.is_empty());
+
+    cruise.set(&status).unwrap();
+
+    let slower = Status {
+        vehicle_velocity: Some(19.0),
+        ..status
+    };
+
+    let command = cruise
+        .update_into(0, &slower, controls(), &mut sink)
+        .unwrap()
+        .unwrap();
+
+    assert!(command.current_percent > 0.0);
+    assert_eq!(recorded(&sink), [controls().drive(command)]);
+}
+
+#[test]
+fn power_cruise_update_into() {
+    let mut cruise = PowerCruise::new(cruise::PowerConfig::default());
+    let mut sink = RecordingSink::<4>::new();
+
+    let status = Status {
+        bus_voltage: Some(100.0),
+        bus_current: Some(5.0),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        cruise.update_into(0, &status, controls(), &mut sink),
+        Ok(None)
+    );
+    assert!(sink.is_empty());
+
+    cruise.set(1000.0).unwrap();
+
+    let percent = cruise
+        .update_into(0, &status, controls(), &mut sink)
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(
+        recorded(&sink),
+        [
+            controls().drive(DriveCommand::torque(100.0)),
+            controls().motor_power(percent),
+        ]
+    );
+}
+
+#[test]
+fn launch_control_update_into() {
+    let mut launch = LaunchControl::new(launch::Config::default());
+    let mut sink = RecordingSink::<4>::new();
+
+    let status = Status {
+        vehicle_velocity: Some(0.0),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        launch.update_into(0, controls(), &status, None, &mut sink),
+        Ok(None)
+    );
+    assert!(sink.is_empty());
+
+    // armed holds zero torque
+    launch.arm(&status).unwrap();
+    assert_eq!(
+        launch.update_into(0, controls(), &status, None, &mut sink),
+        Ok(Some(DriveCommand::torque(0.0)))
+    );
+
+    launch.launch().unwrap();
+    let command = launch
+        .update_into(100, controls(), &status, None, &mut sink)
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(
+        recorded(&sink),
+        [
+            controls().drive(DriveCommand::torque(0.0)),
+            controls().drive(command),
+        ]
+    );
+}
+
+#[test]
+fn charge_manager_update_into() {
+    let mut manager = ChargeManager::new(charging::Config::default());
+    let charger = Charger::new();
+    let mut sink = RecordingSink::<4>::new();
+
+    let status: bms::Status = bms::Status {
+        precharge_state: Some(PrechargeState::Run),
+        ..Default::default()
+    };
+
+    // idle still commands the charger off
+    let idle = manager
+        .update_into(0, &status, &charger, &mut sink)
+        .unwrap();
+    assert!(!idle.command.enable);
+
+    manager.start(0).unwrap();
+    let charging = manager
+        .update_into(10, &status, &charger, &mut sink)
+        .unwrap();
+
+    assert_eq!(manager.state(), ChargeState::ConstantCurrent);
+    assert!(charging.command.enable);
+    assert_eq!(
+        recorded(&sink),
+        [charger.stop(), charger.command(charging.command)]
+    );
+}
+
+#[test]
+fn estop_into_sends_every_frame() {
+    let mut black_box = BlackBox::new(Memory([0xFF; 1024])).unwrap();
+    let mut sink = RecordingSink::<4>::new();
+
+    estop_into(0, controls(), &mut black_box, 0, &[], &mut sink).unwrap();
+
+    assert_eq!(recorded(&sink), estop_frames(controls()));
+}
+
+#[test]
+fn estop_into_attempts_every_frame() {
+    let mut black_box = BlackBox::new(Memory([0xFF; 1024])).unwrap();
+    let mut sink = RecordingSink::<1>::new();
+
+    assert_eq!(
+        estop_into(0, controls(), &mut black_box, 0, &[], &mut sink),
+        Err("recording sink full")
+    );
+    assert_eq!(recorded(&sink), estop_frames(controls())[..1]);
+}
@@ -0,0 +1,56 @@
+use bxcan::{Frame, StandardId};
+use phln::wavesculptor::{decode_ws_with, DecodeMode, ErrorFlags, LimitFlags, Model, WaveSculptor};
+
+#[test]
+fn status_flags_ignore_reserved_bits() {
+    let mut ws = WaveSculptor::new(0x400).unwrap();
+
+    // reserved bits set in both flag words
+    let frame = Frame::new_data(
+        StandardId::new(0x401).unwrap(),
+        [0, 0, 1, 0, 0x01, 0xF0, 0x08, 0xFF],
+    );
+
+    ws.receive(frame).unwrap();
+
+    let status = ws.status();
+    assert_eq!(status.error_flags, Some(ErrorFlags::HARDWARE_OVER_CURRENT));
+    assert_eq!(status.limit_flags, Some(LimitFlags::BUS_CURRENT));
+    assert_eq!(status.active_motor, Some(1));
+}
+
+#[test]
+fn ws200_decodes_as_ws22() {
+    let data = [0x00, 0x00, 0x48, 0x42, 0x00, 0x00, 0xC8, 0x42];
+
+    for offset in 0..0x20 {
+        assert_eq!(
+            decode_ws_with(offset, &data, Model::Ws200, DecodeMode::Strict),
+            decode_ws_with(offset, &data, Model::Ws22, DecodeMode::Strict)
+        );
+    }
+}
+
+#[test]
+fn reset_detected_when_status_arrives_first() {
+    let mut ws = WaveSculptor::new(0x400).unwrap().with_reset_silence_ms(100);
+
+    let id = Frame::new_data(StandardId::new(0x400).unwrap(), [0; 8]);
+    let status = Frame::new_data(StandardId::new(0x401).unwrap(), [0, 0, 2, 0, 0, 0, 0, 0]);
+
+    ws.receive_at(0, id.clone()).unwrap();
+    ws.receive_at(10, status.clone()).unwrap();
+    assert_eq!(ws.status().active_motor, Some(2));
+
+    // the restarted controller broadcasts its status before identifying
+    ws.receive_at(500, status).unwrap();
+    assert_eq!(ws.reset_count(), 0);
+
+    ws.receive_at(510, id.clone()).unwrap();
+    assert_eq!(ws.reset_count(), 1);
+    assert_eq!(ws.status().active_motor, None);
+
+    // later identification broadcasts without a silence are not resets
+    ws.receive_at(600, id).unwrap();
+    assert_eq!(ws.reset_count(), 1);
+}